@@ -0,0 +1,87 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::vector;
+use wgsparkl::models::DruckerPrager;
+use wgsparkl::{
+    models::ElasticCoefficients,
+    pipeline::MpmData,
+    solver::{BoundaryCondition, Particle, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+/// A layer of sand flowing down an infinitely long inclined chute.
+///
+/// The chute is approximated by a domain that is periodic along the X axis, with gravity
+/// tilted by the slope angle.
+pub fn chute_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let rapier_data = RapierData::default();
+    let device = device.wgpu_device();
+
+    let cell_width = 0.5;
+    let mut particles = vec![];
+    for i in 0..128 {
+        for j in 0..80 {
+            let position = vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+            let density = 2700.0;
+            let radius = cell_width / 4.0;
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density),
+                    ElasticCoefficients::from_young_modulus(10_000_000.0, 0.2),
+                )
+                .with_plasticity(DruckerPrager::new(10_000_000.0, 0.2)),
+            );
+        }
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 10;
+        app_state.gravity_factor = 1.0;
+    };
+
+    // The chute’s bed is rough so the sand sticks to it. Its width (32 m) is a multiple of
+    // the grid block width (8 cells) as required along periodic axes.
+    let domain = SimulationDomain::new(
+        vector![0.0, 0.0],
+        vector![32.0, 100.0],
+        BoundaryCondition::Sticky,
+    )
+    .with_periodic_axis(0);
+
+    let slope: f32 = 0.45;
+    let params = SimulationParams {
+        gravity: vector![slope.sin(), -slope.cos()] * 9.81 * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain,
+    };
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
@@ -6,9 +6,9 @@ use nalgebra::{point, vector, Vector2};
 use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
 use wgsparkl::solver::ParticlePhase;
 use wgsparkl::{
-    models::ElasticCoefficients,
+    models::{DruckerPrager, ElasticCoefficients},
     pipeline::MpmData,
-    solver::{Particle, SimulationParams},
+    solver::{Particle, SimulationDomain, SimulationParams},
 };
 use wgsparkl2d::solver::ParticleDynamics;
 use wgsparkl_testbed2d::{AppState, PhysicsContext};
@@ -36,16 +36,16 @@ pub fn elastic_cut_demo(
                 + Vector2::y() * offset_y;
             let density = 1000.0;
             let radius = cell_width / 4.0;
-            particles.push(Particle {
-                position,
-                dynamics: ParticleDynamics::with_density(radius, density),
-                model: ElasticCoefficients::from_young_modulus(5_000_000.0, 0.2),
-                plasticity: None,
-                phase: Some(ParticlePhase {
-                    phase: 1.0,
-                    max_stretch: f32::MAX,
-                }),
-            });
+            // NOTE: the particles torn apart by the blades crumble like sand.
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density),
+                    ElasticCoefficients::from_young_modulus(5_000_000.0, 0.2),
+                )
+                .with_plasticity(DruckerPrager::new(5_000_000.0, 0.2))
+                .with_phase(ParticlePhase::breakable(500_000.0, 2.0)),
+            );
         }
     }
 
@@ -58,6 +58,7 @@ pub fn elastic_cut_demo(
         gravity: vector![0.0, -9.81] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
         padding: 0.0,
+        domain: SimulationDomain::unbounded(),
     };
 
     // const ANGVEL: f32 = 1.0; // 2.0;
@@ -67,7 +68,7 @@ pub fn elastic_cut_demo(
      */
     let rb = RigidBodyBuilder::fixed().translation(vector![35.0, 20.0]);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(70.0, 1.0);
+    let co = ColliderBuilder::cuboid(70.0, 1.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -85,7 +86,9 @@ pub fn elastic_cut_demo(
 
     let rb = RigidBodyBuilder::fixed();
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::polyline(polyline, None).build();
+    let co = ColliderBuilder::polyline(polyline, None)
+        .friction(20.0)
+        .build();
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -100,6 +103,7 @@ pub fn elastic_cut_demo(
             ],
             None,
         )
+        .friction(20.0)
         .build();
         rapier_data
             .colliders
@@ -114,7 +118,8 @@ pub fn elastic_cut_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
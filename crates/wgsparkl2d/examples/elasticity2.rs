@@ -8,7 +8,7 @@ use wgsparkl::solver::ParticlePhase;
 use wgsparkl::{
     models::ElasticCoefficients,
     pipeline::MpmData,
-    solver::{Particle, SimulationParams},
+    solver::{Particle, SimulationDomain, SimulationParams},
 };
 use wgsparkl2d::solver::ParticleDynamics;
 use wgsparkl_testbed2d::{AppState, PhysicsContext};
@@ -37,16 +37,14 @@ pub fn elastic_demo(
                     + Vector2::y() * offset_y;
             let density = 1000.0;
             let radius = cell_width / 4.0;
-            particles.push(Particle {
-                position,
-                dynamics: ParticleDynamics::with_density(radius, density),
-                model: ElasticCoefficients::from_young_modulus(5_000_000.0, 0.2),
-                plasticity: None,
-                phase: Some(ParticlePhase {
-                    phase: 1.0,
-                    max_stretch: f32::MAX,
-                }),
-            });
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density),
+                    ElasticCoefficients::from_young_modulus(5_000_000.0, 0.2),
+                )
+                .with_phase(ParticlePhase::unbreakable()),
+            );
         }
     }
 
@@ -59,11 +57,12 @@ pub fn elastic_demo(
         gravity: vector![0.0, -9.81] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
         padding: 0.0,
+        domain: SimulationDomain::unbounded(),
     };
 
     let rb = RigidBodyBuilder::fixed().translation(vector![0.0, -1.0]);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1000.0, 1.0);
+    let co = ColliderBuilder::cuboid(1000.0, 1.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -72,7 +71,7 @@ pub fn elastic_demo(
         .translation(vector![-20.0, 0.0])
         .rotation(0.5);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 60.0);
+    let co = ColliderBuilder::cuboid(1.0, 60.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -81,7 +80,7 @@ pub fn elastic_demo(
         .translation(vector![90.0, 0.0])
         .rotation(-0.5);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 60.0);
+    let co = ColliderBuilder::cuboid(1.0, 60.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -94,7 +93,8 @@ pub fn elastic_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
@@ -0,0 +1,104 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::vector;
+use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use wgsparkl::models::Fiber;
+use wgsparkl::{
+    models::ElasticCoefficients,
+    pipeline::MpmData,
+    solver::{Particle, ParticlePhase, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+pub fn fiber_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let device = device.wgpu_device();
+    let mut rapier_data = RapierData::default();
+
+    let cell_width = 0.2;
+    let mut particles = vec![];
+
+    /*
+     * Soft blocks without fibers, with horizontal fibers, with vertical fibers,
+     * and with diagonal tension-only fibers, falling on obstacles.
+     */
+    let fibers = [
+        None,
+        Some(Fiber::new(vector![1.0, 0.0], 10_000_000.0)),
+        Some(Fiber::new(vector![0.0, 1.0], 10_000_000.0)),
+        Some(Fiber::new(vector![1.0, 1.0], 10_000_000.0).with_compression_ratio(0.0)),
+    ];
+
+    for (k, fiber) in fibers.into_iter().enumerate() {
+        let offset = vector![5.0 + 25.0 * k as f32, 20.0];
+        for i in 0..150 {
+            for j in 0..100 {
+                let density = 1000.0;
+                let radius = cell_width / 4.0;
+                let position = offset + vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+                particles.push(Particle {
+                    fiber,
+                    ..Particle::new(
+                        position,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(200_000.0, 0.3),
+                    )
+                    .with_phase(ParticlePhase::unbreakable())
+                });
+            }
+        }
+
+        let rb = RigidBodyBuilder::fixed().translation(vector![offset.x + 7.5, 5.0]);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::ball(2.0).friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 20;
+        app_state.gravity_factor = 1.0;
+    };
+
+    let params = SimulationParams {
+        gravity: vector![0.0, -9.81] * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain: SimulationDomain::unbounded(),
+    };
+
+    let rb = RigidBodyBuilder::fixed().translation(vector![50.0, -1.0]);
+    let rb_handle = rapier_data.bodies.insert(rb);
+    let co = ColliderBuilder::cuboid(1000.0, 1.0).friction(20.0);
+    rapier_data
+        .colliders
+        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
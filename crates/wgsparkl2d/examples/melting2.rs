@@ -0,0 +1,124 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::vector;
+use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use wgsparkl::models::{FluidCoefficients, ThermalProperties, Viscosity};
+use wgsparkl::{
+    models::{ConstitutiveModel, ElasticCoefficients},
+    pipeline::MpmData,
+    solver::{Particle, ParticlePhase, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+pub fn melting_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let mut rapier_data = RapierData::default();
+    let device = device.wgpu_device();
+
+    let cell_width = 0.2;
+    let radius = cell_width / 4.0;
+    let density = 1000.0;
+    let mut particles = vec![];
+
+    /*
+     * A block of wax, softening as it warms up, and melting above 60 degrees.
+     */
+    let wax_thermal = ThermalProperties::new(1_000.0, 1.0)
+        .with_softening(20.0, 0.05, 0.0)
+        .with_melting_point(60.0);
+    for i in 0..300 {
+        for j in 0..100 {
+            let position =
+                vector![20.0, 0.0] + vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density).with_temperature(20.0),
+                    ElasticCoefficients::from_young_modulus(1_000_000.0, 0.3),
+                )
+                .with_fluid(FluidCoefficients::linear(1_000_000.0, 1.0))
+                .with_phase(ParticlePhase::unbreakable())
+                .with_thermal(wax_thermal),
+            );
+        }
+    }
+
+    /*
+     * Viscous lava falling on the wax.
+     */
+    for i in 0..100 {
+        for j in 0..100 {
+            let position =
+                vector![30.0, 20.0] + vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density).with_temperature(1_000.0),
+                    ElasticCoefficients::from_young_modulus(0.0, 0.0),
+                )
+                .with_constitutive_model(ConstitutiveModel::Fluid)
+                .with_fluid(FluidCoefficients::linear(1_000_000.0, 0.0))
+                .with_viscosity(Viscosity::kelvin_voigt(50.0))
+                .with_thermal(ThermalProperties::new(1_000.0, 1.0)),
+            );
+        }
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 10;
+        app_state.gravity_factor = 1.0;
+    };
+
+    let params = SimulationParams {
+        gravity: vector![0.0, -9.81] * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain: SimulationDomain::unbounded(),
+    };
+
+    /*
+     * Tank.
+     */
+    let rb = RigidBodyBuilder::fixed().translation(vector![35.0, -1.0]);
+    let rb_handle = rapier_data.bodies.insert(rb);
+    let co = ColliderBuilder::cuboid(37.0, 1.0).friction(20.0);
+    rapier_data
+        .colliders
+        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+
+    for x in [-1.0, 71.0] {
+        let rb = RigidBodyBuilder::fixed().translation(vector![x, 20.0]);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::cuboid(1.0, 20.0).friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
@@ -8,7 +8,7 @@ use wgsparkl::models::DruckerPrager;
 use wgsparkl::{
     models::ElasticCoefficients,
     pipeline::MpmData,
-    solver::{Particle, SimulationParams},
+    solver::{Particle, SimulationDomain, SimulationParams},
 };
 use wgsparkl2d::solver::ParticleDynamics;
 use wgsparkl_testbed2d::{AppState, PhysicsContext};
@@ -36,13 +36,14 @@ pub fn sand_demo(
                 + Vector2::y() * offset_y;
             let density = 1000.0;
             let radius = cell_width / 4.0;
-            particles.push(Particle {
-                position,
-                dynamics: ParticleDynamics::with_density(radius, density),
-                model: ElasticCoefficients::from_young_modulus(10_000_000.0, 0.2),
-                plasticity: Some(DruckerPrager::new(10_000_000.0, 0.2)),
-                phase: None,
-            });
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density),
+                    ElasticCoefficients::from_young_modulus(10_000_000.0, 0.2),
+                )
+                .with_plasticity(DruckerPrager::new(10_000_000.0, 0.2)),
+            );
         }
     }
 
@@ -55,8 +56,11 @@ pub fn sand_demo(
         gravity: vector![0.0, -9.81] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
         padding: 0.0,
+        domain: SimulationDomain::unbounded(),
     };
 
+    // NOTE: a high friction between the sand and the platforms, so the sand piles up on them.
+    const FRICTION: f32 = 20.0;
     const ANGVEL: f32 = 1.0; // 2.0;
 
     /*
@@ -64,7 +68,7 @@ pub fn sand_demo(
      */
     let rb = RigidBodyBuilder::fixed().translation(vector![35.0, -1.0]);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(42.0, 1.0);
+    let co = ColliderBuilder::cuboid(42.0, 1.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -73,7 +77,7 @@ pub fn sand_demo(
         .translation(vector![-25.0, 45.0])
         .rotation(0.5);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 52.0);
+    let co = ColliderBuilder::cuboid(1.0, 52.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -82,7 +86,7 @@ pub fn sand_demo(
         .translation(vector![95.0, 45.0])
         .rotation(-0.5);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 52.0);
+    let co = ColliderBuilder::cuboid(1.0, 52.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -94,7 +98,7 @@ pub fn sand_demo(
         .translation(vector![5.0, 35.0])
         .angvel(ANGVEL);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 10.0);
+    let co = ColliderBuilder::cuboid(1.0, 10.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -103,7 +107,7 @@ pub fn sand_demo(
         .translation(vector![35.0, 35.0])
         .angvel(-ANGVEL);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(10.0, 1.0);
+    let co = ColliderBuilder::cuboid(10.0, 1.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -112,7 +116,7 @@ pub fn sand_demo(
         .translation(vector![65.0, 35.0])
         .angvel(ANGVEL);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(1.0, 10.0);
+    let co = ColliderBuilder::cuboid(1.0, 10.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -121,7 +125,7 @@ pub fn sand_demo(
         .translation(vector![20.0, 20.0])
         .angvel(-ANGVEL);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::ball(5.0);
+    let co = ColliderBuilder::ball(5.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -130,7 +134,7 @@ pub fn sand_demo(
         .translation(vector![50.0, 20.0])
         .angvel(-ANGVEL);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::capsule_y(5.0, 3.0);
+    let co = ColliderBuilder::capsule_y(5.0, 3.0).friction(FRICTION);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -172,7 +176,8 @@ pub fn sand_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
@@ -0,0 +1,112 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::vector;
+use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use wgsparkl::models::Snow;
+use wgsparkl::{
+    models::ElasticCoefficients,
+    pipeline::MpmData,
+    solver::{Particle, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+pub fn snow_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let mut rapier_data = RapierData::default();
+    let device = device.wgpu_device();
+
+    let cell_width = 0.2;
+    let mut particles = vec![];
+
+    /*
+     * Snowballs of increasing hardening.
+     */
+    for (k, hardening_coeff) in [5.0, 10.0, 20.0].into_iter().enumerate() {
+        let center = vector![20.0 + 30.0 * k as f32, 30.0];
+        let ball_radius = 8.0;
+        let n = (ball_radius * 2.0 / (cell_width / 2.0)) as i32;
+        for i in -n / 2..n / 2 {
+            for j in -n / 2..n / 2 {
+                let delta = vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+                if delta.norm() > ball_radius {
+                    continue;
+                }
+
+                let density = 400.0;
+                let radius = cell_width / 4.0;
+                particles.push(
+                    Particle::new(
+                        center + delta,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(140_000.0, 0.2),
+                    )
+                    .with_plasticity(Snow::new(
+                        2.5e-2,
+                        7.5e-3,
+                        hardening_coeff,
+                    )),
+                );
+            }
+        }
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 10;
+        app_state.gravity_factor = 1.0;
+    };
+
+    let params = SimulationParams {
+        gravity: vector![0.0, -9.81] * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain: SimulationDomain::unbounded(),
+    };
+
+    /*
+     * Ground and obstacles.
+     */
+    let rb = RigidBodyBuilder::fixed().translation(vector![50.0, -1.0]);
+    let rb_handle = rapier_data.bodies.insert(rb);
+    let co = ColliderBuilder::cuboid(60.0, 1.0).friction(20.0);
+    rapier_data
+        .colliders
+        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+
+    for k in 0..3 {
+        let rb = RigidBodyBuilder::fixed()
+            .translation(vector![20.0 + 30.0 * k as f32, 8.0])
+            .rotation(0.3);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::cuboid(4.0, 1.0).friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
@@ -1,9 +1,15 @@
 use bevy::prelude::*;
 use wgsparkl_testbed2d::{init_testbed, SceneInits};
 
+mod chute2;
 mod elastic_cut2;
 mod elasticity2;
+mod fiber2;
+mod melting2;
 mod sand2;
+mod snow2;
+mod viscous2;
+mod water2;
 
 pub fn main() {
     let mut app = App::new();
@@ -28,6 +34,27 @@ fn register_scenes(world: &mut World) {
             "elastic cut".to_string(),
             world.register_system(elastic_cut2::elastic_cut_demo),
         ),
+        (
+            "water".to_string(),
+            world.register_system(water2::water_demo),
+        ),
+        ("snow".to_string(), world.register_system(snow2::snow_demo)),
+        (
+            "viscous".to_string(),
+            world.register_system(viscous2::viscous_demo),
+        ),
+        (
+            "melting".to_string(),
+            world.register_system(melting2::melting_demo),
+        ),
+        (
+            "fiber".to_string(),
+            world.register_system(fiber2::fiber_demo),
+        ),
+        (
+            "chute".to_string(),
+            world.register_system(chute2::chute_demo),
+        ),
     ];
     let mut inits = world.resource_mut::<SceneInits>();
     inits.scenes = scenes;
@@ -0,0 +1,122 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::vector;
+use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use wgsparkl::models::{FluidCoefficients, Viscosity};
+use wgsparkl::{
+    models::{ConstitutiveModel, ElasticCoefficients},
+    pipeline::MpmData,
+    solver::{Particle, ParticlePhase, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+pub fn viscous_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let mut rapier_data = RapierData::default();
+    let device = device.wgpu_device();
+
+    let cell_width = 0.2;
+    let mut particles = vec![];
+
+    /*
+     * Blocks of toothpaste-like (Bingham), mud-like (shear-thinning Herschel-Bulkley), and
+     * Maxwell viscoelastic materials.
+     */
+    let materials = [
+        (
+            ConstitutiveModel::Fluid,
+            Some(FluidCoefficients::linear(1_000_000.0, 0.0)),
+            Viscosity::bingham(200.0, 50.0),
+        ),
+        (
+            ConstitutiveModel::Fluid,
+            Some(FluidCoefficients::linear(1_000_000.0, 0.0)),
+            Viscosity::herschel_bulkley(50.0, 200.0, 0.5),
+        ),
+        (ConstitutiveModel::Corotated, None, Viscosity::maxwell(0.5)),
+    ];
+
+    for (k, (constitutive_model, fluid, viscosity)) in materials.into_iter().enumerate() {
+        let offset = vector![10.0 + 30.0 * k as f32, 20.0];
+        for i in 0..100 {
+            for j in 0..100 {
+                let density = 1000.0;
+                let radius = cell_width / 4.0;
+                let position = offset + vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0;
+                particles.push(Particle {
+                    fluid,
+                    ..Particle::new(
+                        position,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(1_000_000.0, 0.3),
+                    )
+                    .with_constitutive_model(constitutive_model)
+                    .with_viscosity(viscosity)
+                    // NOTE: keep the particles intact so they don’t go through the
+                    //       plasticity projections.
+                    .with_phase(ParticlePhase::unbreakable())
+                });
+            }
+        }
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 10;
+        app_state.gravity_factor = 1.0;
+    };
+
+    let params = SimulationParams {
+        gravity: vector![0.0, -9.81] * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain: SimulationDomain::unbounded(),
+    };
+
+    /*
+     * Ground and obstacles.
+     */
+    let rb = RigidBodyBuilder::fixed().translation(vector![50.0, -1.0]);
+    let rb_handle = rapier_data.bodies.insert(rb);
+    let co = ColliderBuilder::cuboid(60.0, 1.0).friction(20.0);
+    rapier_data
+        .colliders
+        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+
+    for k in 0..3 {
+        let rb = RigidBodyBuilder::fixed()
+            .translation(vector![20.0 + 30.0 * k as f32, 8.0])
+            .rotation(0.3);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::cuboid(4.0, 1.0).friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
@@ -0,0 +1,110 @@
+use wgsparkl_testbed2d::{wgsparkl, RapierData};
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use nalgebra::{vector, Vector2};
+use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use wgsparkl::models::FluidCoefficients;
+use wgsparkl::{
+    models::{ConstitutiveModel, ElasticCoefficients},
+    pipeline::MpmData,
+    solver::{Particle, SimulationDomain, SimulationParams},
+};
+use wgsparkl2d::solver::ParticleDynamics;
+use wgsparkl_testbed2d::{AppState, PhysicsContext};
+
+#[allow(dead_code)]
+fn main() {
+    panic!("Run the `testbed2` example instead.");
+}
+
+pub fn water_demo(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut app_state: ResMut<AppState>,
+) {
+    let mut rapier_data = RapierData::default();
+    let device = device.wgpu_device();
+
+    let offset_y = 2.0;
+    let cell_width = 0.2;
+    let mut particles = vec![];
+    for i in 0..400 {
+        for j in 0..600 {
+            let position = vector![i as f32 + 0.5, j as f32 + 0.5] * cell_width / 2.0
+                + Vector2::y() * offset_y;
+            let density = 1000.0;
+            let radius = cell_width / 4.0;
+            particles.push(
+                Particle::new(
+                    position,
+                    ParticleDynamics::with_density(radius, density),
+                    ElasticCoefficients::from_young_modulus(0.0, 0.0),
+                )
+                .with_constitutive_model(ConstitutiveModel::Fluid)
+                .with_fluid(FluidCoefficients::tait(1_000_000.0, 0.1)),
+            );
+        }
+    }
+
+    if !app_state.restarting {
+        app_state.num_substeps = 15;
+        app_state.gravity_factor = 1.0;
+    };
+
+    let params = SimulationParams {
+        gravity: vector![0.0, -9.81] * app_state.gravity_factor,
+        dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        padding: 0.0,
+        domain: SimulationDomain::unbounded(),
+    };
+
+    /*
+     * Tank.
+     */
+    let rb = RigidBodyBuilder::fixed().translation(vector![60.0, -1.0]);
+    let rb_handle = rapier_data.bodies.insert(rb);
+    let co = ColliderBuilder::cuboid(62.0, 1.0).friction(20.0);
+    rapier_data
+        .colliders
+        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+
+    for x in [-1.0, 121.0] {
+        let rb = RigidBodyBuilder::fixed().translation(vector![x, 40.0]);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::cuboid(1.0, 40.0).friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    /*
+     * Floating and sinking boxes.
+     */
+    for k in 0..6 {
+        let rb = RigidBodyBuilder::dynamic().translation(vector![55.0 + 10.0 * k as f32, 20.0]);
+        let rb_handle = rapier_data.bodies.insert(rb);
+        let co = ColliderBuilder::cuboid(2.0, 2.0)
+            .density(250.0 + k as f32 * 500.0)
+            .friction(20.0);
+        rapier_data
+            .colliders
+            .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
+    }
+
+    let data = MpmData::new(
+        device,
+        params,
+        &particles,
+        &rapier_data.bodies,
+        &rapier_data.colliders,
+        cell_width,
+        60_000,
+    )
+    .unwrap();
+    commands.insert_resource(PhysicsContext {
+        data,
+        rapier_data,
+        particles,
+    });
+}
@@ -8,7 +8,7 @@ use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
 use wgsparkl::{
     models::ElasticCoefficients,
     pipeline::MpmData,
-    solver::{Particle, ParticleDynamics, ParticlePhase, SimulationParams},
+    solver::{Particle, ParticleDynamics, ParticlePhase, SimulationDomain, SimulationParams},
 };
 use wgsparkl_testbed3d::{AppState, PhysicsContext};
 
@@ -39,16 +39,14 @@ pub fn elastic_cut_demo(
                     / 2.0;
                 let density = 2700.0;
                 let radius = cell_width / 4.0;
-                particles.push(Particle {
-                    position,
-                    dynamics: ParticleDynamics::with_density(radius, density),
-                    model: ElasticCoefficients::from_young_modulus(10_000_000.0, 0.2),
-                    plasticity: None,
-                    phase: Some(ParticlePhase {
-                        phase: 1.0,
-                        max_stretch: f32::MAX,
-                    }),
-                });
+                particles.push(
+                    Particle::new(
+                        position,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(10_000_000.0, 0.2),
+                    )
+                    .with_phase(ParticlePhase::unbreakable()),
+                );
             }
         }
     }
@@ -61,11 +59,12 @@ pub fn elastic_cut_demo(
     let params = SimulationParams {
         gravity: vector![0.0, -9.81, 0.0] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        domain: SimulationDomain::unbounded(),
     };
 
     let rb = RigidBodyBuilder::fixed().translation(vector![0.0, -4.0, 0.0]);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(100.0, 1.0, 100.0);
+    let co = ColliderBuilder::cuboid(100.0, 1.0, 100.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -82,7 +81,7 @@ pub fn elastic_cut_demo(
         });
         let rb = RigidBodyBuilder::fixed();
         let rb_handle = rapier_data.bodies.insert(rb);
-        let co = ColliderBuilder::trimesh(vtx, idx).unwrap();
+        let co = ColliderBuilder::trimesh(vtx, idx).unwrap().friction(20.0);
         rapier_data
             .colliders
             .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -96,7 +95,8 @@ pub fn elastic_cut_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
@@ -9,7 +9,7 @@ use wgsparkl::models::DruckerPrager;
 use wgsparkl::{
     models::ElasticCoefficients,
     pipeline::MpmData,
-    solver::{Particle, ParticleDynamics, SimulationParams},
+    solver::{Particle, ParticleDynamics, SimulationDomain, SimulationParams},
 };
 use wgsparkl_testbed3d::{AppState, PhysicsContext};
 
@@ -40,13 +40,14 @@ pub fn heightfield_demo(
                     / 2.0;
                 let density = 2700.0;
                 let radius = cell_width / 4.0;
-                particles.push(Particle {
-                    position,
-                    dynamics: ParticleDynamics::with_density(radius, density),
-                    model: ElasticCoefficients::from_young_modulus(2_000_000_000.0, 0.2),
-                    plasticity: Some(DruckerPrager::new(2_000_000_000.0, 0.2)),
-                    phase: None,
-                });
+                particles.push(
+                    Particle::new(
+                        position,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(2_000_000_000.0, 0.2),
+                    )
+                    .with_plasticity(DruckerPrager::new(2_000_000_000.0, 0.2)),
+                );
             }
         }
     }
@@ -59,6 +60,7 @@ pub fn heightfield_demo(
     let params = SimulationParams {
         gravity: vector![0.0, -9.81, 0.0] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        domain: SimulationDomain::unbounded(),
     };
 
     let heights = DMatrix::from_fn(200, 200, |i, j| {
@@ -68,7 +70,8 @@ pub fn heightfield_demo(
     let (vtx, idx) = heightfield.to_trimesh();
     let rb = RigidBodyBuilder::fixed();
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::trimesh(vtx, idx).unwrap();
+    // NOTE: a high friction so the sand piles up on the slopes instead of sliding down.
+    let co = ColliderBuilder::trimesh(vtx, idx).unwrap().friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -81,7 +84,8 @@ pub fn heightfield_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
@@ -8,7 +8,7 @@ use wgsparkl::models::DruckerPrager;
 use wgsparkl::{
     models::ElasticCoefficients,
     pipeline::MpmData,
-    solver::{Particle, ParticleDynamics, SimulationParams},
+    solver::{BoundaryCondition, Particle, ParticleDynamics, SimulationDomain, SimulationParams},
 };
 use wgsparkl_testbed3d::{AppState, PhysicsContext};
 
@@ -39,13 +39,14 @@ pub fn sand_demo(
                     / 2.0;
                 let density = 2700.0;
                 let radius = cell_width / 4.0;
-                particles.push(Particle {
-                    position,
-                    dynamics: ParticleDynamics::with_density(radius, density),
-                    model: ElasticCoefficients::from_young_modulus(2_000_000_000.0, 0.2),
-                    plasticity: Some(DruckerPrager::new(2_000_000_000.0, 0.2)),
-                    phase: None,
-                });
+                particles.push(
+                    Particle::new(
+                        position,
+                        ParticleDynamics::with_density(radius, density),
+                        ElasticCoefficients::from_young_modulus(2_000_000_000.0, 0.2),
+                    )
+                    .with_plasticity(DruckerPrager::new(2_000_000_000.0, 0.2)),
+                );
             }
         }
     }
@@ -55,49 +56,27 @@ pub fn sand_demo(
         app_state.gravity_factor = 1.0;
     };
 
+    // The floor and walls of the container are the boundaries of the simulation domain.
+    let domain = SimulationDomain::new(
+        vector![-34.5, 0.0, -34.5],
+        vector![34.5, 100.0, 34.5],
+        BoundaryCondition::Separate,
+    )
+    .with_friction(0.5);
+
     let params = SimulationParams {
         gravity: vector![0.0, -9.81, 0.0] * app_state.gravity_factor,
         dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
+        domain,
     };
 
-    let rb = RigidBodyBuilder::fixed().translation(vector![0.0, -4.0, 0.0]);
-    let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(100.0, 4.0, 100.0);
-    rapier_data
-        .colliders
-        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
-
-    let rb = RigidBodyBuilder::fixed().translation(vector![0.0, 5.0, -35.0]);
-    let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(35.0, 5.0, 0.5);
-    rapier_data
-        .colliders
-        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
-    let rb = RigidBodyBuilder::fixed().translation(vector![0.0, 5.0, 35.0]);
-    let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(35.0, 5.0, 0.5);
-    rapier_data
-        .colliders
-        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
-    let rb = RigidBodyBuilder::fixed().translation(vector![-35.0, 5.0, 0.0]);
-    let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(0.5, 5.0, 35.0);
-    rapier_data
-        .colliders
-        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
-    let rb = RigidBodyBuilder::fixed().translation(vector![35.0, 5.0, 0.0]);
-    let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(0.5, 5.0, 35.0);
-    rapier_data
-        .colliders
-        .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
-
     let rb = RigidBodyBuilder::kinematic_velocity_based()
         .translation(vector![0.0, 2.0, 0.0])
         .rotation(vector![0.0, 0.0, -0.5])
         .angvel(vector![0.0, -1.0, 0.0]);
     let rb_handle = rapier_data.bodies.insert(rb);
-    let co = ColliderBuilder::cuboid(0.5, 2.0, 30.0);
+    // NOTE: a high friction so the paddle drags the sand along.
+    let co = ColliderBuilder::cuboid(0.5, 2.0, 30.0).friction(20.0);
     rapier_data
         .colliders
         .insert_with_parent(co, rb_handle, &mut rapier_data.bodies);
@@ -110,7 +89,8 @@ pub fn sand_demo(
         &rapier_data.colliders,
         cell_width,
         60_000,
-    );
+    )
+    .unwrap();
     commands.insert_resource(PhysicsContext {
         data,
         rapier_data,
@@ -1,3 +1,4 @@
+use crate::collision::mesh::WgCollisionMesh;
 use crate::grid::grid::WgGrid;
 use crate::{dim_shader_defs, substitute_aliases};
 use wgcore::Shader;
@@ -6,7 +7,7 @@ use wgrapier::dynamics::WgBody;
 
 #[derive(Shader)]
 #[shader(
-    derive(WgShape, WgBody, WgGrid),
+    derive(WgShape, WgBody, WgGrid, WgCollisionMesh),
     src = "collide.wgsl",
     src_fn = "substitute_aliases",
     shader_defs = "dim_shader_defs"
@@ -2,6 +2,7 @@
 #import wgparry::shape as Shape;
 #import wgrapier::body as Body;
 #import wgsparkl::grid::grid as Grid;
+#import wgsparkl::collision::mesh as Mesh;
 
 #if DIM == 2
 #import wgebra::sim2 as Pose;
@@ -14,15 +15,13 @@
 var<storage, read> collision_shapes: array<Shape::Shape>;
 @group(2) @binding(1)
 var<storage, read> collision_shape_poses: array<Transform>;
-//@group(2) @binding(2)
-//var<storage, read> body_vels: array<Body::Velocity>;
-//@group(2) @binding(3)
-//var<storage, read> body_mprops: array<Body::MassProperties>;
 
 
 fn collide(cell_width: f32, point: Vector) -> Grid::NodeCdf {
     const MAX_FLT: f32 = 1.0e10; // Is the f32::MAX constant defined somewhere in WGSL?
-    var cdf = Grid::NodeCdf(MAX_FLT, 0u, Grid::NONE);
+    var cdf = Grid::NodeCdf(MAX_FLT, Grid::NO_AFFINITY, Grid::NONE);
+    // Distances to the colliders currently stored in each affinity slot.
+    var slot_distances = array(MAX_FLT, MAX_FLT, MAX_FLT, MAX_FLT);
 
 #if DIM == 2
     let dist_cap = vec2(cell_width * 1.5);
@@ -33,24 +32,55 @@ fn collide(cell_width: f32, point: Vector) -> Grid::NodeCdf {
     // TODO: don’t  rely on the array length, e.g., if the user wants to
     //       preallocate the array to add more dynamically.
     for (var i = 0u; i < arrayLength(&collision_shapes); i++) {
-        // FIXME: figure out a way to support more than 16 colliders.
         let shape = collision_shapes[i];
         let shape_pose = collision_shape_poses[i];
         let shape_type = Shape::shape_type(shape);
-        if shape_type != Shape::SHAPE_TYPE_POLYLINE && shape_type != Shape::SHAPE_TYPE_TRIMESH {
+        var proj_point: Vector;
+        var is_inside: bool;
+
+        if shape_type == Shape::SHAPE_TYPE_POLYLINE || shape_type == Shape::SHAPE_TYPE_TRIMESH {
+            // Meshes are projected in the local-space of the collider, through their BVH.
+            let local_point = Pose::invMulPt(shape_pose, point);
+            let mesh_proj = Mesh::project_local_point(i, local_point, length(dist_cap));
+            if !mesh_proj.found {
+                continue;
+            }
+            proj_point = Pose::mulPt(shape_pose, mesh_proj.point);
+            is_inside = mesh_proj.is_inside;
+        } else {
             let proj = Shape::projectPointOnBoundary(shape, shape_pose, point);
-            let dpt = proj.point - point;
-
-            if proj.is_inside || all(abs(dpt) <= dist_cap) {
-                let dist = length(dpt);
-                // TODO: take is_inside into account to select the deepest
-                //       penetration as the closest collider?
-                cdf.closest_id = select(cdf.closest_id, i, dist < cdf.distance);
-                cdf.distance = min(cdf.distance, dist);
-                cdf.affinities |= select(0x00000001u, 0x00010001u, proj.is_inside) << i;
+            proj_point = proj.point;
+            is_inside = proj.is_inside;
+        }
+
+        let dpt = proj_point - point;
+
+        if is_inside || all(abs(dpt) <= dist_cap) {
+            let dist = length(dpt);
+            // TODO: take is_inside into account to select the deepest
+            //       penetration as the closest collider?
+            cdf.closest_id = select(cdf.closest_id, i, dist < cdf.distance);
+            cdf.distance = min(cdf.distance, dist);
+
+            // Only keep the closest colliders: replace the farthest one if
+            // all the affinity slots are already occupied.
+            var farthest_slot = 0u;
+            for (var k = 1u; k < Grid::MAX_AFFINITIES; k += 1u) {
+                if slot_distances[k] > slot_distances[farthest_slot] {
+                    farthest_slot = k;
+                }
+            }
+
+            if dist < slot_distances[farthest_slot] {
+                slot_distances[farthest_slot] = dist;
+                cdf.affinities = Grid::set_affinity_slot(
+                    cdf.affinities,
+                    farthest_slot,
+                    Grid::pack_affinity(i, is_inside)
+                );
             }
         }
     }
 
     return cdf;
-}
\ No newline at end of file
+}
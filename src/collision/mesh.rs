@@ -0,0 +1,218 @@
+use crate::{dim_shader_defs, substitute_aliases};
+use nalgebra::Vector4;
+use rapier::geometry::{Aabb, ColliderSet};
+use rapier::math::{Point, DIM};
+use rapier::parry::bounding_volume::BoundingVolume;
+use wgcore::tensor::GpuVector;
+use wgcore::Shader;
+use wgpu::{BufferUsages, Device};
+use wgrapier::dynamics::body::BodyCouplingEntry;
+use wgrapier::dynamics::GpuBodySet;
+
+/// The maximum number of primitives stored in a BVH leaf.
+const MAX_LEAF_PRIMITIVES: usize = 4;
+/// The BVH root of colliders that are not meshes.
+const NO_MESH: u32 = u32::MAX;
+
+/// A node of the BVH of a collision mesh.
+///
+/// Internal nodes have `num_primitives == 0`. Their left child is the node right after them
+/// and their right child is at index `right_or_first`. Leaves contain the primitives in
+/// the range `right_or_first..right_or_first + num_primitives`.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct GpuBvhNode {
+    pub mins: [f32; 4],
+    pub maxs: [f32; 4],
+    pub right_or_first: u32,
+    pub num_primitives: u32,
+    pub padding: [u32; 2],
+}
+
+impl GpuBvhNode {
+    fn new(aabb: &Aabb, right_or_first: u32, num_primitives: u32) -> Self {
+        let mut mins = [0.0; 4];
+        let mut maxs = [0.0; 4];
+        mins[..DIM].copy_from_slice(aabb.mins.coords.as_slice());
+        maxs[..DIM].copy_from_slice(aabb.maxs.coords.as_slice());
+        Self {
+            mins,
+            maxs,
+            right_or_first,
+            num_primitives,
+            padding: [0; 2],
+        }
+    }
+}
+
+/// The triangle meshes (in 3D) and polylines (in 2D) of the colliders coupled with the
+/// particles, used to compute the grid CDF by direct point projection.
+///
+/// The primitives index the local-space vertex buffer of the [`GpuBodySet`] and are
+/// organized into one BVH per mesh, built in the local-space of its collider.
+pub struct GpuCollisionMeshes {
+    /// The BVH root of each coupled collider, or `u32::MAX` if it isn’t a mesh.
+    pub roots: GpuVector<u32>,
+    pub nodes: GpuVector<GpuBvhNode>,
+    /// The vertex indices of each primitive. The last component is unused, as well as the
+    /// third one for segments.
+    pub primitives: GpuVector<Vector4<u32>>,
+}
+
+impl GpuCollisionMeshes {
+    pub fn from_rapier(
+        device: &Device,
+        colliders: &ColliderSet,
+        gpu_bodies: &GpuBodySet,
+        coupling: &[BodyCouplingEntry],
+    ) -> Self {
+        let mut roots = vec![];
+        let mut nodes = vec![];
+        let mut primitives = vec![];
+
+        for (coupling, gpu_data) in coupling.iter().zip(gpu_bodies.shapes_data().iter()) {
+            let collider = &colliders[coupling.collider];
+
+            #[cfg(feature = "dim2")]
+            let mesh = collider.shape().as_polyline().map(|polyline| {
+                let indices: Vec<_> = polyline
+                    .indices()
+                    .iter()
+                    .map(|[a, b]| [*a, *b, 0])
+                    .collect();
+                (
+                    polyline.vertices().to_vec(),
+                    indices,
+                    gpu_data.polyline_rngs()[0],
+                )
+            });
+            #[cfg(feature = "dim3")]
+            let mesh = if let Some(trimesh) = collider.shape().as_trimesh() {
+                Some((
+                    trimesh.vertices().to_vec(),
+                    trimesh.indices().to_vec(),
+                    gpu_data.trimesh_rngs()[0],
+                ))
+            } else {
+                // NOTE: heightfields are converted to triangle meshes when uploaded to the gpu.
+                collider.shape().as_heightfield().map(|heightfield| {
+                    let (vtx, idx) = heightfield.to_trimesh();
+                    (vtx, idx, gpu_data.trimesh_rngs()[0])
+                })
+            };
+
+            match mesh {
+                Some((vertices, indices, base_vid)) => {
+                    let root =
+                        push_mesh_bvh(&vertices, &indices, base_vid, &mut nodes, &mut primitives);
+                    roots.push(root);
+                }
+                None => roots.push(NO_MESH),
+            }
+        }
+
+        // NOTE: allocate at least one element since empty buffers can’t be bound.
+        if roots.is_empty() {
+            roots.push(NO_MESH);
+        }
+        if nodes.is_empty() {
+            nodes.push(GpuBvhNode::new(&Aabb::new_invalid(), 0, 0));
+        }
+        if primitives.is_empty() {
+            primitives.push(Vector4::zeros());
+        }
+
+        Self {
+            roots: GpuVector::init(device, &roots, BufferUsages::STORAGE),
+            nodes: GpuVector::init(device, &nodes, BufferUsages::STORAGE),
+            primitives: GpuVector::init(device, &primitives, BufferUsages::STORAGE),
+        }
+    }
+}
+
+/// Builds the BVH of a mesh and appends its nodes and primitives to the given buffers.
+///
+/// Returns the index of the BVH root, or `u32::MAX` if the mesh is empty.
+fn push_mesh_bvh(
+    vertices: &[Point<f32>],
+    indices: &[[u32; 3]],
+    base_vid: u32,
+    nodes: &mut Vec<GpuBvhNode>,
+    primitives: &mut Vec<Vector4<u32>>,
+) -> u32 {
+    if indices.is_empty() {
+        return NO_MESH;
+    }
+
+    #[cfg(feature = "dim2")]
+    let num_vertices_per_primitive = 2;
+    #[cfg(feature = "dim3")]
+    let num_vertices_per_primitive = 3;
+
+    let aabbs: Vec<_> = indices
+        .iter()
+        .map(|idx| {
+            let mut aabb = Aabb::new_invalid();
+            for i in &idx[..num_vertices_per_primitive] {
+                aabb.take_point(vertices[*i as usize]);
+            }
+            aabb
+        })
+        .collect();
+    let mut ids: Vec<_> = (0..indices.len() as u32).collect();
+    let first_primitive = primitives.len() as u32;
+    let root = build_bvh_node(&aabbs, &mut ids, first_primitive, nodes);
+
+    primitives.extend(ids.iter().map(|id| {
+        let idx = indices[*id as usize];
+        Vector4::new(idx[0] + base_vid, idx[1] + base_vid, idx[2] + base_vid, 0)
+    }));
+
+    root
+}
+
+/// Recursively splits the primitives `ids` at the median of the largest axis of their AABB.
+///
+/// The nodes are stored in depth-first order so the left child of an internal node is always
+/// right after it. The `ids` are reordered so that each leaf covers a contiguous range.
+fn build_bvh_node(
+    aabbs: &[Aabb],
+    ids: &mut [u32],
+    first_primitive: u32,
+    nodes: &mut Vec<GpuBvhNode>,
+) -> u32 {
+    let aabb = ids.iter().fold(Aabb::new_invalid(), |acc, id| {
+        acc.merged(&aabbs[*id as usize])
+    });
+    let node_id = nodes.len() as u32;
+
+    if ids.len() <= MAX_LEAF_PRIMITIVES {
+        nodes.push(GpuBvhNode::new(&aabb, first_primitive, ids.len() as u32));
+        return node_id;
+    }
+
+    nodes.push(GpuBvhNode::new(&aabb, 0, 0));
+    let axis = aabb.extents().imax();
+    ids.sort_unstable_by(|a, b| {
+        let ca = aabbs[*a as usize].center()[axis];
+        let cb = aabbs[*b as usize].center()[axis];
+        ca.total_cmp(&cb)
+    });
+
+    let mid = ids.len() / 2;
+    let (left, right) = ids.split_at_mut(mid);
+    build_bvh_node(aabbs, left, first_primitive, nodes);
+    let right_id = build_bvh_node(aabbs, right, first_primitive + mid as u32, nodes);
+    nodes[node_id as usize].right_or_first = right_id;
+    node_id
+}
+
+#[derive(Shader)]
+#[shader(
+    src = "mesh.wgsl",
+    src_fn = "substitute_aliases",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgCollisionMesh;
+
+wgcore::test_shader_compilation!(WgCollisionMesh, wgcore, crate::dim_shader_defs());
@@ -0,0 +1,168 @@
+#define_import_path wgsparkl::collision::mesh
+
+// The BVH root of colliders that are not meshes.
+const NO_MESH: u32 = 0xffffffffu;
+// The maximum number of BVH nodes pending traversal.
+const MAX_STACK_LEN: u32 = 32u;
+
+struct BvhNode {
+    mins: vec4<f32>,
+    maxs: vec4<f32>,
+    // The right child of an internal node, or the first primitive of a leaf.
+    // The left child of an internal node is always right after it.
+    right_or_first: u32,
+    // Zero for internal nodes.
+    num_primitives: u32,
+    padding: vec2<u32>,
+}
+
+struct MeshProjection {
+    point: Vector,
+    distance: f32,
+    is_inside: bool,
+    found: bool,
+}
+
+// NOTE: the vertices are expressed in the local-space of their collider.
+@group(2) @binding(2)
+var<storage, read> mesh_vertices: array<Vector>;
+@group(2) @binding(3)
+var<storage, read> mesh_roots: array<u32>;
+@group(2) @binding(4)
+var<storage, read> bvh_nodes: array<BvhNode>;
+@group(2) @binding(5)
+var<storage, read> mesh_primitives: array<vec4<u32>>;
+
+#if DIM == 2
+fn project_on_segment(a: Vector, b: Vector, pt: Vector) -> Vector {
+    let ab = b - a;
+    let sq_len = dot(ab, ab);
+    let t = select(0.0, clamp(dot(pt - a, ab) / sq_len, 0.0, 1.0), sq_len > 0.0);
+    return a + ab * t;
+}
+#else
+// Closest point on a triangle, from "Real-Time Collision Detection" by Christer Ericson.
+fn project_on_triangle(a: Vector, b: Vector, c: Vector, pt: Vector) -> Vector {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = pt - a;
+    let d1 = dot(ab, ap);
+    let d2 = dot(ac, ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = pt - b;
+    let d3 = dot(ab, bp);
+    let d4 = dot(ac, bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = pt - c;
+    let d5 = dot(ab, cp);
+    let d6 = dot(ac, cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    return a + ab * (vb * denom) + ac * (vc * denom);
+}
+#endif
+
+// Projects a local-space point on the mesh of the `mesh_id`-th collider, ignoring every
+// primitive farther than `max_dist`.
+//
+// The point is inside if it is on the back side of the closest primitive (the side opposite
+// to the counter-clockwise normal of triangles, or to the left-hand normal of segments). If the
+// closest point is on an edge or a vertex shared by several primitives, the sign is taken from the
+// primitive with a normal most aligned with the projection direction.
+fn project_local_point(mesh_id: u32, pt: Vector, max_dist: f32) -> MeshProjection {
+    var result = MeshProjection(pt, max_dist, false, false);
+    let root = mesh_roots[mesh_id];
+    if root == NO_MESH {
+        return result;
+    }
+
+    let tie_eps = max_dist * 1.0e-3;
+    var best_alignment = -1.0;
+    var stack: array<u32, MAX_STACK_LEN>;
+    var stack_len = 1u;
+    stack[0] = root;
+
+    while stack_len > 0u {
+        stack_len -= 1u;
+        let node_id = stack[stack_len];
+        let node = bvh_nodes[node_id];
+
+#if DIM == 2
+        let closest_in_aabb = clamp(pt, node.mins.xy, node.maxs.xy);
+#else
+        let closest_in_aabb = clamp(pt, node.mins.xyz, node.maxs.xyz);
+#endif
+        if length(pt - closest_in_aabb) > result.distance + tie_eps {
+            continue;
+        }
+
+        if node.num_primitives == 0u {
+            // NOTE: the left child is pushed last so it is traversed first.
+            if stack_len + 2u <= MAX_STACK_LEN {
+                stack[stack_len] = node.right_or_first;
+                stack[stack_len + 1u] = node_id + 1u;
+                stack_len += 2u;
+            }
+            continue;
+        }
+
+        for (var i = node.right_or_first; i < node.right_or_first + node.num_primitives; i++) {
+            let idx = mesh_primitives[i];
+            let a = mesh_vertices[idx.x];
+            let b = mesh_vertices[idx.y];
+#if DIM == 2
+            let proj = project_on_segment(a, b, pt);
+            let ab = b - a;
+            let n = vec2(-ab.y, ab.x);
+#else
+            let c = mesh_vertices[idx.z];
+            let proj = project_on_triangle(a, b, c, pt);
+            let n = cross(b - a, c - a);
+#endif
+            let n_length = length(n);
+            if n_length == 0.0 {
+                // Degenerate primitive.
+                continue;
+            }
+
+            let dpt = pt - proj;
+            let distance = length(dpt);
+            let alignment = select(0.0, abs(dot(dpt, n)) / (distance * n_length), distance > 0.0);
+
+            if distance < result.distance - tie_eps
+                || (distance <= result.distance + tie_eps && alignment > best_alignment) {
+                result.point = proj;
+                result.distance = distance;
+                result.is_inside = dot(dpt, n) < 0.0;
+                result.found = true;
+                best_alignment = alignment;
+            }
+        }
+    }
+
+    return result;
+}
@@ -1,3 +1,5 @@
 pub use collide::WgCollide;
+pub use mesh::{GpuBvhNode, GpuCollisionMeshes, WgCollisionMesh};
 
 mod collide;
+mod mesh;
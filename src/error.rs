@@ -0,0 +1,105 @@
+use naga_oil::compose::ComposerError;
+use std::fmt;
+
+/// Errors that can occur while initializing or running a simulation.
+#[derive(Debug)]
+pub enum Error {
+    /// A shader failed to compose or to compile.
+    Shader(ComposerError),
+    /// The shader sources couldn’t be watched for hot-reloading.
+    HotReloading(String),
+    /// A limit of the device is too low for the simulation, e.g., the number of storage buffers
+    /// per shader stage on WebGPU.
+    DeviceLimit {
+        /// The name of the limit, as in [`wgpu::Limits`].
+        limit: &'static str,
+        /// The minimum value of the limit needed by the simulation.
+        required: u64,
+        /// The value of the limit supported by the device.
+        supported: u64,
+    },
+    /// The grid needs more blocks than the device can fit in a single storage buffer.
+    GridOverflow {
+        /// The number of blocks the grid needs to be able to hold.
+        required_capacity: u32,
+        /// The maximum number of blocks fitting in a single storage buffer of the device.
+        max_capacity: u32,
+    },
+    /// The grid load factor isn’t in `]0, 1]`.
+    InvalidGridLoadFactor(f32),
+    /// The periodic axes of the simulation domain were changed after the grid was created.
+    PeriodicDomainChanged,
+    /// A gpu buffer couldn’t be read back.
+    Readback(String),
+    /// A checkpoint couldn’t be written, read, or restored.
+    Checkpoint(String),
+    /// The particles couldn’t be exported.
+    Export(String),
+}
+
+impl Error {
+    pub(crate) fn hot_reloading(err: impl fmt::Display) -> Self {
+        Self::HotReloading(err.to_string())
+    }
+
+    pub(crate) fn readback(err: impl fmt::Display) -> Self {
+        Self::Readback(err.to_string())
+    }
+
+    pub(crate) fn checkpoint(err: impl fmt::Display) -> Self {
+        Self::Checkpoint(err.to_string())
+    }
+
+    pub(crate) fn export(err: impl fmt::Display) -> Self {
+        Self::Export(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shader(err) => write!(f, "shader composition failed: {err}"),
+            Self::HotReloading(err) => write!(f, "shader hot-reloading failed: {err}"),
+            Self::DeviceLimit {
+                limit,
+                required,
+                supported,
+            } => write!(
+                f,
+                "the device limit `{limit}` is too low: {required} required, {supported} supported"
+            ),
+            Self::GridOverflow {
+                required_capacity,
+                max_capacity,
+            } => write!(
+                f,
+                "the grid needs {required_capacity} blocks but the device supports at most {max_capacity}"
+            ),
+            Self::InvalidGridLoadFactor(load_factor) => {
+                write!(f, "the grid load factor {load_factor} isn’t in ]0, 1]")
+            }
+            Self::PeriodicDomainChanged => write!(
+                f,
+                "the periodic axes of the domain can’t change after the grid is created"
+            ),
+            Self::Readback(err) => write!(f, "gpu buffer readback failed: {err}"),
+            Self::Checkpoint(err) => write!(f, "checkpoint failed: {err}"),
+            Self::Export(err) => write!(f, "particle export failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Shader(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ComposerError> for Error {
+    fn from(err: ComposerError) -> Self {
+        Self::Shader(err)
+    }
+}
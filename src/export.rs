@@ -0,0 +1,546 @@
+//! Export of the particles to the file formats of offline renderers.
+//!
+//! The particles can be written as PLY (ASCII or binary), legacy VTK polydata, or the
+//! classic Houdini BGEO format (as written by Partio). 2D particles are exported on the
+//! `z = 0` plane.
+
+use crate::error::Error;
+use crate::pipeline::MpmData;
+use crate::solver::{GpuParticleReadback, ParticleFields, ParticleSnapshot};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use wgpu::{Device, Queue};
+
+/// The file format of exported particles.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    PlyAscii,
+    /// Little-endian binary PLY.
+    PlyBinary,
+    /// ASCII legacy VTK polydata.
+    Vtk,
+    /// Uncompressed classic Houdini geometry (version 5), readable by Houdini and Partio.
+    Bgeo,
+}
+
+impl ExportFormat {
+    /// The file extension of this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::PlyAscii | Self::PlyBinary => "ply",
+            Self::Vtk => "vtk",
+            Self::Bgeo => "bgeo",
+        }
+    }
+}
+
+/// Selects the per-particle attributes exported alongside the positions.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ExportAttributes {
+    pub velocity: bool,
+    /// The volume ratio `J = det(F)` of the deformation gradient.
+    pub jacobian: bool,
+    /// The hardening variable of the plasticity model (see [`crate::models::PlasticState::hardening`]).
+    pub plastic_hardening: bool,
+    /// The [`crate::solver::ParticlePhase::phase`].
+    pub phase: bool,
+    /// The [`crate::models::ConstitutiveModel`], as an integer.
+    pub material_id: bool,
+}
+
+impl ExportAttributes {
+    /// Every attribute.
+    pub const ALL: Self = Self {
+        velocity: true,
+        jacobian: true,
+        plastic_hardening: true,
+        phase: true,
+        material_id: true,
+    };
+
+    /// The particle fields that need to be read back for exporting these attributes.
+    pub fn readback_fields(&self) -> ParticleFields {
+        ParticleFields {
+            positions: true,
+            dynamics: self.velocity || self.jacobian,
+            phases: self.phase,
+            plastic_states: self.plastic_hardening,
+            model_ids: self.material_id,
+        }
+    }
+}
+
+enum AttributeValues {
+    Float(Vec<f32>),
+    Int(Vec<i32>),
+    Vector(Vec<[f32; 3]>),
+}
+
+struct Attribute {
+    name: &'static str,
+    /// The name of the attribute in BGEO files, following the Houdini conventions.
+    bgeo_name: &'static str,
+    values: AttributeValues,
+}
+
+/// Particle positions and attributes, ready to be written in any [`ExportFormat`].
+pub struct ExportedParticles {
+    positions: Vec<[f32; 3]>,
+    attributes: Vec<Attribute>,
+}
+
+impl ExportedParticles {
+    /// Extracts the given `attributes` from a particle snapshot.
+    ///
+    /// Returns an error if the snapshot lacks one of the [`ExportAttributes::readback_fields`].
+    pub fn from_snapshot(
+        snapshot: &ParticleSnapshot,
+        attributes: ExportAttributes,
+    ) -> Result<Self, Error> {
+        let len = snapshot.len;
+        let check_len = |field: &str, field_len: usize| {
+            if field_len == len {
+                Ok(())
+            } else {
+                Err(Error::Export(format!(
+                    "the particle snapshot has {field_len} {field} for {len} particles"
+                )))
+            }
+        };
+
+        check_len("positions", snapshot.positions.len())?;
+        let positions = snapshot
+            .positions
+            .iter()
+            .map(|pt| to_3d(pt.as_slice()))
+            .collect();
+        let mut result = Self {
+            positions,
+            attributes: vec![],
+        };
+
+        if attributes.velocity || attributes.jacobian {
+            check_len("dynamics", snapshot.dynamics.len())?;
+        }
+        if attributes.velocity {
+            result.attributes.push(Attribute {
+                name: "velocity",
+                bgeo_name: "v",
+                values: AttributeValues::Vector(
+                    snapshot
+                        .dynamics
+                        .iter()
+                        .map(|dynamics| to_3d(dynamics.velocity.as_slice()))
+                        .collect(),
+                ),
+            });
+        }
+        if attributes.jacobian {
+            result.attributes.push(Attribute {
+                name: "J",
+                bgeo_name: "J",
+                values: AttributeValues::Float(
+                    snapshot
+                        .dynamics
+                        .iter()
+                        .map(|dynamics| dynamics.def_grad.determinant())
+                        .collect(),
+                ),
+            });
+        }
+        if attributes.plastic_hardening {
+            check_len("plastic states", snapshot.plastic_states.len())?;
+            result.attributes.push(Attribute {
+                name: "plastic_hardening",
+                bgeo_name: "hardening",
+                values: AttributeValues::Float(
+                    snapshot
+                        .plastic_states
+                        .iter()
+                        .map(|state| state.hardening())
+                        .collect(),
+                ),
+            });
+        }
+        if attributes.phase {
+            check_len("phases", snapshot.phases.len())?;
+            result.attributes.push(Attribute {
+                name: "phase",
+                bgeo_name: "phase",
+                values: AttributeValues::Float(
+                    snapshot.phases.iter().map(|phase| phase.phase).collect(),
+                ),
+            });
+        }
+        if attributes.material_id {
+            check_len("model ids", snapshot.model_ids.len())?;
+            result.attributes.push(Attribute {
+                name: "material_id",
+                bgeo_name: "material",
+                values: AttributeValues::Int(
+                    snapshot.model_ids.iter().map(|id| *id as i32).collect(),
+                ),
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// The number of exported particles.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Writes the particles in the given `format`.
+    pub fn write(&self, mut writer: impl Write, format: ExportFormat) -> Result<(), Error> {
+        match format {
+            ExportFormat::PlyAscii => self.write_ply(&mut writer, false),
+            ExportFormat::PlyBinary => self.write_ply(&mut writer, true),
+            ExportFormat::Vtk => self.write_vtk(&mut writer),
+            ExportFormat::Bgeo => self.write_bgeo(&mut writer),
+        }
+        .and_then(|_| writer.flush())
+        .map_err(Error::export)
+    }
+
+    fn write_ply(&self, w: &mut impl Write, binary: bool) -> std::io::Result<()> {
+        writeln!(w, "ply")?;
+        if binary {
+            writeln!(w, "format binary_little_endian 1.0")?;
+        } else {
+            writeln!(w, "format ascii 1.0")?;
+        }
+        writeln!(w, "comment exported by wgsparkl")?;
+        writeln!(w, "element vertex {}", self.len())?;
+        for axis in ["x", "y", "z"] {
+            writeln!(w, "property float {axis}")?;
+        }
+        for attr in &self.attributes {
+            match &attr.values {
+                AttributeValues::Float(_) => writeln!(w, "property float {}", attr.name)?,
+                AttributeValues::Int(_) => writeln!(w, "property int {}", attr.name)?,
+                AttributeValues::Vector(_) => {
+                    for axis in ["x", "y", "z"] {
+                        writeln!(w, "property float {}_{axis}", attr.name)?;
+                    }
+                }
+            }
+        }
+        writeln!(w, "end_header")?;
+
+        for (i, pt) in self.positions.iter().enumerate() {
+            if binary {
+                for x in pt {
+                    w.write_all(&x.to_le_bytes())?;
+                }
+                for attr in &self.attributes {
+                    match &attr.values {
+                        AttributeValues::Float(values) => w.write_all(&values[i].to_le_bytes())?,
+                        AttributeValues::Int(values) => w.write_all(&values[i].to_le_bytes())?,
+                        AttributeValues::Vector(values) => {
+                            for x in values[i] {
+                                w.write_all(&x.to_le_bytes())?;
+                            }
+                        }
+                    }
+                }
+            } else {
+                write!(w, "{} {} {}", pt[0], pt[1], pt[2])?;
+                for attr in &self.attributes {
+                    match &attr.values {
+                        AttributeValues::Float(values) => write!(w, " {}", values[i])?,
+                        AttributeValues::Int(values) => write!(w, " {}", values[i])?,
+                        AttributeValues::Vector(values) => {
+                            let [x, y, z] = values[i];
+                            write!(w, " {x} {y} {z}")?
+                        }
+                    }
+                }
+                writeln!(w)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_vtk(&self, w: &mut impl Write) -> std::io::Result<()> {
+        writeln!(w, "# vtk DataFile Version 3.0")?;
+        writeln!(w, "wgsparkl particles")?;
+        writeln!(w, "ASCII")?;
+        writeln!(w, "DATASET POLYDATA")?;
+        writeln!(w, "POINTS {} float", self.len())?;
+        for [x, y, z] in &self.positions {
+            writeln!(w, "{x} {y} {z}")?;
+        }
+        // One vertex cell per particle, so the points are rendered.
+        writeln!(w, "VERTICES {} {}", self.len(), self.len() * 2)?;
+        for i in 0..self.len() {
+            writeln!(w, "1 {i}")?;
+        }
+
+        if self.attributes.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(w, "POINT_DATA {}", self.len())?;
+        for attr in &self.attributes {
+            match &attr.values {
+                AttributeValues::Float(values) => {
+                    writeln!(w, "SCALARS {} float 1", attr.name)?;
+                    writeln!(w, "LOOKUP_TABLE default")?;
+                    for value in values {
+                        writeln!(w, "{value}")?;
+                    }
+                }
+                AttributeValues::Int(values) => {
+                    writeln!(w, "SCALARS {} int 1", attr.name)?;
+                    writeln!(w, "LOOKUP_TABLE default")?;
+                    for value in values {
+                        writeln!(w, "{value}")?;
+                    }
+                }
+                AttributeValues::Vector(values) => {
+                    writeln!(w, "VECTORS {} float", attr.name)?;
+                    for [x, y, z] in values {
+                        writeln!(w, "{x} {y} {z}")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_bgeo(&self, w: &mut impl Write) -> std::io::Result<()> {
+        // NOTE: every value of a BGEO file is big-endian.
+        const HOUDINI_FLOAT: i32 = 0;
+        const HOUDINI_INT: i32 = 1;
+        const HOUDINI_VECTOR: i32 = 5;
+
+        w.write_all(b"BgeoV")?;
+        let num_points = self.len() as i32;
+        let num_attributes = self.attributes.len() as i32;
+        // Version, points, primitives, point groups, primitive groups, point attributes,
+        // vertex attributes, primitive attributes, detail attributes.
+        for value in [5, num_points, 0, 0, 0, num_attributes, 0, 0, 0] {
+            w.write_all(&i32::to_be_bytes(value))?;
+        }
+
+        for attr in &self.attributes {
+            let (size, houdini_type) = match &attr.values {
+                AttributeValues::Float(_) => (1u16, HOUDINI_FLOAT),
+                AttributeValues::Int(_) => (1, HOUDINI_INT),
+                AttributeValues::Vector(_) => (3, HOUDINI_VECTOR),
+            };
+            w.write_all(&(attr.bgeo_name.len() as u16).to_be_bytes())?;
+            w.write_all(attr.bgeo_name.as_bytes())?;
+            w.write_all(&size.to_be_bytes())?;
+            w.write_all(&houdini_type.to_be_bytes())?;
+            // The default value of each component, zero for both floats and ints.
+            for _ in 0..size {
+                w.write_all(&[0; 4])?;
+            }
+        }
+
+        for (i, pt) in self.positions.iter().enumerate() {
+            // The homogeneous coordinate of the point comes after its position.
+            for x in [pt[0], pt[1], pt[2], 1.0] {
+                w.write_all(&x.to_be_bytes())?;
+            }
+            for attr in &self.attributes {
+                match &attr.values {
+                    AttributeValues::Float(values) => w.write_all(&values[i].to_be_bytes())?,
+                    AttributeValues::Int(values) => w.write_all(&values[i].to_be_bytes())?,
+                    AttributeValues::Vector(values) => {
+                        for x in values[i] {
+                            w.write_all(&x.to_be_bytes())?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // The end-of-geometry marker.
+        w.write_all(&[0x00, 0xff])
+    }
+}
+
+/// Writes one file per simulation frame, named `{prefix}.{frame:04}.{extension}`.
+///
+/// The staging buffers of the particle readback are allocated at the first frame and reused
+/// afterwards, so a writer must always be given the same [`MpmData`].
+pub struct FrameSequenceWriter {
+    directory: PathBuf,
+    prefix: String,
+    format: ExportFormat,
+    attributes: ExportAttributes,
+    next_frame: usize,
+    readback: Option<GpuParticleReadback>,
+}
+
+impl FrameSequenceWriter {
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        prefix: impl Into<String>,
+        format: ExportFormat,
+        attributes: ExportAttributes,
+    ) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: prefix.into(),
+            format,
+            attributes,
+            next_frame: 0,
+            readback: None,
+        }
+    }
+
+    /// The index of the frame written by the next call to [`Self::write_frame`].
+    pub fn next_frame(&self) -> usize {
+        self.next_frame
+    }
+
+    /// The path of the file of the given `frame`.
+    pub fn frame_path(&self, frame: usize) -> PathBuf {
+        self.directory.join(format!(
+            "{}.{frame:04}.{}",
+            self.prefix,
+            self.format.extension()
+        ))
+    }
+
+    /// Reads back the particles of `data` and writes them as the next frame.
+    ///
+    /// This waits for all the simulation steps already submitted to the `queue` to complete.
+    /// Returns the path of the written file.
+    pub async fn write_frame(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        data: &MpmData,
+    ) -> Result<PathBuf, Error> {
+        let fields = self.attributes.readback_fields();
+        let readback = self
+            .readback
+            .get_or_insert_with(|| GpuParticleReadback::new(device, &data.particles, fields));
+        let mut encoder = device.create_command_encoder(&Default::default());
+        readback.queue_readback(&mut encoder, &data.particles, data.models());
+        queue.submit(Some(encoder.finish()));
+        let snapshot = readback.read(device).await?;
+        let particles = ExportedParticles::from_snapshot(&snapshot, self.attributes)?;
+
+        std::fs::create_dir_all(&self.directory).map_err(Error::export)?;
+        let path = self.frame_path(self.next_frame);
+        let file = File::create(&path).map_err(Error::export)?;
+        particles.write(BufWriter::new(file), self.format)?;
+        self.next_frame += 1;
+        Ok(path)
+    }
+}
+
+fn to_3d(coords: &[f32]) -> [f32; 3] {
+    let mut result = [0.0; 3];
+    result[..coords.len()].copy_from_slice(coords);
+    result
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::{ExportAttributes, ExportFormat, FrameSequenceWriter};
+    use crate::pipeline::MpmData;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::particle_block;
+    use nalgebra::vector;
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+    use wgcore::gpu::GpuInstance;
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn export_frames_in_every_format() {
+        let gpu = GpuInstance::new().await.unwrap();
+
+        let cell_width = 1.0;
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
+
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let data = MpmData::new(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            cell_width,
+            100_000,
+        )
+        .unwrap();
+
+        let directory = std::env::temp_dir().join("wgsparkl_export_test");
+        let formats = [
+            ExportFormat::PlyAscii,
+            ExportFormat::PlyBinary,
+            ExportFormat::Vtk,
+            ExportFormat::Bgeo,
+        ];
+
+        for (i, format) in formats.into_iter().enumerate() {
+            let mut writer = FrameSequenceWriter::new(
+                &directory,
+                format!("particles{i}"),
+                format,
+                ExportAttributes::ALL,
+            );
+            let path = writer
+                .write_frame(gpu.device(), gpu.queue(), &data)
+                .await
+                .unwrap();
+            assert_eq!(path, writer.frame_path(0));
+            assert_eq!(writer.next_frame(), 1);
+            let bytes = std::fs::read(&path).unwrap();
+
+            match format {
+                ExportFormat::PlyAscii => {
+                    let text = String::from_utf8(bytes).unwrap();
+                    assert!(text.starts_with("ply\nformat ascii 1.0\n"));
+                    assert!(text.contains("element vertex 1000\n"));
+                    // The first particle is at the origin, at rest, undeformed, without
+                    // plasticity (zero hardening), and without phase (the default).
+                    let header_end = text.find("end_header\n").unwrap() + "end_header\n".len();
+                    let first = text[header_end..].lines().next().unwrap();
+                    assert_eq!(first, "0 0 0 0 0 0 1 0 0 0");
+                }
+                ExportFormat::PlyBinary => {
+                    let header = b"end_header\n";
+                    let header_end = bytes
+                        .windows(header.len())
+                        .position(|w| w == header)
+                        .unwrap()
+                        + header.len();
+                    // 3 position components, 3 velocity components, and 4 scalars.
+                    assert_eq!(bytes.len() - header_end, 1000 * 10 * 4);
+                }
+                ExportFormat::Vtk => {
+                    let text = String::from_utf8(bytes).unwrap();
+                    assert!(text.starts_with("# vtk DataFile Version 3.0\n"));
+                    assert!(text.contains("POINTS 1000 float\n"));
+                    assert!(text.contains("VECTORS velocity float\n"));
+                    assert!(text.contains("SCALARS material_id int 1\n"));
+                }
+                ExportFormat::Bgeo => {
+                    assert!(bytes.starts_with(b"BgeoV"));
+                    assert_eq!(i32::from_be_bytes(bytes[9..13].try_into().unwrap()), 1000);
+                    assert!(bytes.ends_with(&[0x00, 0xff]));
+                }
+            }
+        }
+    }
+}
@@ -1,17 +1,24 @@
+use crate::error::Error;
 use crate::grid::prefix_sum::{PrefixSumWorkspace, WgPrefixSum};
 use crate::grid::sort::WgSort;
-use crate::solver::{GpuParticles, GpuRigidParticles, WgParams};
+use crate::solver::{GpuParticles, GpuRigidParticles, SimulationDomain, WgParams};
 use crate::{dim_shader_defs, substitute_aliases};
+use encase::ShaderSize;
+use rapier::math::DIM;
 use std::sync::Arc;
 use wgcore::kernel::{KernelInvocationBuilder, KernelInvocationQueue};
 use wgcore::tensor::{GpuScalar, GpuVector};
 use wgcore::Shader;
 use wgpu::util::DispatchIndirectArgs;
-use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages, ComputePipeline, Device};
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoder, ComputePipeline, Device,
+};
 
 #[cfg(target_os = "macos")]
 use crate::grid::sort::TouchParticleBlocks;
 
+const NODES_PER_BLOCK: u32 = 64; // 8 * 8 in 2D and 4 * 4 * 4 in 3D.
+
 #[derive(Shader)]
 #[shader(
     derive(WgParams),
@@ -40,82 +47,79 @@ impl WgGrid {
     ) {
         const GRID_WORKGROUP_SIZE: u32 = 64;
 
-        // Retry until we allocated enough room on the sparse grid for all the blocks.
-        let mut sparse_grid_has_the_correct_size = false;
-        while !sparse_grid_has_the_correct_size {
-            // - Reset next grid’s hashmap.
-            // - Reset grid.num_active_blocks to 0.
-            // - Run touch_particle_blocks on the next grid.
-            // - Readback num_active_blocks.
-            // - Update the hashmap & grid buffer sizes if its occupancy is to high.
-
-            // NOTE: num_active_blocks := 0 is set in reset_hmap.
-            KernelInvocationBuilder::new(queue, &self.reset_hmap)
-                .bind0([grid.meta.buffer(), grid.hmap_entries.buffer()])
-                .queue(grid.cpu_meta.hmap_capacity.div_ceil(GRID_WORKGROUP_SIZE));
-
-            #[cfg(not(target_os = "macos"))]
-            let touch_particle_blocks_pipeline = &sort_module.touch_particle_blocks;
-            #[cfg(target_os = "macos")]
-            let touch_particle_blocks_pipeline = &touch_particle_blocks.touch_particle_blocks;
-
-            KernelInvocationBuilder::new(queue, touch_particle_blocks_pipeline)
-                .bind_at(
-                    0,
-                    [
-                        (grid.meta.buffer(), 0),
-                        (grid.hmap_entries.buffer(), 1),
-                        (grid.active_blocks.buffer(), 2),
-                        (grid.debug.buffer(), 8),
-                    ],
-                )
-                .bind(1, [particles.positions.buffer()])
-                .queue((particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE));
-
-            // Ensure blocks exist wherever we have rigid particles that might affect
-            // other blocks. This is done in two passes:
-            // 1. Mark all rigid particles that need to ensure it’s associated block exists
-            // 2. Touch the blocks with marked rigid particles.
-            KernelInvocationBuilder::new(queue, &sort_module.mark_rigid_particles_needing_block)
-                .bind_at(
-                    0,
-                    [(grid.meta.buffer(), 0), (grid.hmap_entries.buffer(), 1)],
-                )
-                .bind_at(
-                    1,
-                    [
-                        (rigid_particles.sample_points.buffer(), 0),
-                        (rigid_particles.rigid_particle_needs_block.buffer(), 6),
-                    ],
-                )
-                .queue((rigid_particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE));
-
-            #[cfg(not(target_os = "macos"))]
-            let touch_rigid_particle_blocks = &sort_module.touch_rigid_particle_blocks;
-            #[cfg(target_os = "macos")]
-            let touch_rigid_particle_blocks = &touch_particle_blocks.touch_rigid_particle_blocks;
-            KernelInvocationBuilder::new(queue, touch_rigid_particle_blocks)
-                .bind_at(
-                    0,
-                    [
-                        (grid.meta.buffer(), 0),
-                        (grid.hmap_entries.buffer(), 1),
-                        (grid.active_blocks.buffer(), 2),
-                        (grid.debug.buffer(), 8),
-                    ],
-                )
-                .bind_at(
-                    1,
-                    [
-                        (rigid_particles.sample_points.buffer(), 0),
-                        (rigid_particles.rigid_particle_needs_block.buffer(), 6),
-                    ],
-                )
-                .queue((rigid_particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE));
-
-            // TODO: handle grid buffer resizing
-            sparse_grid_has_the_correct_size = true;
-        }
+        // NOTE: the blocks that don’t fit in the sparse grid are ignored. We can’t read back the
+        //       grid occupancy here since the queue may be encoded several times per frame, so
+        //       the grid is resized between steps instead (see `GpuGrid::grow_if_needed`).
+
+        // NOTE: num_active_blocks := 0 is set in reset_hmap.
+        KernelInvocationBuilder::new(queue, &self.reset_hmap)
+            .bind0([grid.meta.buffer(), grid.hmap_entries.buffer()])
+            .queue(grid.cpu_meta.hmap_capacity.div_ceil(GRID_WORKGROUP_SIZE));
+
+        #[cfg(not(target_os = "macos"))]
+        let touch_particle_blocks_pipeline = &sort_module.touch_particle_blocks;
+        #[cfg(target_os = "macos")]
+        let touch_particle_blocks_pipeline = &touch_particle_blocks.touch_particle_blocks;
+
+        KernelInvocationBuilder::new(queue, touch_particle_blocks_pipeline)
+            .bind_at(
+                0,
+                [
+                    (grid.meta.buffer(), 0),
+                    (grid.hmap_entries.buffer(), 1),
+                    (grid.active_blocks.buffer(), 2),
+                    (grid.debug.buffer(), 8),
+                ],
+            )
+            .bind_at(
+                1,
+                [
+                    (particles.positions.buffer(), 0),
+                    (particles.count.buffer(), 7),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        // Ensure blocks exist wherever we have rigid particles that might affect
+        // other blocks. This is done in two passes:
+        // 1. Mark all rigid particles that need to ensure it’s associated block exists
+        // 2. Touch the blocks with marked rigid particles.
+        KernelInvocationBuilder::new(queue, &sort_module.mark_rigid_particles_needing_block)
+            .bind_at(
+                0,
+                [(grid.meta.buffer(), 0), (grid.hmap_entries.buffer(), 1)],
+            )
+            .bind_at(
+                1,
+                [
+                    (rigid_particles.sample_points.buffer(), 0),
+                    (rigid_particles.rigid_particle_needs_block.buffer(), 6),
+                ],
+            )
+            .queue((rigid_particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE));
+
+        #[cfg(not(target_os = "macos"))]
+        let touch_rigid_particle_blocks = &sort_module.touch_rigid_particle_blocks;
+        #[cfg(target_os = "macos")]
+        let touch_rigid_particle_blocks = &touch_particle_blocks.touch_rigid_particle_blocks;
+        KernelInvocationBuilder::new(queue, touch_rigid_particle_blocks)
+            .bind_at(
+                0,
+                [
+                    (grid.meta.buffer(), 0),
+                    (grid.hmap_entries.buffer(), 1),
+                    (grid.active_blocks.buffer(), 2),
+                    (grid.debug.buffer(), 8),
+                ],
+            )
+            .bind_at(
+                1,
+                [
+                    (rigid_particles.sample_points.buffer(), 0),
+                    (rigid_particles.rigid_particle_needs_block.buffer(), 6),
+                ],
+            )
+            .queue((rigid_particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE));
 
         // - Launch update_block_particle_count
         // - Launch copy_particle_len_to_scan_value
@@ -137,7 +141,7 @@ impl WgGrid {
             )
             .queue(1);
 
-        let n_groups = (particles.len() as u32).div_ceil(GRID_WORKGROUP_SIZE);
+        let n_groups = particles.indirect_n_groups.clone();
         let n_block_groups = grid.indirect_n_blocks_groups.clone();
 
         KernelInvocationBuilder::new(queue, &sort_module.update_block_particle_count)
@@ -149,8 +153,14 @@ impl WgGrid {
                     grid.active_blocks.buffer(),
                 ],
             )
-            .bind(1, [particles.positions.buffer()])
-            .queue(n_groups);
+            .bind_at(
+                1,
+                [
+                    (particles.positions.buffer(), 0),
+                    (particles.count.buffer(), 7),
+                ],
+            )
+            .queue_indirect(n_groups.clone());
 
         KernelInvocationBuilder::new(queue, &sort_module.copy_particles_len_to_scan_value)
             .bind_at(
@@ -193,16 +203,17 @@ impl WgGrid {
                     (grid.nodes_linked_lists.buffer(), 6),
                 ],
             )
-            .bind(
+            .bind_at(
                 1,
                 [
-                    particles.positions.buffer(),
-                    grid.scan_values.buffer(),
-                    particles.sorted_ids.buffer(),
-                    particles.node_linked_lists.buffer(),
+                    (particles.positions.buffer(), 0),
+                    (grid.scan_values.buffer(), 1),
+                    (particles.sorted_ids.buffer(), 2),
+                    (particles.node_linked_lists.buffer(), 3),
+                    (particles.count.buffer(), 7),
                 ],
             )
-            .queue(n_groups);
+            .queue_indirect(n_groups);
     }
 }
 
@@ -213,6 +224,25 @@ pub struct GpuGridMetadata {
     cell_width: f32,
     hmap_capacity: u32,
     capacity: u32,
+    periodic_first_block: nalgebra::Vector4<i32>,
+    periodic_num_blocks: nalgebra::Vector4<i32>,
+}
+
+impl GpuGridMetadata {
+    /// The number of blocks touched by the particles during the last grid sort.
+    pub fn num_active_blocks(&self) -> u32 {
+        self.num_active_blocks
+    }
+
+    /// The maximum number of blocks the grid can hold.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The width of a grid cell.
+    pub fn cell_width(&self) -> f32 {
+        self.cell_width
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, encase::ShaderType)]
@@ -220,6 +250,7 @@ pub struct GpuGridMetadata {
 pub struct GpuGridNode {
     momentum_velocity_mass: nalgebra::Vector4<f32>,
     cdf: GpuGridNodeCdf,
+    temperature: f32,
 }
 
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq)]
@@ -259,13 +290,15 @@ pub struct GpuActiveBlockHeader {
 #[repr(C)]
 pub struct GpuGridNodeCdf {
     pub distance: f32,
-    pub affinities: u32,
+    pub affinities: nalgebra::Vector2<u32>,
     pub closest_id: u32,
 }
 
 pub struct GpuGrid {
     pub cpu_meta: GpuGridMetadata,
     pub meta: GpuScalar<GpuGridMetadata>,
+    /// Staging buffer for reading back the grid metadata (see [`Self::queue_meta_readback`]).
+    pub meta_staging: GpuVector<GpuGridMetadata>,
     pub hmap_entries: GpuVector<GpuGridHashMapEntry>,
     pub nodes: GpuVector<GpuGridNode>,
     pub active_blocks: GpuVector<GpuActiveBlockHeader>,
@@ -279,19 +312,62 @@ pub struct GpuGrid {
 
 impl GpuGrid {
     pub fn with_capacity(device: &Device, capacity: u32, cell_width: f32) -> Self {
-        const NODES_PER_BLOCK: u32 = 64; // 8 * 8 in 2D and 4 * 4 * 4 in 3D.
-        let capacity = capacity.next_power_of_two();
+        Self::with_domain(device, capacity, cell_width, &SimulationDomain::unbounded())
+    }
+
+    /// Creates a grid with block ids wrapping around the periodic axes of the `domain`.
+    ///
+    /// Along each periodic axis, the domain bounds are rounded to the closest multiple of the
+    /// block width (8 cells in 2D, 4 cells in 3D).
+    pub fn with_domain(
+        device: &Device,
+        capacity: u32,
+        cell_width: f32,
+        domain: &SimulationDomain,
+    ) -> Self {
+        #[cfg(feature = "dim2")]
+        const BLOCK_WIDTH: f32 = 8.0; // In number of cells.
+        #[cfg(feature = "dim3")]
+        const BLOCK_WIDTH: f32 = 4.0; // In number of cells.
+
+        let block_width = cell_width * BLOCK_WIDTH;
+        let mut periodic_first_block = nalgebra::Vector4::zeros();
+        let mut periodic_num_blocks = nalgebra::Vector4::zeros();
+
+        for i in 0..DIM {
+            if domain.is_periodic(i) {
+                let first_block = (domain.mins[i] / block_width).round() as i32;
+                let last_block = (domain.maxs[i] / block_width).round() as i32;
+                periodic_first_block[i] = first_block;
+                periodic_num_blocks[i] = (last_block - first_block).max(1);
+            }
+        }
+
         let cpu_meta = GpuGridMetadata {
             num_active_blocks: 0,
             cell_width,
             hmap_capacity: capacity,
             capacity,
+            periodic_first_block,
+            periodic_num_blocks,
+        };
+        Self::with_metadata(device, cpu_meta)
+    }
+
+    fn with_metadata(device: &Device, cpu_meta: GpuGridMetadata) -> Self {
+        let capacity = cpu_meta.capacity.next_power_of_two();
+        let cpu_meta = GpuGridMetadata {
+            hmap_capacity: capacity,
+            capacity,
+            ..cpu_meta
         };
         let meta = GpuScalar::init(
             device,
             cpu_meta,
             BufferUsages::STORAGE | BufferUsages::COPY_SRC,
         );
+        let meta_staging =
+            GpuVector::uninit(device, 1, BufferUsages::MAP_READ | BufferUsages::COPY_DST);
         let hmap_entries = GpuVector::uninit(device, capacity, BufferUsages::STORAGE);
         let nodes =
             GpuVector::uninit_encased(device, capacity * NODES_PER_BLOCK, BufferUsages::STORAGE);
@@ -318,6 +394,7 @@ impl GpuGrid {
         Self {
             cpu_meta,
             meta,
+            meta_staging,
             hmap_entries,
             nodes,
             active_blocks,
@@ -329,6 +406,91 @@ impl GpuGrid {
             debug,
         }
     }
+
+    /// The maximum number of blocks this grid can hold.
+    pub fn capacity(&self) -> u32 {
+        self.cpu_meta.capacity
+    }
+
+    /// The maximum grid capacity supported by the `device`.
+    ///
+    /// This is the largest power of two such that the grid nodes fit in a single storage buffer
+    /// binding.
+    pub fn max_capacity(device: &Device) -> u32 {
+        let max_binding_size = device.limits().max_storage_buffer_binding_size as u64;
+        let block_size = NODES_PER_BLOCK as u64 * <GpuGridNode as ShaderSize>::SHADER_SIZE.get();
+        let max_blocks = (max_binding_size / block_size).clamp(1, 1 << 31) as u32;
+        1 << max_blocks.ilog2()
+    }
+
+    /// Queues the copy of the grid metadata into [`Self::meta_staging`].
+    ///
+    /// This must be encoded after the simulation step for [`Self::read_num_active_blocks`]
+    /// to return the occupancy of the grid during that step.
+    pub fn queue_meta_readback(&self, encoder: &mut CommandEncoder) {
+        encoder.copy_buffer_to_buffer(
+            self.meta.buffer(),
+            0,
+            self.meta_staging.buffer(),
+            0,
+            std::mem::size_of::<GpuGridMetadata>() as BufferAddress,
+        );
+    }
+
+    /// Reads back the number of active blocks copied by the last [`Self::queue_meta_readback`].
+    pub async fn read_num_active_blocks(&self, device: &Device) -> Result<u32, Error> {
+        let meta = self
+            .meta_staging
+            .read(device)
+            .await
+            .map_err(Error::readback)?;
+        meta.first()
+            .map(|meta| meta.num_active_blocks())
+            .ok_or_else(|| Error::Readback("empty grid metadata".to_string()))
+    }
+
+    /// Reallocates the grid buffers if `num_active_blocks` exceeds the fraction `load_factor`
+    /// of the grid capacity, or if the grid is saturated (`num_active_blocks` reached its
+    /// capacity), whatever the `load_factor`.
+    ///
+    /// The grid content is lost, which is fine since it is rebuilt from the particles at the
+    /// beginning of each step. Returns `true` if the grid was reallocated, or
+    /// [`Error::GridOverflow`] if it is already at the [`Self::max_capacity`] of the device.
+    pub fn grow_if_needed(
+        &mut self,
+        device: &Device,
+        num_active_blocks: u32,
+        load_factor: f32,
+    ) -> Result<bool, Error> {
+        let capacity = self.capacity();
+        // NOTE: a saturated grid may have dropped blocks, so it must grow even if the load
+        //       factor is 1.0.
+        if num_active_blocks < capacity && num_active_blocks as f32 <= capacity as f32 * load_factor
+        {
+            return Ok(false);
+        }
+
+        // NOTE: if the grid is saturated, we don’t know how many blocks were dropped so
+        //       we at least double the capacity.
+        let required = (num_active_blocks as f32 / load_factor).ceil() as u32;
+        let max_capacity = Self::max_capacity(device);
+        if capacity >= max_capacity {
+            return Err(Error::GridOverflow {
+                required_capacity: required,
+                max_capacity,
+            });
+        }
+        let new_capacity = required.max(capacity * 2).min(max_capacity);
+        *self = Self::with_metadata(
+            device,
+            GpuGridMetadata {
+                num_active_blocks: 0,
+                capacity: new_capacity,
+                ..self.cpu_meta
+            },
+        );
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -336,8 +498,8 @@ impl GpuGrid {
 mod test {
     use super::{GpuGrid, PrefixSumWorkspace, WgGrid, WgPrefixSum};
     use crate::grid::sort::WgSort;
-    use crate::models::ElasticCoefficients;
-    use crate::solver::{GpuParticles, GpuRigidParticles, Particle, ParticleDynamics};
+    use crate::solver::{GpuParticles, GpuRigidParticles};
+    use crate::test_utils::particle_block;
     use nalgebra::vector;
     use wgcore::gpu::GpuInstance;
     use wgcore::kernel::KernelInvocationQueue;
@@ -353,21 +515,7 @@ mod test {
         let sort_module = WgSort::from_device(gpu.device()).unwrap();
 
         let cell_width = 1.0;
-        let mut cpu_particles = vec![];
-        for i in 0..10 {
-            for j in 0..10 {
-                for k in 0..10 {
-                    let position = vector![i as f32, j as f32, k as f32] / cell_width / 2.0;
-                    cpu_particles.push(Particle {
-                        position,
-                        dynamics: ParticleDynamics::with_density(cell_width / 4.0, 1.0),
-                        model: ElasticCoefficients::from_young_modulus(100_000.0, 0.33),
-                        plasticity: None,
-                        phase: None,
-                    });
-                }
-            }
-        }
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
 
         let particles = GpuParticles::from_particles(gpu.device(), &cpu_particles);
         let grid = GpuGrid::with_capacity(gpu.device(), 100_000, cell_width);
@@ -400,4 +548,70 @@ mod test {
             println!("Grid sort gpu time: {}", t0.elapsed().as_secs_f32());
         }
     }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn gpu_grid_grows_when_saturated() {
+        grow_until_fits(0.75).await;
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn gpu_grid_grows_when_saturated_with_full_load_factor() {
+        grow_until_fits(1.0).await;
+    }
+
+    async fn grow_until_fits(load_factor: f32) {
+        let gpu = GpuInstance::new().await.unwrap();
+        let prefix_sum_module = WgPrefixSum::from_device(gpu.device()).unwrap();
+        let grid_module = WgGrid::from_device(gpu.device()).unwrap();
+        let sort_module = WgSort::from_device(gpu.device()).unwrap();
+        #[cfg(target_os = "macos")]
+        let touch_particle_blocks =
+            crate::grid::sort::TouchParticleBlocks::from_device(gpu.device());
+
+        // Particles spread over 10x10x10 blocks (of 4x4x4 cells).
+        let cell_width = 1.0;
+        let cpu_particles = particle_block(10, 4.0, vector![0.0, 0.0, 0.0]);
+
+        let particles = GpuParticles::from_particles(gpu.device(), &cpu_particles);
+        let rigid_particles = GpuRigidParticles::new(gpu.device());
+        let mut grid = GpuGrid::with_capacity(gpu.device(), 16, cell_width);
+        let mut prefix_sum = PrefixSumWorkspace::with_capacity(gpu.device(), 16);
+        let mut num_active_blocks = 0;
+
+        for _ in 0..10 {
+            let mut queue = KernelInvocationQueue::new(gpu.device());
+            grid_module.queue_sort(
+                &particles,
+                &rigid_particles,
+                &grid,
+                &mut prefix_sum,
+                &sort_module,
+                #[cfg(target_os = "macos")]
+                &touch_particle_blocks,
+                &prefix_sum_module,
+                &mut queue,
+            );
+
+            let mut encoder = gpu.device().create_command_encoder(&Default::default());
+            queue.encode(&mut encoder, None);
+            grid.queue_meta_readback(&mut encoder);
+            gpu.queue().submit(Some(encoder.finish()));
+
+            num_active_blocks = grid.read_num_active_blocks(gpu.device()).await.unwrap();
+            if !grid
+                .grow_if_needed(gpu.device(), num_active_blocks, load_factor)
+                .unwrap()
+            {
+                break;
+            }
+            prefix_sum.reserve(gpu.device(), grid.capacity());
+        }
+
+        // Every particle touches the 2x2x2 blocks around it, none of them should have been dropped.
+        assert!(num_active_blocks >= 1000);
+        assert!(num_active_blocks < grid.capacity());
+        assert!(num_active_blocks as f32 <= grid.capacity() as f32 * load_factor);
+    }
 }
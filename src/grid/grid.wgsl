@@ -79,22 +79,64 @@ struct NodePhysicalId {
  */
 const NONE: u32 = 0xffffffffu;
 
+// NOTE: the key is wrapped around the periodic axes so that every periodic
+//       image of a block maps to the same hashmap entry.
 #if DIM == 2
 fn pack_key(key: BlockVirtualId) -> u32 {
-    return (bitcast<u32>(key.id.x + 0x00007fff) & 0x0000ffffu) |
-    ((bitcast<u32>(key.id.y + 0x00007fff) & 0x0000ffffu) << 16);
+    let id = wrap_block(key).id;
+    return (bitcast<u32>(id.x + 0x00007fff) & 0x0000ffffu) |
+    ((bitcast<u32>(id.y + 0x00007fff) & 0x0000ffffu) << 16);
 }
 #else
 fn pack_key(key: BlockVirtualId) -> u32 {
+    let id = wrap_block(key).id;
     // NOTE: we give the X and Z axis one more bit than Y.
     //       This is assuming Y-up and the fact that we want
     //       more room on the X-Z plane rather than along the up axis.
-    return (bitcast<u32>(key.id.x + 0x000003ff) & 0x000007ffu) |
-    ((bitcast<u32>(key.id.y + 0x000001ff) & 0x000003ffu) << 11) |
-    ((bitcast<u32>(key.id.z + 0x000003ff) & 0x000007ffu) << 21);
+    return (bitcast<u32>(id.x + 0x000003ff) & 0x000007ffu) |
+    ((bitcast<u32>(id.y + 0x000001ff) & 0x000003ffu) << 11) |
+    ((bitcast<u32>(id.z + 0x000003ff) & 0x000007ffu) << 21);
 }
 #endif
 
+// The periodic image of `block` lying within the periodic range of the grid.
+//
+// Axes that aren’t periodic are left unchanged.
+fn wrap_block(block: BlockVirtualId) -> BlockVirtualId {
+#if DIM == 2
+    let first = grid.periodic_first_block.xy;
+    let len = grid.periodic_num_blocks.xy;
+    let safe_len = max(len, vec2(1));
+    let periodic = len > vec2(0);
+#else
+    let first = grid.periodic_first_block.xyz;
+    let len = grid.periodic_num_blocks.xyz;
+    let safe_len = max(len, vec3(1));
+    let periodic = len > vec3(0);
+#endif
+    // NOTE: `%` has the sign of the dividend so we need a second `%` for negative ids.
+    let wrapped = first + ((block.id - first) % safe_len + safe_len) % safe_len;
+    return BlockVirtualId(select(block.id, wrapped, periodic));
+}
+
+// The periodic image of the point `pt` lying within the periodic range of the grid.
+//
+// Axes that aren’t periodic are left unchanged.
+fn wrap_point(pt: Vector) -> Vector {
+#if DIM == 2
+    let block_width = grid.cell_width * 8.0;
+    let first = vec2<f32>(grid.periodic_first_block.xy) * block_width;
+    let len = vec2<f32>(grid.periodic_num_blocks.xy) * block_width;
+#else
+    let block_width = grid.cell_width * 4.0;
+    let first = vec3<f32>(grid.periodic_first_block.xyz) * block_width;
+    let len = vec3<f32>(grid.periodic_num_blocks.xyz) * block_width;
+#endif
+    let safe_len = select(len, Vector(1.0), len == Vector(0.0));
+    let wrapped = pt - floor((pt - first) / safe_len) * safe_len;
+    return select(pt, wrapped, len > Vector(0.0));
+}
+
 fn hash(packed_key: u32) -> u32 {
     // Murmur3 hash function.
     var key = packed_key;
@@ -225,33 +267,128 @@ struct Grid {
     // NOTE: the hashmap capacity MUST be a power of 2.
     hmap_capacity: u32,
     capacity: u32,
-}
-
-const AFFINITY_BITS_MASK: u32 = 0x0000ffffu;
-const SIGN_BITS_SHIFT: u32 = 16;
+    // The first block, and the number of blocks, of the periodic range along each axis.
+    // Block ids wrap around this range along the axes with a nonzero number of blocks.
+    // The last component is unused.
+    periodic_first_block: vec4<i32>,
+    periodic_num_blocks: vec4<i32>,
+}
+
+// The maximum number of colliders a node (or particle) can have an affinity with.
+// If more colliders are nearby, the collision detection keeps the closest ones, but
+// `add_affinity` and `merge_affinities` keep the first `MAX_AFFINITIES` colliders found.
+const MAX_AFFINITIES: u32 = 4;
+// Each affinity is packed into 16 bits: the first 15 bits contain the collider index + 1
+// (so that 0 means "no affinity"), and the last bit contains the sign.
+const AFFINITY_ID_MASK: u32 = 0x7fffu;
+const AFFINITY_SIGN_BIT: u32 = 0x8000u;
+const NO_AFFINITY: vec2<u32> = vec2(0u, 0u);
 
 struct NodeCdf {
     distance: f32,
-    // Two bits per collider.
-    // The 16 first bits are for affinity, the 16 last are for signs.
-    affinities: u32,
+    // Up to `MAX_AFFINITIES` colliders (and their sign) packed two per component.
+    affinities: vec2<u32>,
     // Index to the closest collider.
     closest_id: u32,
 }
 
-fn affinity_bit(i_collider: u32, affinity: u32) -> bool {
-    return (affinity & (1u << i_collider)) != 0;
+// The packed affinity at the given slot (zero if the slot is empty).
+fn affinity_slot(affinities: vec2<u32>, i_slot: u32) -> u32 {
+    return (affinities[i_slot / 2u] >> ((i_slot % 2u) * 16u)) & 0xffffu;
+}
+
+fn set_affinity_slot(affinities: vec2<u32>, i_slot: u32, slot: u32) -> vec2<u32> {
+    let shift = (i_slot % 2u) * 16u;
+    var result = affinities;
+    result[i_slot / 2u] = (result[i_slot / 2u] & ~(0xffffu << shift)) | (slot << shift);
+    return result;
 }
 
-fn sign_bit(i_collider: u32, affinity: u32) -> bool {
-    return ((affinity >> SIGN_BITS_SHIFT) & (1u << i_collider)) != 0;
+fn pack_affinity(i_collider: u32, sign: bool) -> u32 {
+    return (i_collider + 1u) | select(0u, AFFINITY_SIGN_BIT, sign);
+}
+
+// The collider index of a non-empty affinity slot.
+fn slot_collider(slot: u32) -> u32 {
+    return (slot & AFFINITY_ID_MASK) - 1u;
+}
+
+fn slot_sign(slot: u32) -> bool {
+    return (slot & AFFINITY_SIGN_BIT) != 0u;
+}
+
+// Index of the slot containing the affinity with the given collider, or `MAX_AFFINITIES` if
+// there is no affinity with this collider.
+fn find_affinity_slot(affinities: vec2<u32>, i_collider: u32) -> u32 {
+    for (var i = 0u; i < MAX_AFFINITIES; i += 1u) {
+        if (affinity_slot(affinities, i) & AFFINITY_ID_MASK) == i_collider + 1u {
+            return i;
+        }
+    }
+    return MAX_AFFINITIES;
 }
 
-fn affinities_are_compatible(affinity1: u32, affinity2: u32) -> bool {
-    let affinities_in_common = affinity1 & affinity2 & AFFINITY_BITS_MASK;
-    let signs1 = (affinity1 >> SIGN_BITS_SHIFT) & affinities_in_common;
-    let signs2 = (affinity2 >> SIGN_BITS_SHIFT) & affinities_in_common;
-    return signs1 == signs2;
+fn affinity_bit(i_collider: u32, affinities: vec2<u32>) -> bool {
+    return find_affinity_slot(affinities, i_collider) != MAX_AFFINITIES;
+}
+
+fn sign_bit(i_collider: u32, affinities: vec2<u32>) -> bool {
+    let i_slot = find_affinity_slot(affinities, i_collider);
+    return i_slot != MAX_AFFINITIES && slot_sign(affinity_slot(affinities, i_slot));
+}
+
+// Adds an affinity with the given collider. If there is already an affinity with this
+// collider, the signs are combined with a logical "or". If all the slots are occupied,
+// the affinity is ignored.
+fn add_affinity(affinities: vec2<u32>, i_collider: u32, sign: bool) -> vec2<u32> {
+    let packed = pack_affinity(i_collider, sign);
+    for (var i = 0u; i < MAX_AFFINITIES; i += 1u) {
+        let slot = affinity_slot(affinities, i);
+        if slot == 0u || (slot & AFFINITY_ID_MASK) == i_collider + 1u {
+            return set_affinity_slot(affinities, i, slot | packed);
+        }
+    }
+    return affinities;
+}
+
+fn merge_affinities(affinities1: vec2<u32>, affinities2: vec2<u32>) -> vec2<u32> {
+    var result = affinities1;
+    for (var i = 0u; i < MAX_AFFINITIES; i += 1u) {
+        let slot = affinity_slot(affinities2, i);
+        if slot != 0u {
+            result = add_affinity(result, slot_collider(slot), slot_sign(slot));
+        }
+    }
+    return result;
+}
+
+// The same affinities, without their signs.
+fn unsigned_affinities(affinities: vec2<u32>) -> vec2<u32> {
+    return affinities & vec2(~(AFFINITY_SIGN_BIT | (AFFINITY_SIGN_BIT << 16u)));
+}
+
+fn have_common_affinity(affinities1: vec2<u32>, affinities2: vec2<u32>) -> bool {
+    for (var i = 0u; i < MAX_AFFINITIES; i += 1u) {
+        let slot = affinity_slot(affinities1, i);
+        if slot != 0u && affinity_bit(slot_collider(slot), affinities2) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Checks that the affinities with the colliders shared by both sets have the same signs.
+fn affinities_are_compatible(affinities1: vec2<u32>, affinities2: vec2<u32>) -> bool {
+    for (var i = 0u; i < MAX_AFFINITIES; i += 1u) {
+        let slot1 = affinity_slot(affinities1, i);
+        if slot1 != 0u {
+            let i_slot2 = find_affinity_slot(affinities2, slot_collider(slot1));
+            if i_slot2 != MAX_AFFINITIES && slot_sign(slot1) != slot_sign(affinity_slot(affinities2, i_slot2)) {
+                return false;
+            }
+        }
+    }
+    return true;
 }
 
 struct Node {
@@ -264,6 +401,9 @@ struct Node {
     momentum_velocity_mass: vec4<f32>,
     #endif
     cdf: NodeCdf,
+    /// Either the cell’s mass-weighted temperature or its temperature (depending on the
+    /// context), similarly to `momentum_velocity_mass`.
+    temperature: f32,
 }
 
 #if DIM == 2
@@ -321,12 +461,15 @@ fn blocks_associated_to_block(block: BlockVirtualId) -> array<BlockVirtualId, NU
 
 #if MACOS == 0
 fn mark_block_as_active(block: BlockVirtualId) {
-    let slot = insertion_index(grid.hmap_capacity, block);
+    // NOTE: store the wrapped id so that the world-space position of the block
+    //       lies within the periodic range.
+    let wrapped_block = wrap_block(block);
+    let slot = insertion_index(grid.hmap_capacity, wrapped_block);
 
     if slot != NONE {
         let block_header_id = atomicAdd(&grid.num_active_blocks, 1u);
         let active_block = &active_blocks[block_header_id];
-        (*active_block).virtual_id = block;
+        (*active_block).virtual_id = wrapped_block;
         (*active_block).first_particle = 0u;
         (*active_block).num_particles = 0u;
         hmap_entries[slot].value = BlockHeaderId(block_header_id);
@@ -370,7 +513,8 @@ fn reset(@builtin(global_invocation_id) invocation_id: vec3<u32>, @builtin(num_w
        #else
        nodes[i].momentum_velocity_mass = vec4(0.0);
        #endif
-       nodes[i].cdf = NodeCdf(0.0, 0, NONE);
+       nodes[i].cdf = NodeCdf(0.0, NO_AFFINITY, NONE);
+       nodes[i].temperature = 0.0;
        nodes_linked_lists[i].head = NONE;
        nodes_linked_lists[i].len = 0u;
        nodes_rigid_linked_lists[i].head = NONE;
@@ -387,17 +531,17 @@ struct SimulationParameters {
     #endif
 }
 
-fn project_velocity(vel: Vector, n: Vector) -> Vector {
-    // TODO: this should depend on the collider’s material
-    //       properties.
+// Projects the relative velocity `vel` of a material point approaching a collider with
+// normal `n`, applying Coulomb friction and restitution.
+fn project_velocity(vel: Vector, n: Vector, friction: f32, restitution: f32) -> Vector {
     let normal_vel = dot(vel, n);
 
     if normal_vel < 0.0 {
-        let friction = 20.0;
         let tangent_vel = vel - n * normal_vel;
         let tangent_vel_len = length(tangent_vel);
         let tangent_vel_dir = select(Vector(0.0), tangent_vel / tangent_vel_len, tangent_vel_len > 1.0e-8);
-        return tangent_vel_dir * max(0.0, tangent_vel_len + friction * normal_vel);
+        let restitution_vel = n * (-restitution * normal_vel);
+        return tangent_vel_dir * max(0.0, tangent_vel_len + friction * normal_vel) + restitution_vel;
     } else {
         return vel;
     }
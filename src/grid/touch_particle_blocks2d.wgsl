@@ -56,9 +56,20 @@ struct NodePhysicalId {
  */
 const NONE: u32 = 0xffffffffu;
 
+// NOTE: the key is wrapped around the periodic axes, see grid.wgsl.
 fn pack_key(key: BlockVirtualId) -> u32 {
-    return (bitcast<u32>(key.id.x + 0x00007fff) & 0x0000ffffu) |
-    ((bitcast<u32>(key.id.y + 0x00007fff) & 0x0000ffffu) << 16);
+    let id = wrap_block(key).id;
+    return (bitcast<u32>(id.x + 0x00007fff) & 0x0000ffffu) |
+    ((bitcast<u32>(id.y + 0x00007fff) & 0x0000ffffu) << 16);
+}
+
+// The periodic image of `block` lying within the periodic range of the grid.
+fn wrap_block(block: BlockVirtualId) -> BlockVirtualId {
+    let first = grid.periodic_first_block.xy;
+    let len = grid.periodic_num_blocks.xy;
+    let safe_len = max(len, vec2(1));
+    let wrapped = first + ((block.id - first) % safe_len + safe_len) % safe_len;
+    return BlockVirtualId(select(block.id, wrapped, len > vec2(0)));
 }
 
 fn hash(packed_key: u32) -> u32 {
@@ -147,6 +158,9 @@ struct Grid {
     // NOTE: the hashmap capacity MUST be a power of 2.
     hmap_capacity: u32,
     capacity: u32,
+    // The first block, and the number of blocks, of the periodic range along each axis.
+    periodic_first_block: vec4<i32>,
+    periodic_num_blocks: vec4<i32>,
 }
 
 struct Node {
@@ -179,12 +193,15 @@ fn blocks_associated_to_block(block: BlockVirtualId) -> array<BlockVirtualId, NU
 }
 
 fn mark_block_as_active(block: BlockVirtualId) {
-    let slot = insertion_index(grid.hmap_capacity, block);
+    // NOTE: store the wrapped id so that the world-space position of the block
+    //       lies within the periodic range.
+    let wrapped_block = wrap_block(block);
+    let slot = insertion_index(grid.hmap_capacity, wrapped_block);
 
     if slot != NONE {
         let block_header_id = atomicAdd(&grid.num_active_blocks, 1u);
         let active_block = &active_blocks[block_header_id];
-        (*active_block).virtual_id = block;
+        (*active_block).virtual_id = wrapped_block;
         (*active_block).first_particle = 0u;
         (*active_block).num_particles = 0u;
         hmap_entries[slot].value = BlockHeaderId(block_header_id);
@@ -226,6 +243,11 @@ struct SimulationParameters {
 }
 
 // ~~~~~~~~~~~ Copied from sort.wgsl and particle2/3d.wgsl ~~~~~~~~~~~~~
+struct Count {
+    len: u32,
+    capacity: u32,
+}
+
 struct Position {
     pt: vec2<f32>,
 }
@@ -234,11 +256,13 @@ struct Position {
 var<storage, read_write> particles_pos: array<Position>;
 @group(1) @binding(6)
 var<storage, read_write> rigid_particle_needs_block: array<u32>;
+@group(1) @binding(7)
+var<storage, read> particles_count: Count;
 
 @compute @workgroup_size(GRID_WORKGROUP_SIZE, 1, 1)
 fn touch_particle_blocks(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
     let id = invocation_id.x;
-    if id < arrayLength(&particles_pos) {
+    if id < particles_count.len {
         let particle = particles_pos[id];
         var blocks = blocks_associated_to_point(particle.pt);
         for (var i = 0u; i < NUM_ASSOC_BLOCKS; i += 1u) {
@@ -56,13 +56,24 @@ struct NodePhysicalId {
  */
 const NONE: u32 = 0xffffffffu;
 
+// NOTE: the key is wrapped around the periodic axes, see grid.wgsl.
 fn pack_key(key: BlockVirtualId) -> u32 {
+    let id = wrap_block(key).id;
     // NOTE: we give the X and Z axis one more bit than Y.
     //       This is assuming Y-up and the fact that we want
     //       more room on the X-Z plane rather than along the up axis.
-    return (bitcast<u32>(key.id.x + 0x000003ff) & 0x000007ffu) |
-    ((bitcast<u32>(key.id.y + 0x000001ff) & 0x000003ffu) << 11) |
-    ((bitcast<u32>(key.id.z + 0x000003ff) & 0x000007ffu) << 21);
+    return (bitcast<u32>(id.x + 0x000003ff) & 0x000007ffu) |
+    ((bitcast<u32>(id.y + 0x000001ff) & 0x000003ffu) << 11) |
+    ((bitcast<u32>(id.z + 0x000003ff) & 0x000007ffu) << 21);
+}
+
+// The periodic image of `block` lying within the periodic range of the grid.
+fn wrap_block(block: BlockVirtualId) -> BlockVirtualId {
+    let first = grid.periodic_first_block.xyz;
+    let len = grid.periodic_num_blocks.xyz;
+    let safe_len = max(len, vec3(1));
+    let wrapped = first + ((block.id - first) % safe_len + safe_len) % safe_len;
+    return BlockVirtualId(select(block.id, wrapped, len > vec3(0)));
 }
 
 fn hash(packed_key: u32) -> u32 {
@@ -151,6 +162,9 @@ struct Grid {
     // NOTE: the hashmap capacity MUST be a power of 2.
     hmap_capacity: u32,
     capacity: u32,
+    // The first block, and the number of blocks, of the periodic range along each axis.
+    periodic_first_block: vec4<i32>,
+    periodic_num_blocks: vec4<i32>,
 }
 
 struct Node {
@@ -188,12 +202,15 @@ fn blocks_associated_to_block(block: BlockVirtualId) -> array<BlockVirtualId, NU
 }
 
 fn mark_block_as_active(block: BlockVirtualId) {
-    let slot = insertion_index(grid.hmap_capacity, block);
+    // NOTE: store the wrapped id so that the world-space position of the block
+    //       lies within the periodic range.
+    let wrapped_block = wrap_block(block);
+    let slot = insertion_index(grid.hmap_capacity, wrapped_block);
 
     if slot != NONE {
         let block_header_id = atomicAdd(&grid.num_active_blocks, 1u);
         let active_block = &active_blocks[block_header_id];
-        (*active_block).virtual_id = block;
+        (*active_block).virtual_id = wrapped_block;
         (*active_block).first_particle = 0u;
         (*active_block).num_particles = 0u;
         hmap_entries[slot].value = BlockHeaderId(block_header_id);
@@ -235,6 +252,11 @@ struct SimulationParameters {
 }
 
 // ~~~~~~~~~~~ Copied from sort.wgsl and particle2/3d.wgsl ~~~~~~~~~~~~~
+struct Count {
+    len: u32,
+    capacity: u32,
+}
+
 struct Position {
     pt: vec3<f32>,
 }
@@ -243,11 +265,13 @@ struct Position {
 var<storage, read_write> particles_pos: array<Position>;
 @group(1) @binding(6)
 var<storage, read_write> rigid_particle_needs_block: array<u32>;
+@group(1) @binding(7)
+var<storage, read> particles_count: Count;
 
 @compute @workgroup_size(GRID_WORKGROUP_SIZE, 1, 1)
 fn touch_particle_blocks(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
     let id = invocation_id.x;
-    if id < arrayLength(&particles_pos) {
+    if id < particles_count.len {
         let particle = particles_pos[id];
         // PERF: we should look at the local cell coordinates of the point
         //       in the block and only touch adjacent blocks if the
@@ -14,11 +14,17 @@ pub extern crate wgrapier2d as wgrapier;
 #[cfg(feature = "dim3")]
 pub extern crate wgrapier3d as wgrapier;
 
-mod collision;
+pub mod checkpoint;
+pub mod collision;
+mod error;
+pub mod export;
 pub mod grid;
 pub mod models;
 pub mod pipeline;
 pub mod solver;
+#[cfg(test)]
+mod test_utils;
+pub mod world;
 
 pub(crate) fn dim_shader_defs() -> HashMap<String, ShaderDefValue> {
     let mut result = wgparry::dim_shader_defs();
@@ -29,6 +35,7 @@ pub(crate) fn dim_shader_defs() -> HashMap<String, ShaderDefValue> {
     result
 }
 
+pub use error::Error;
 use naga_oil::compose::ShaderDefValue;
 use std::collections::HashMap;
 pub(crate) use wgparry::substitute_aliases;
@@ -0,0 +1,8 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+
+#[derive(Shader)]
+#[shader(src = "damage.wgsl", shader_defs = "dim_shader_defs")]
+pub struct WgDamage;
+
+wgcore::test_shader_compilation!(WgDamage, wgcore, crate::dim_shader_defs());
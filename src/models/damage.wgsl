@@ -0,0 +1,64 @@
+//! Continuum damage model degrading the elastic coefficients of intact particles.
+
+#define_import_path wgsparkl::models::damage
+
+
+struct Phase {
+    phase: f32,
+    damage: f32,
+    damage_threshold: f32,
+    softening: f32,
+}
+
+// Damage above which the particle is considered fully broken.
+const BROKEN_DAMAGE: f32 = 0.99;
+
+// Factor applied to the elastic coefficients of the particle.
+//
+// Broken particles aren’t degraded anymore since they are handled by their plasticity model.
+fn degradation(phase: Phase) -> f32 {
+    return select(1.0, 1.0 - phase.damage, phase.phase > 0.0);
+}
+
+// Updates the damage of an intact particle, given the largest principal stress of its
+// undamaged material. The damage never decreases.
+fn update(phase: Phase, max_principal_stress: f32) -> Phase {
+    if phase.phase <= 0.0 || phase.damage_threshold <= 0.0 || max_principal_stress <= phase.damage_threshold {
+        return phase;
+    }
+
+    // Exponential softening.
+    let excess = (max_principal_stress - phase.damage_threshold) / phase.damage_threshold;
+    let damage = max(phase.damage, 1.0 - exp(-phase.softening * excess));
+
+    if damage >= BROKEN_DAMAGE {
+        return Phase(0.0, 1.0, phase.damage_threshold, phase.softening);
+    } else {
+        return Phase(phase.phase, damage, phase.damage_threshold, phase.softening);
+    }
+}
+
+// Largest eigenvalue of a symmetric matrix.
+#if DIM == 2
+fn max_eigenvalue(m: mat2x2<f32>) -> f32 {
+    let half_trace = (m.x.x + m.y.y) / 2.0;
+    let half_diff = (m.x.x - m.y.y) / 2.0;
+    return half_trace + sqrt(half_diff * half_diff + m.x.y * m.x.y);
+}
+#else
+fn max_eigenvalue(m: mat3x3<f32>) -> f32 {
+    // Analytic eigenvalues of a symmetric 3x3 matrix.
+    let off_diag = m.x.y * m.x.y + m.x.z * m.x.z + m.y.z * m.y.z;
+    let q = (m.x.x + m.y.y + m.z.z) / 3.0;
+    let p2 = (m.x.x - q) * (m.x.x - q) + (m.y.y - q) * (m.y.y - q) + (m.z.z - q) * (m.z.z - q) + 2.0 * off_diag;
+    let p = sqrt(p2 / 6.0);
+
+    if p < 1.0e-10 {
+        return q;
+    }
+
+    let b = (m - mat3x3(q, 0.0, 0.0, 0.0, q, 0.0, 0.0, 0.0, q)) * (1.0 / p);
+    let r = clamp(determinant(b) / 2.0, -1.0, 1.0);
+    return q + 2.0 * p * cos(acos(r) / 3.0);
+}
+#endif
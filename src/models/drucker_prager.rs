@@ -36,9 +36,11 @@ impl DruckerPrager {
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
 #[repr(C)]
 pub struct DruckerPragerPlasticState {
-    plastic_deformation_gradient_det: f32,
-    plastic_hardening: f32,
-    log_vol_gain: f32,
+    /// The determinant of the plastic part of the deformation gradient.
+    pub plastic_deformation_gradient_det: f32,
+    pub plastic_hardening: f32,
+    /// The logarithm of the volume gained through plastic flow.
+    pub log_vol_gain: f32,
 }
 
 impl Default for DruckerPragerPlasticState {
@@ -62,16 +62,6 @@ fn project_deformation_gradient(plasticity: Plasticity, singular_values: vec2<f3
 }
 
 fn project(plasticity: Plasticity, state: PlasticState, deformation_gradient: mat2x2<f32>) -> DruckerPragerResult {
-//    if true {
-//        let snowvd = Svd2::svd(deformation_gradient);
-//        let yield_surface = exp(1.0 - 0.95);
-//        let j = snowvd.S.x * snowvd.S.y;
-//        var new_sigvals = clamp(snowvd.S, vec2(1.0 / yield_surface), vec2(yield_surface));
-//        let new_j = new_sigvals.x * new_sigvals.y;
-//        new_sigvals *= sqrt(j / new_j);
-//        let new_def_grad = Svd2::recompose(Svd2::Svd(snowvd.U, new_sigvals, snowvd.Vt));
-//        return DruckerPragerResult(state, new_def_grad);
-//    }
     if plasticity.lambda == 0 {
         // Plasticity is disable on this particle.
         return DruckerPragerResult(state, deformation_gradient);
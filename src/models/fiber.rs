@@ -0,0 +1,127 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+
+#[cfg(feature = "dim2")]
+type Vector = nalgebra::Vector2<f32>;
+#[cfg(feature = "dim3")]
+type Vector = nalgebra::Vector3<f32>;
+
+/// Fiber reinforcement making the elasticity of a particle anisotropic.
+///
+/// The fiber `direction` is given in material space (i.e. in the rest configuration) and is
+/// rotated and stretched by the deformation gradient `F`. The fiber adds the stress of the
+/// energy `stiffness / 2 * (λ - 1)²` where `λ = |F * direction|` is the fiber stretch, on top
+/// of the stress of the particle’s [`ElasticCoefficients`](crate::models::ElasticCoefficients).
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct Fiber {
+    /// The unit fiber direction, in material space.
+    pub direction: Vector,
+    /// The stiffness of the fiber under extension.
+    pub stiffness: f32,
+    /// The fraction of the `stiffness` resisting compression. Set it to 0 for fibers that only
+    /// resist extension (muscles, cables), or 1 for fibers that are as stiff in compression
+    /// as in extension (wood).
+    pub compression_ratio: f32,
+    #[cfg(feature = "dim3")]
+    padding: [f32; 3],
+}
+
+impl Fiber {
+    /// A fiber along `direction` (normalized automatically) with the same stiffness in
+    /// compression and extension.
+    pub fn new(direction: Vector, stiffness: f32) -> Self {
+        Self {
+            direction: direction.normalize(),
+            stiffness,
+            compression_ratio: 1.0,
+            #[cfg(feature = "dim3")]
+            padding: [0.0; 3],
+        }
+    }
+
+    /// Sets the fraction of the stiffness resisting compression.
+    pub fn with_compression_ratio(mut self, compression_ratio: f32) -> Self {
+        self.compression_ratio = compression_ratio;
+        self
+    }
+}
+
+impl Default for Fiber {
+    /// No fiber reinforcement.
+    fn default() -> Self {
+        Self {
+            direction: Vector::zeros(),
+            stiffness: 0.0,
+            compression_ratio: 0.0,
+            #[cfg(feature = "dim3")]
+            padding: [0.0; 3],
+        }
+    }
+}
+
+#[derive(Shader)]
+#[shader(src = "fiber.wgsl", shader_defs = "dim_shader_defs")]
+pub struct WgFiber;
+
+wgcore::test_shader_compilation!(WgFiber, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::Fiber;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::{vector, Vector3};
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+
+    /// The elongation of a fiber-reinforced block whose halves are pulled apart along `axis`.
+    async fn elongation_along(axis: usize) -> f32 {
+        let params = SimulationParams {
+            gravity: vector![0.0, 0.0, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let fiber = Fiber::new(Vector3::x(), 1_000_000.0);
+        let particles: Vec<_> = particle_block(10, 0.5, vector![0.0, 0.0, 0.0])
+            .into_iter()
+            .map(|mut particle| {
+                let dir = if particle.position[axis] < 2.25 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                particle.dynamics.velocity[axis] = dir * 2.0;
+                particle.with_fiber(fiber)
+            })
+            .collect();
+
+        let snapshot = simulate(
+            &particles,
+            params,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            30,
+        )
+        .await;
+        let min = snapshot
+            .positions
+            .iter()
+            .map(|pt| pt[axis])
+            .fold(f32::MAX, f32::min);
+        let max = snapshot
+            .positions
+            .iter()
+            .map(|pt| pt[axis])
+            .fold(f32::MIN, f32::max);
+        max - min - 9.0 * 0.5
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn fiber_is_stiffer_along_its_direction() {
+        let along = elongation_along(0).await;
+        let across = elongation_along(1).await;
+        assert!(along < across, "{along} {across}");
+    }
+}
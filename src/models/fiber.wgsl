@@ -0,0 +1,47 @@
+//! Anisotropic fiber-reinforced elasticity.
+
+#define_import_path wgsparkl::models::fiber
+
+
+struct Fiber {
+#if DIM == 2
+    direction: vec2<f32>,
+#else
+    direction: vec3<f32>,
+#endif
+    stiffness: f32,
+    compression_ratio: f32,
+}
+
+const MIN_STRETCH: f32 = 1.0e-6;
+
+// Stiffness of the fiber at the given stretch.
+fn effective_stiffness(model: Fiber, stretch: f32) -> f32 {
+    return select(model.stiffness, model.stiffness * model.compression_ratio, stretch < 1.0);
+}
+
+// The Kirchhoff stress `k * (λ - 1) / λ * f ⊗ f` where `f = F * direction` is the deformed
+// fiber and `λ = |f|` its stretch.
+#if DIM == 2
+fn kirchoff_stress(model: Fiber, deformation_gradient: mat2x2<f32>) -> mat2x2<f32> {
+    if model.stiffness == 0.0 {
+        return mat2x2(vec2(0.0), vec2(0.0));
+    }
+
+    let f = deformation_gradient * model.direction;
+    let stretch = max(length(f), MIN_STRETCH);
+    let coeff = effective_stiffness(model, stretch) * (stretch - 1.0) / stretch;
+    return mat2x2(f * f.x, f * f.y) * coeff;
+}
+#else
+fn kirchoff_stress(model: Fiber, deformation_gradient: mat3x3<f32>) -> mat3x3<f32> {
+    if model.stiffness == 0.0 {
+        return mat3x3(vec3(0.0), vec3(0.0), vec3(0.0));
+    }
+
+    let f = deformation_gradient * model.direction;
+    let stretch = max(length(f), MIN_STRETCH);
+    let coeff = effective_stiffness(model, stretch) * (stretch - 1.0) / stretch;
+    return mat3x3(f * f.x, f * f.y, f * f.z) * coeff;
+}
+#endif
@@ -0,0 +1,48 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+
+/// Coefficients of a weakly-compressible fluid.
+///
+/// The pressure is given by the Tait equation of state
+/// `p = bulk_modulus / gamma * ((density / rest_density)^gamma - 1)`.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct FluidCoefficients {
+    pub bulk_modulus: f32,
+    /// The Tait exponent. Set it to 1 for a linear equation of state.
+    pub gamma: f32,
+    /// The dynamic viscosity. Set it to 0 for an inviscid fluid.
+    pub viscosity: f32,
+}
+
+impl FluidCoefficients {
+    /// Fluid with the Tait equation of state commonly used for water (`gamma = 7`).
+    pub fn tait(bulk_modulus: f32, viscosity: f32) -> Self {
+        Self {
+            bulk_modulus,
+            gamma: 7.0,
+            viscosity,
+        }
+    }
+
+    /// Fluid with a pressure linear in the volume change.
+    pub fn linear(bulk_modulus: f32, viscosity: f32) -> Self {
+        Self {
+            bulk_modulus,
+            gamma: 1.0,
+            viscosity,
+        }
+    }
+}
+
+impl Default for FluidCoefficients {
+    fn default() -> Self {
+        Self::linear(0.0, 0.0)
+    }
+}
+
+#[derive(Shader)]
+#[shader(src = "fluid.wgsl", shader_defs = "dim_shader_defs")]
+pub struct WgFluid;
+
+wgcore::test_shader_compilation!(WgFluid, wgcore, crate::dim_shader_defs());
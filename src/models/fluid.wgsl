@@ -0,0 +1,50 @@
+//! Weakly-compressible fluid model.
+
+#define_import_path wgsparkl::models::fluid
+
+
+struct FluidCoefficients {
+    bulk_modulus: f32,
+    // Exponent of the Tait equation of state. A value of 1 gives a linear equation of state.
+    gamma: f32,
+    viscosity: f32,
+}
+
+// Pressure given by the Tait equation of state, with J = rest_density / density.
+fn pressure(model: FluidCoefficients, j: f32) -> f32 {
+    return model.bulk_modulus / model.gamma * (pow(j, -model.gamma) - 1.0);
+}
+
+// Only keeps the volume change of the deformation gradient since fluids don’t remember
+// their shape.
+#if DIM == 2
+fn project_deformation_gradient(deformation_gradient: mat2x2<f32>) -> mat2x2<f32> {
+    let j = max(determinant(deformation_gradient), 1.0e-10);
+    let s = sqrt(j);
+    return mat2x2(s, 0.0, 0.0, s);
+}
+#else
+fn project_deformation_gradient(deformation_gradient: mat3x3<f32>) -> mat3x3<f32> {
+    let j = max(determinant(deformation_gradient), 1.0e-10);
+    let s = pow(j, 1.0 / 3.0);
+    return mat3x3(s, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, s);
+}
+#endif
+
+#if DIM == 2
+fn kirchoff_stress(model: FluidCoefficients, deformation_gradient: mat2x2<f32>, velocity_gradient: mat2x2<f32>) -> mat2x2<f32> {
+#else
+fn kirchoff_stress(model: FluidCoefficients, deformation_gradient: mat3x3<f32>, velocity_gradient: mat3x3<f32>) -> mat3x3<f32> {
+#endif
+    let j = max(determinant(deformation_gradient), 1.0e-10);
+    let p = pressure(model, j);
+    // Newtonian viscosity: the Cauchy stress is 2 * viscosity * strain_rate.
+    var stress = (velocity_gradient + transpose(velocity_gradient)) * (model.viscosity * j);
+    stress.x.x -= p * j;
+    stress.y.y -= p * j;
+    #if DIM == 3
+    stress.z.z -= p * j;
+    #endif
+
+    return stress;
+}
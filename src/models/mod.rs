@@ -1,54 +1,393 @@
 use crate::solver::{Particle, ParticlePhase};
+use bytemuck::{cast_slice, Zeroable};
+pub use damage::WgDamage;
 pub use drucker_prager::{DruckerPrager, DruckerPragerPlasticState, WgDruckerPrager};
+pub use fiber::{Fiber, WgFiber};
+pub use fluid::{FluidCoefficients, WgFluid};
 pub use linear_elasticity::WgLinearElasticity;
+pub use nacc::{Nacc, NaccPlasticState, WgNacc};
 pub use neo_hookean_elasticity::WgNeoHookeanElasticity;
+pub use snow::{Snow, SnowPlasticState, WgSnow};
+pub use thermal::{ThermalProperties, WgThermal};
+pub use viscosity::{Viscosity, WgViscosity};
+pub use von_mises::{VonMises, VonMisesPlasticState, WgVonMises};
 use wgcore::tensor::GpuVector;
-use wgpu::{BufferUsages, Device};
+use wgpu::{Buffer, BufferUsages, Device, Queue};
 
+mod damage;
 mod drucker_prager;
+mod fiber;
+mod fluid;
 mod linear_elasticity;
+mod nacc;
 mod neo_hookean_elasticity;
+mod snow;
+mod thermal;
+mod viscosity;
+mod von_mises;
 
 pub struct GpuModels {
+    /// The [`ConstitutiveModel`] of each particle, as a `u32`.
+    pub model_ids: GpuVector<u32>,
     pub linear_elasticity: GpuVector<ElasticCoefficients>,
+    pub fibers: GpuVector<Fiber>,
+    pub fluid: GpuVector<FluidCoefficients>,
+    pub viscosity: GpuVector<Viscosity>,
+    /// The [`Plasticity`] model of each particle, as a `u32`.
+    pub plasticity_ids: GpuVector<u32>,
     pub drucker_prager_plasticity: GpuVector<DruckerPrager>,
     pub drucker_prager_plastic_state: GpuVector<DruckerPragerPlasticState>,
+    pub snow_plasticity: GpuVector<Snow>,
+    pub snow_plastic_state: GpuVector<SnowPlasticState>,
+    pub von_mises_plasticity: GpuVector<VonMises>,
+    pub von_mises_plastic_state: GpuVector<VonMisesPlasticState>,
+    pub nacc_plasticity: GpuVector<Nacc>,
+    pub nacc_plastic_state: GpuVector<NaccPlasticState>,
     pub phases: GpuVector<ParticlePhase>,
+    pub thermal: GpuVector<ThermalProperties>,
+    /// The elastic coefficients after accounting for hardening and damage.
+    ///
+    /// This is recomputed at each step so it isn’t part of the [`Self::per_particle_buffers`].
+    pub effective_elasticity: GpuVector<ElasticCoefficients>,
 }
 
 impl GpuModels {
     pub fn from_particles(device: &Device, particles: &[Particle]) -> Self {
-        let models: Vec<_> = particles.iter().map(|p| p.model).collect();
-        let plasticity: Vec<_> = particles
-            .iter()
-            .map(|p| p.plasticity.unwrap_or(DruckerPrager::new(-1.0, -1.0)))
-            .collect();
-        let plastic_states: Vec<_> = particles
-            .iter()
-            .map(|_| DruckerPragerPlasticState::default())
-            .collect();
-        let phases: Vec<_> = particles
+        Self::with_capacity(device, particles, particles.len())
+    }
+
+    /// Initializes the material buffers with room for at least `capacity` particles.
+    pub fn with_capacity(device: &Device, particles: &[Particle], capacity: usize) -> Self {
+        let capacity = capacity.max(particles.len());
+        let mut data = ModelsData::new(particles);
+        data.resize(capacity);
+
+        // NOTE: the per-particle buffers are copied from and to when the simulation state
+        //       is read back or restored from a checkpoint.
+        let usages = BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+
+        Self {
+            model_ids: GpuVector::init(device, &data.model_ids, usages),
+            linear_elasticity: GpuVector::init(device, &data.models, usages),
+            fibers: GpuVector::init(device, &data.fibers, usages),
+            fluid: GpuVector::init(device, &data.fluids, usages),
+            viscosity: GpuVector::init(device, &data.viscosity, usages),
+            plasticity_ids: GpuVector::init(device, &data.plasticity_ids, usages),
+            drucker_prager_plasticity: GpuVector::init(device, &data.plasticity, usages),
+            drucker_prager_plastic_state: GpuVector::init(device, &data.plastic_states, usages),
+            snow_plasticity: GpuVector::init(device, &data.snow, usages),
+            snow_plastic_state: GpuVector::init(device, &data.snow_states, usages),
+            von_mises_plasticity: GpuVector::init(device, &data.von_mises, usages),
+            von_mises_plastic_state: GpuVector::init(device, &data.von_mises_states, usages),
+            nacc_plasticity: GpuVector::init(device, &data.nacc, usages),
+            nacc_plastic_state: GpuVector::init(device, &data.nacc_states, usages),
+            phases: GpuVector::init(device, &data.phases, usages),
+            thermal: GpuVector::init(device, &data.thermal, usages),
+            effective_elasticity: GpuVector::init(device, &data.models, BufferUsages::STORAGE),
+        }
+    }
+
+    /// Overwrites the materials of the first `particles.len()` particles.
+    ///
+    /// The buffers must have room for all the `particles`.
+    pub fn write(&self, queue: &Queue, particles: &[Particle]) {
+        let data = ModelsData::new(particles);
+        let write = |buffer: &Buffer, bytes: &[u8]| queue.write_buffer(buffer, 0, bytes);
+        write(self.model_ids.buffer(), cast_slice(&data.model_ids));
+        write(self.linear_elasticity.buffer(), cast_slice(&data.models));
+        write(self.fibers.buffer(), cast_slice(&data.fibers));
+        write(self.fluid.buffer(), cast_slice(&data.fluids));
+        write(self.viscosity.buffer(), cast_slice(&data.viscosity));
+        write(
+            self.plasticity_ids.buffer(),
+            cast_slice(&data.plasticity_ids),
+        );
+        write(
+            self.drucker_prager_plasticity.buffer(),
+            cast_slice(&data.plasticity),
+        );
+        write(
+            self.drucker_prager_plastic_state.buffer(),
+            cast_slice(&data.plastic_states),
+        );
+        write(self.snow_plasticity.buffer(), cast_slice(&data.snow));
+        write(
+            self.snow_plastic_state.buffer(),
+            cast_slice(&data.snow_states),
+        );
+        write(
+            self.von_mises_plasticity.buffer(),
+            cast_slice(&data.von_mises),
+        );
+        write(
+            self.von_mises_plastic_state.buffer(),
+            cast_slice(&data.von_mises_states),
+        );
+        write(self.nacc_plasticity.buffer(), cast_slice(&data.nacc));
+        write(
+            self.nacc_plastic_state.buffer(),
+            cast_slice(&data.nacc_states),
+        );
+        write(self.phases.buffer(), cast_slice(&data.phases));
+        write(self.thermal.buffer(), cast_slice(&data.thermal));
+    }
+
+    /// All the buffers storing one element per particle.
+    ///
+    /// These are the buffers that need to be moved around when particles are emitted or
+    /// removed, so any new per-particle buffer must be listed here.
+    pub fn per_particle_buffers(&self) -> Vec<&Buffer> {
+        vec![
+            self.model_ids.buffer(),
+            self.linear_elasticity.buffer(),
+            self.fibers.buffer(),
+            self.fluid.buffer(),
+            self.viscosity.buffer(),
+            self.plasticity_ids.buffer(),
+            self.drucker_prager_plasticity.buffer(),
+            self.drucker_prager_plastic_state.buffer(),
+            self.snow_plasticity.buffer(),
+            self.snow_plastic_state.buffer(),
+            self.von_mises_plasticity.buffer(),
+            self.von_mises_plastic_state.buffer(),
+            self.nacc_plasticity.buffer(),
+            self.nacc_plastic_state.buffer(),
+            self.phases.buffer(),
+            self.thermal.buffer(),
+        ]
+    }
+}
+
+/// The material data of a set of particles, laid out like the [`GpuModels`] buffers.
+struct ModelsData {
+    model_ids: Vec<u32>,
+    models: Vec<ElasticCoefficients>,
+    fibers: Vec<Fiber>,
+    fluids: Vec<FluidCoefficients>,
+    viscosity: Vec<Viscosity>,
+    plasticity_ids: Vec<u32>,
+    plasticity: Vec<DruckerPrager>,
+    plastic_states: Vec<DruckerPragerPlasticState>,
+    snow: Vec<Snow>,
+    snow_states: Vec<SnowPlasticState>,
+    von_mises: Vec<VonMises>,
+    von_mises_states: Vec<VonMisesPlasticState>,
+    nacc: Vec<Nacc>,
+    nacc_states: Vec<NaccPlasticState>,
+    phases: Vec<ParticlePhase>,
+    thermal: Vec<ThermalProperties>,
+}
+
+impl ModelsData {
+    fn new(particles: &[Particle]) -> Self {
+        let nacc: Vec<_> = particles
             .iter()
-            .map(|p| {
-                p.phase.unwrap_or(ParticlePhase {
-                    phase: 0.0,
-                    max_stretch: -1.0,
-                })
+            .map(|p| match p.plasticity {
+                Some(Plasticity::Nacc(nacc)) => nacc,
+                _ => Nacc::zeroed(),
             })
             .collect();
+
         Self {
-            linear_elasticity: GpuVector::init(device, &models, BufferUsages::STORAGE),
-            drucker_prager_plasticity: GpuVector::init(device, &plasticity, BufferUsages::STORAGE),
-            drucker_prager_plastic_state: GpuVector::init(
-                device,
-                &plastic_states,
-                BufferUsages::STORAGE,
-            ),
-            phases: GpuVector::init(device, &phases, BufferUsages::STORAGE),
+            model_ids: particles
+                .iter()
+                .map(|p| p.constitutive_model as u32)
+                .collect(),
+            models: particles.iter().map(|p| p.model).collect(),
+            fibers: particles
+                .iter()
+                .map(|p| p.fiber.unwrap_or_default())
+                .collect(),
+            fluids: particles
+                .iter()
+                .map(|p| p.fluid.unwrap_or_default())
+                .collect(),
+            viscosity: particles
+                .iter()
+                .map(|p| p.viscosity.unwrap_or_default())
+                .collect(),
+            plasticity_ids: particles
+                .iter()
+                .map(|p| Plasticity::model_id(p.plasticity.as_ref()))
+                .collect(),
+            plasticity: particles
+                .iter()
+                .map(|p| match p.plasticity {
+                    Some(Plasticity::DruckerPrager(plasticity)) => plasticity,
+                    _ => DruckerPrager::new(-1.0, -1.0),
+                })
+                .collect(),
+            plastic_states: particles
+                .iter()
+                .map(|_| DruckerPragerPlasticState::default())
+                .collect(),
+            // NOTE: particles without snow plasticity get zero coefficients so their
+            //       stiffness isn’t affected by the snow hardening.
+            snow: particles
+                .iter()
+                .map(|p| match p.plasticity {
+                    Some(Plasticity::Snow(snow)) => snow,
+                    _ => Snow::zeroed(),
+                })
+                .collect(),
+            snow_states: particles
+                .iter()
+                .map(|_| SnowPlasticState::default())
+                .collect(),
+            von_mises: particles
+                .iter()
+                .map(|p| match p.plasticity {
+                    Some(Plasticity::VonMises(von_mises)) => von_mises,
+                    _ => VonMises::zeroed(),
+                })
+                .collect(),
+            von_mises_states: particles
+                .iter()
+                .map(|_| VonMisesPlasticState::default())
+                .collect(),
+            nacc_states: nacc.iter().map(NaccPlasticState::new).collect(),
+            nacc,
+            phases: particles
+                .iter()
+                .map(|p| p.phase.unwrap_or_default())
+                .collect(),
+            thermal: particles
+                .iter()
+                .map(|p| p.thermal.unwrap_or_default())
+                .collect(),
+        }
+    }
+
+    /// Pads the data of the unused particle slots, up to `capacity`.
+    fn resize(&mut self, capacity: usize) {
+        self.model_ids
+            .resize(capacity, ConstitutiveModel::default() as u32);
+        self.models.resize(capacity, ElasticCoefficients::zeroed());
+        self.fibers.resize(capacity, Fiber::default());
+        self.fluids.resize(capacity, FluidCoefficients::default());
+        self.viscosity.resize(capacity, Viscosity::default());
+        self.plasticity_ids
+            .resize(capacity, Plasticity::model_id(None));
+        self.plasticity
+            .resize(capacity, DruckerPrager::new(-1.0, -1.0));
+        self.plastic_states
+            .resize(capacity, DruckerPragerPlasticState::default());
+        self.snow.resize(capacity, Snow::zeroed());
+        self.snow_states
+            .resize(capacity, SnowPlasticState::default());
+        self.von_mises.resize(capacity, VonMises::zeroed());
+        self.von_mises_states
+            .resize(capacity, VonMisesPlasticState::default());
+        self.nacc.resize(capacity, Nacc::zeroed());
+        self.nacc_states
+            .resize(capacity, NaccPlasticState::default());
+        self.phases.resize(capacity, ParticlePhase::default());
+        self.thermal.resize(capacity, ThermalProperties::default());
+    }
+}
+
+/// The constitutive model used for computing the stress of a particle.
+///
+/// The elastic models read their coefficients from [`Particle::model`], and can be
+/// made anisotropic with [`Particle::fiber`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[repr(u32)]
+pub enum ConstitutiveModel {
+    /// Fixed-corotated elasticity.
+    #[default]
+    Corotated = 0,
+    /// Neo-Hookean elasticity, well suited for rubber-like materials.
+    NeoHookean = 1,
+    /// Weakly-compressible fluid reading its coefficients from [`Particle::fluid`].
+    ///
+    /// The deformation gradient of fluid particles is reduced to its determinant so
+    /// only volume changes generate stress.
+    ///
+    /// Particles with a [`ThermalProperties::melting_point`] switch to this model when they melt.
+    Fluid = 2,
+}
+
+/// The plasticity model of a particle.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Plasticity {
+    DruckerPrager(DruckerPrager),
+    Snow(Snow),
+    VonMises(VonMises),
+    Nacc(Nacc),
+}
+
+impl Plasticity {
+    /// The index identifying this model in the shaders.
+    ///
+    /// Particles without plasticity have their own index so they can be told apart when
+    /// reading back their [`PlasticState`], but they still go through the Drucker-Prager
+    /// projection with negative coefficients, which is what makes broken particles (with a
+    /// zero [`ParticlePhase::phase`]) behave like sand.
+    // NOTE: must match the `PLASTICITY_*` constants of `particle_update.wgsl`.
+    pub(crate) fn model_id(plasticity: Option<&Self>) -> u32 {
+        match plasticity {
+            Some(Self::DruckerPrager(_)) => 0,
+            Some(Self::Snow(_)) => 1,
+            Some(Self::VonMises(_)) => 2,
+            Some(Self::Nacc(_)) => 3,
+            None => 4,
+        }
+    }
+}
+
+/// The evolving state of the plasticity model of a particle, as read back from the gpu.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PlasticState {
+    /// The particle doesn’t have any [`Plasticity`] model.
+    None,
+    DruckerPrager(DruckerPragerPlasticState),
+    Snow(SnowPlasticState),
+    VonMises(VonMisesPlasticState),
+    Nacc(NaccPlasticState),
+}
+
+impl PlasticState {
+    /// The scalar hardening variable of the plasticity model.
+    ///
+    /// This is the hardening parameter for Drucker-Prager, the determinant of the plastic
+    /// deformation gradient for snow, the accumulated plastic strain for von Mises, and the
+    /// logarithm of the determinant of the plastic deformation gradient for NACC. This is
+    /// zero for particles without plasticity.
+    pub fn hardening(&self) -> f32 {
+        match self {
+            Self::None => 0.0,
+            Self::DruckerPrager(state) => state.plastic_hardening,
+            Self::Snow(state) => state.plastic_deformation_gradient_det,
+            Self::VonMises(state) => state.plastic_strain,
+            Self::Nacc(state) => state.log_jp,
         }
     }
 }
 
+impl From<DruckerPrager> for Plasticity {
+    fn from(value: DruckerPrager) -> Self {
+        Self::DruckerPrager(value)
+    }
+}
+
+impl From<Snow> for Plasticity {
+    fn from(value: Snow) -> Self {
+        Self::Snow(value)
+    }
+}
+
+impl From<VonMises> for Plasticity {
+    fn from(value: VonMises) -> Self {
+        Self::VonMises(value)
+    }
+}
+
+impl From<Nacc> for Plasticity {
+    fn from(value: Nacc) -> Self {
+        Self::Nacc(value)
+    }
+}
+
 fn lame_lambda_mu(young_modulus: f32, poisson_ratio: f32) -> (f32, f32) {
     (
         young_modulus * poisson_ratio / ((1.0 + poisson_ratio) * (1.0 - 2.0 * poisson_ratio)),
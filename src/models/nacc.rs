@@ -0,0 +1,134 @@
+use crate::dim_shader_defs;
+use crate::models::lame_lambda_mu;
+use wgcore::Shader;
+use wgebra::{WgSvd2, WgSvd3};
+
+/// Non-associative Cam-Clay plasticity, well suited for cohesive soils like wet sand or mud.
+///
+/// Unlike [`DruckerPrager`](crate::models::DruckerPrager), the yield surface is bounded under
+/// compression and grows as the material gets compacted (hardening).
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct Nacc {
+    /// Slope of the critical state line, derived from the friction angle.
+    pub friction: f32,
+    /// Cohesion coefficient β. Zero for a cohesionless material.
+    pub cohesion: f32,
+    /// Hardening coefficient ξ.
+    pub hardening: f32,
+    pub bulk_modulus: f32,
+    pub mu: f32,
+    /// Initial value of `log(Jp)` where `Jp` is the determinant of the plastic deformation
+    /// gradient. Negative values correspond to an already compacted material with a larger
+    /// yield surface.
+    pub initial_log_jp: f32,
+}
+
+impl Nacc {
+    /// Creates a NACC plasticity model from the elastic coefficients of the particle, its
+    /// friction angle (in radians), cohesion β, and hardening coefficient ξ.
+    pub fn new(
+        young_modulus: f32,
+        poisson_ratio: f32,
+        friction_angle: f32,
+        cohesion: f32,
+        hardening: f32,
+    ) -> Self {
+        let dim = if cfg!(feature = "dim2") { 2.0 } else { 3.0 };
+        let (lambda, mu) = lame_lambda_mu(young_modulus, poisson_ratio);
+        let sin_angle = friction_angle.sin();
+        let mohr_coulomb_friction = (2.0f32 / 3.0).sqrt() * 2.0 * sin_angle / (3.0 - sin_angle);
+
+        Self {
+            friction: mohr_coulomb_friction * dim / (2.0 / (6.0 - dim)).sqrt(),
+            cohesion,
+            hardening,
+            bulk_modulus: lambda + 2.0 / dim * mu,
+            mu,
+            initial_log_jp: -0.01,
+        }
+    }
+
+    /// Sets the initial value of `log(Jp)`.
+    pub fn with_initial_log_jp(mut self, initial_log_jp: f32) -> Self {
+        self.initial_log_jp = initial_log_jp;
+        self
+    }
+}
+
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub struct NaccPlasticState {
+    /// The logarithm of the determinant of the plastic part of the deformation gradient.
+    pub log_jp: f32,
+}
+
+impl NaccPlasticState {
+    pub fn new(plasticity: &Nacc) -> Self {
+        Self {
+            log_jp: plasticity.initial_log_jp,
+        }
+    }
+}
+
+#[derive(Shader)]
+#[shader(
+    derive(WgSvd2, WgSvd3),
+    src = "nacc.wgsl",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgNacc;
+
+wgcore::test_shader_compilation!(WgNacc, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::Nacc;
+    use crate::models::PlasticState;
+    use crate::solver::{BoundaryCondition, SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::{vector, Vector3};
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+
+    fn width_along_x(positions: &[Vector3<f32>]) -> f32 {
+        let min = positions.iter().map(|pt| pt.x).fold(f32::MAX, f32::min);
+        let max = positions.iter().map(|pt| pt.x).fold(f32::MIN, f32::max);
+        max - min
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn nacc_block_collapses_where_elastic_block_stands() {
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::new(
+                vector![-20.0, 0.0, -20.0],
+                vector![25.0, 20.0, 25.0],
+                BoundaryCondition::Separate,
+            )
+            .with_friction(0.5),
+        };
+        let elastic = particle_block(10, 0.5, vector![0.0, 0.5, 0.0]);
+        let nacc = Nacc::new(100_000.0, 0.33, 30.0f32.to_radians(), 0.0, 1.0);
+        let granular: Vec<_> = elastic
+            .iter()
+            .map(|particle| particle.with_plasticity(nacc))
+            .collect();
+
+        let bodies = RigidBodySet::default();
+        let colliders = ColliderSet::default();
+        let elastic = simulate(&elastic, params, &bodies, &colliders, 600).await;
+        let granular = simulate(&granular, params, &bodies, &colliders, 600).await;
+
+        // The cohesionless soil spreads on the floor while the elastic block keeps its shape.
+        let initial_width = 9.0 * 0.5;
+        assert!(width_along_x(&elastic.positions) < initial_width + 0.5);
+        assert!(width_along_x(&granular.positions) > initial_width + 1.0);
+        assert!(granular.plastic_states.iter().any(|state| matches!(
+            state,
+            PlasticState::Nacc(state) if state.log_jp != nacc.initial_log_jp
+        )));
+    }
+}
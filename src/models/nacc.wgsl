@@ -0,0 +1,162 @@
+//! Non-associative Cam-Clay (NACC) plasticity model, as described in “CD-MPM: continuum
+//! damage material point methods for dynamic fracture animation”, Wolper et al. 2019.
+
+#define_import_path wgsparkl::models::nacc
+#import wgebra::svd2 as Svd2
+#import wgebra::svd3 as Svd3
+
+
+struct Plasticity {
+    // Slope of the critical state line.
+    friction: f32,
+    cohesion: f32,
+    hardening: f32,
+    bulk_modulus: f32,
+    mu: f32,
+    // Only used for initializing the plastic state.
+    initial_log_jp: f32,
+}
+
+struct PlasticState {
+    // Logarithm of the determinant of the plastic deformation gradient.
+    log_jp: f32,
+}
+
+const PROJECTION_NONE: u32 = 0;
+const PROJECTION_VOLUMETRIC: u32 = 1;
+const PROJECTION_YIELD_SURFACE: u32 = 2;
+
+struct ReturnMapping {
+    kind: u32,
+    // The new elastic volume change for volumetric projections, or the norm of the
+    // deviatoric part of the new left Cauchy-Green strain for yield surface projections.
+    value: f32,
+    log_jp_delta: f32,
+}
+
+// The dimension-independent part of the return mapping, operating on the volume change `j`
+// and the norm of the deviatoric trial stress.
+fn return_mapping(plasticity: Plasticity, state: PlasticState, j: f32, s_hat_trial_norm: f32, d: f32) -> ReturnMapping {
+    let kappa = plasticity.bulk_modulus;
+    let beta = plasticity.cohesion;
+    let m2 = plasticity.friction * plasticity.friction;
+    // NOTE: p0 must stay below kappa / 2 for the volumetric projections to be well-defined.
+    let p0 = min(
+        kappa * (1.0e-5 + sinh(plasticity.hardening * max(-state.log_jp, 0.0))),
+        kappa * 0.5 * (1.0 - 1.0e-4),
+    );
+    let p_trial = -kappa * 0.5 * (j - 1.0 / j) * j;
+
+    // Beyond the tips of the yield surface.
+    if p_trial > p0 {
+        let new_j = sqrt(-2.0 * p0 / kappa + 1.0);
+        return ReturnMapping(PROJECTION_VOLUMETRIC, new_j, log(j / new_j));
+    }
+
+    if p_trial < -beta * p0 {
+        let new_j = sqrt(2.0 * beta * p0 / kappa + 1.0);
+        return ReturnMapping(PROJECTION_VOLUMETRIC, new_j, log(j / new_j));
+    }
+
+    let y_s_half_coeff = (6.0 - d) / 2.0 * (1.0 + 2.0 * beta);
+    let y_p_half = m2 * (p_trial + beta * p0) * (p_trial - p0);
+    let y = y_s_half_coeff * s_hat_trial_norm * s_hat_trial_norm + y_p_half;
+
+    if y < 1.0e-4 {
+        // Inside of the yield surface.
+        return ReturnMapping(PROJECTION_NONE, 0.0, 0.0);
+    }
+
+    // Hardening: the state is moved to the intersection between the yield surface and
+    // the line joining the trial stress to the center of the yield surface.
+    var log_jp_delta = 0.0;
+    if p0 > 1.0e-4 && p_trial < p0 - 1.0e-4 && p_trial > 1.0e-4 - beta * p0 {
+        let p_c = (1.0 - beta) * p0 / 2.0;
+        let q_trial = sqrt((6.0 - d) / 2.0) * s_hat_trial_norm;
+        let dir = normalize(vec2(p_c - p_trial, -q_trial));
+        let a = m2 * dir.x * dir.x + (1.0 + 2.0 * beta) * dir.y * dir.y;
+        let b = m2 * dir.x * (2.0 * p_c - p0 + beta * p0);
+        let c = m2 * (p_c + beta * p0) * (p_c - p0);
+        let sqrt_delta = sqrt(max(b * b - 4.0 * a * c, 0.0));
+        let p1 = p_c + (-b + sqrt_delta) / (2.0 * a) * dir.x;
+        let p2 = p_c + (-b - sqrt_delta) / (2.0 * a) * dir.x;
+        let p_x = select(p2, p1, (p_trial - p_c) * (p1 - p_c) > 0.0);
+        let j_x = sqrt(abs(-2.0 * p_x / kappa + 1.0));
+        if j_x > 1.0e-4 {
+            log_jp_delta = log(j / j_x);
+        }
+    }
+
+    let deviatoric_norm = sqrt(max(-y_p_half / y_s_half_coeff, 0.0)) * pow(j, 2.0 / d) / plasticity.mu;
+    return ReturnMapping(PROJECTION_YIELD_SURFACE, deviatoric_norm, log_jp_delta);
+}
+
+#if DIM == 2
+struct NaccResult {
+    state: PlasticState,
+    deformation_gradient: mat2x2<f32>,
+}
+
+fn project(plasticity: Plasticity, state: PlasticState, deformation_gradient: mat2x2<f32>) -> NaccResult {
+    if plasticity.bulk_modulus <= 0.0 {
+        // Plasticity is disabled on this particle.
+        return NaccResult(state, deformation_gradient);
+    }
+
+    let d = 2.0;
+    let svd = Svd2::svd(deformation_gradient);
+    let j = max(svd.S.x * svd.S.y, 1.0e-6);
+    let b_hat_trial = svd.S * svd.S;
+    let b_hat_trial_mean = (b_hat_trial.x + b_hat_trial.y) / d;
+    let s_hat_trial = (b_hat_trial - vec2(b_hat_trial_mean)) * (plasticity.mu * pow(j, -2.0 / d));
+    let s_hat_trial_norm = length(s_hat_trial);
+    let mapping = return_mapping(plasticity, state, j, s_hat_trial_norm, d);
+
+    if mapping.kind == PROJECTION_NONE {
+        return NaccResult(state, deformation_gradient);
+    }
+
+    var new_singular_values = vec2(pow(mapping.value, 1.0 / d));
+    if mapping.kind == PROJECTION_YIELD_SURFACE {
+        let b_hat_new = s_hat_trial * (mapping.value / s_hat_trial_norm) + vec2(b_hat_trial_mean);
+        new_singular_values = sqrt(max(b_hat_new, vec2(0.0)));
+    }
+
+    let new_deformation_gradient = Svd2::recompose(Svd2::Svd(svd.U, new_singular_values, svd.Vt));
+    return NaccResult(PlasticState(state.log_jp + mapping.log_jp_delta), new_deformation_gradient);
+}
+#else
+struct NaccResult {
+    state: PlasticState,
+    deformation_gradient: mat3x3<f32>,
+}
+
+fn project(plasticity: Plasticity, state: PlasticState, deformation_gradient: mat3x3<f32>) -> NaccResult {
+    if plasticity.bulk_modulus <= 0.0 {
+        // Plasticity is disabled on this particle.
+        return NaccResult(state, deformation_gradient);
+    }
+
+    let d = 3.0;
+    let svd = Svd3::svd(deformation_gradient);
+    let j = max(svd.S.x * svd.S.y * svd.S.z, 1.0e-6);
+    let b_hat_trial = svd.S * svd.S;
+    let b_hat_trial_mean = (b_hat_trial.x + b_hat_trial.y + b_hat_trial.z) / d;
+    let s_hat_trial = (b_hat_trial - vec3(b_hat_trial_mean)) * (plasticity.mu * pow(j, -2.0 / d));
+    let s_hat_trial_norm = length(s_hat_trial);
+    let mapping = return_mapping(plasticity, state, j, s_hat_trial_norm, d);
+
+    if mapping.kind == PROJECTION_NONE {
+        return NaccResult(state, deformation_gradient);
+    }
+
+    var new_singular_values = vec3(pow(mapping.value, 1.0 / d));
+    if mapping.kind == PROJECTION_YIELD_SURFACE {
+        let b_hat_new = s_hat_trial * (mapping.value / s_hat_trial_norm) + vec3(b_hat_trial_mean);
+        new_singular_values = sqrt(max(b_hat_new, vec3(0.0)));
+    }
+
+    let new_deformation_gradient = Svd3::recompose(Svd3::Svd(svd.U, new_singular_values, svd.Vt));
+    return NaccResult(PlasticState(state.log_jp + mapping.log_jp_delta), new_deformation_gradient);
+}
+#endif
@@ -0,0 +1,60 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+use wgebra::{WgSvd2, WgSvd3};
+
+/// Snow plasticity model from Stomakhin et al. 2013.
+///
+/// The singular values of the elastic deformation gradient are clamped to
+/// `[1 - critical_compression, 1 + critical_stretch]` and the excess is transferred to the plastic
+/// deformation. The Lamé coefficients of the particle are then scaled by
+/// `exp(hardening_coeff * (1 - Jp))` where `Jp` is the determinant of the plastic deformation
+/// gradient, making the snow stiffer as it gets packed.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct Snow {
+    pub critical_compression: f32,
+    pub critical_stretch: f32,
+    pub hardening_coeff: f32,
+}
+
+impl Snow {
+    pub fn new(critical_compression: f32, critical_stretch: f32, hardening_coeff: f32) -> Self {
+        Self {
+            critical_compression,
+            critical_stretch,
+            hardening_coeff,
+        }
+    }
+}
+
+impl Default for Snow {
+    /// The coefficients used for the reference snow in the original paper.
+    fn default() -> Self {
+        Self::new(2.5e-2, 7.5e-3, 10.0)
+    }
+}
+
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct SnowPlasticState {
+    /// The determinant of the plastic part of the deformation gradient.
+    pub plastic_deformation_gradient_det: f32,
+}
+
+impl Default for SnowPlasticState {
+    fn default() -> Self {
+        Self {
+            plastic_deformation_gradient_det: 1.0,
+        }
+    }
+}
+
+#[derive(Shader)]
+#[shader(
+    derive(WgSvd2, WgSvd3),
+    src = "snow.wgsl",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgSnow;
+
+wgcore::test_shader_compilation!(WgSnow, wgcore, crate::dim_shader_defs());
@@ -0,0 +1,65 @@
+//! Snow plasticity model from “A material point method for snow simulation”, Stomakhin et al. 2013.
+
+#define_import_path wgsparkl::models::snow
+#import wgebra::svd2 as Svd2
+#import wgebra::svd3 as Svd3
+
+
+struct Plasticity {
+    critical_compression: f32,
+    critical_stretch: f32,
+    hardening_coeff: f32,
+}
+
+struct PlasticState {
+    plastic_deformation_gradient_det: f32,
+}
+
+// Factor applied to the Lamé coefficients to account for the hardening of compacted snow.
+fn hardening(plasticity: Plasticity, state: PlasticState) -> f32 {
+    // NOTE: clamp to avoid exploding stiffnesses when the snow gets heavily compressed
+    //       or stretched.
+    return clamp(exp(plasticity.hardening_coeff * (1.0 - state.plastic_deformation_gradient_det)), 0.1, 5.0);
+}
+
+#if DIM == 2
+struct SnowResult {
+    state: PlasticState,
+    deformation_gradient: mat2x2<f32>,
+}
+
+fn project(plasticity: Plasticity, state: PlasticState, deformation_gradient: mat2x2<f32>) -> SnowResult {
+    let svd = Svd2::svd(deformation_gradient);
+    let new_singular_values = clamp(
+        svd.S,
+        vec2(1.0 - plasticity.critical_compression),
+        vec2(1.0 + plasticity.critical_stretch),
+    );
+
+    let prev_det = svd.S.x * svd.S.y;
+    let new_det = new_singular_values.x * new_singular_values.y;
+    let new_plastic_deformation_gradient_det = state.plastic_deformation_gradient_det * prev_det / new_det;
+    let new_deformation_gradient = Svd2::recompose(Svd2::Svd(svd.U, new_singular_values, svd.Vt));
+    return SnowResult(PlasticState(new_plastic_deformation_gradient_det), new_deformation_gradient);
+}
+#else
+struct SnowResult {
+    state: PlasticState,
+    deformation_gradient: mat3x3<f32>,
+}
+
+fn project(plasticity: Plasticity, state: PlasticState, deformation_gradient: mat3x3<f32>) -> SnowResult {
+    let svd = Svd3::svd(deformation_gradient);
+    let new_singular_values = clamp(
+        svd.S,
+        vec3(1.0 - plasticity.critical_compression),
+        vec3(1.0 + plasticity.critical_stretch),
+    );
+
+    let prev_det = svd.S.x * svd.S.y * svd.S.z;
+    let new_det = new_singular_values.x * new_singular_values.y * new_singular_values.z;
+    let new_plastic_deformation_gradient_det = state.plastic_deformation_gradient_det * prev_det / new_det;
+    let new_deformation_gradient = Svd3::recompose(Svd3::Svd(svd.U, new_singular_values, svd.Vt));
+    return SnowResult(PlasticState(new_plastic_deformation_gradient_det), new_deformation_gradient);
+}
+#endif
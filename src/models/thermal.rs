@@ -0,0 +1,163 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+
+/// Thermal properties of a particle.
+///
+/// The particle temperature is stored in [`ParticleDynamics::temperature`](crate::solver::ParticleDynamics::temperature)
+/// and diffuses through the grid based on the `conductivity`. The elastic coefficients and the
+/// Drucker-Prager friction angle soften linearly (in log-space for the elastic coefficients) as the
+/// temperature rises above the `reference_temperature`, and the particle turns into a
+/// [`ConstitutiveModel::Fluid`](crate::models::ConstitutiveModel::Fluid) above its `melting_point`.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct ThermalProperties {
+    /// The heat conductivity. Zero for a particle that doesn’t conduct heat.
+    pub conductivity: f32,
+    /// The heat capacity per unit mass.
+    pub specific_heat: f32,
+    /// The temperature at which the material parameters are not affected by the temperature.
+    pub reference_temperature: f32,
+    /// The temperature above which the particle melts into a fluid.
+    pub melting_point: f32,
+    /// The elastic coefficients are multiplied by `exp(-stiffness_softening * (T - reference_temperature))`.
+    pub stiffness_softening: f32,
+    /// The decrease of the Drucker-Prager friction angle (in radians) per degree above the
+    /// `reference_temperature`.
+    pub friction_softening: f32,
+}
+
+impl ThermalProperties {
+    /// A material conducting heat, with temperature-independent parameters, and that never melts.
+    pub fn new(conductivity: f32, specific_heat: f32) -> Self {
+        Self {
+            conductivity,
+            specific_heat,
+            reference_temperature: 0.0,
+            melting_point: f32::MAX,
+            stiffness_softening: 0.0,
+            friction_softening: 0.0,
+        }
+    }
+
+    /// Makes the particle turn into a fluid above `melting_point`.
+    ///
+    /// The fluid coefficients are read from [`Particle::fluid`](crate::solver::Particle::fluid).
+    /// If they are not set, the melted particle gets a bulk modulus derived from its elastic
+    /// coefficients. The melting is irreversible: materials that harden when cooling down
+    /// (like lava) should rather rely on the stiffness softening of [`Self::with_softening`].
+    pub fn with_melting_point(mut self, melting_point: f32) -> Self {
+        self.melting_point = melting_point;
+        self
+    }
+
+    /// Makes the elastic coefficients and the Drucker-Prager friction angle depend on the
+    /// temperature relative to `reference_temperature`.
+    pub fn with_softening(
+        mut self,
+        reference_temperature: f32,
+        stiffness_softening: f32,
+        friction_softening: f32,
+    ) -> Self {
+        self.reference_temperature = reference_temperature;
+        self.stiffness_softening = stiffness_softening;
+        self.friction_softening = friction_softening;
+        self
+    }
+}
+
+impl Default for ThermalProperties {
+    /// An insulating material that never melts.
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+#[derive(Shader)]
+#[shader(src = "thermal.wgsl", shader_defs = "dim_shader_defs")]
+pub struct WgThermal;
+
+wgcore::test_shader_compilation!(WgThermal, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::ThermalProperties;
+    use crate::models::ConstitutiveModel;
+    use crate::solver::{Particle, SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::vector;
+    use rapier::math::Vector;
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+
+    fn params() -> SimulationParams {
+        SimulationParams {
+            gravity: vector![0.0, 0.0, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        }
+    }
+
+    fn heated_block(
+        origin: Vector<f32>,
+        thermal: ThermalProperties,
+        temperature: f32,
+    ) -> Vec<Particle> {
+        particle_block(10, 0.5, origin)
+            .into_iter()
+            .map(|mut particle| {
+                particle.dynamics.temperature = temperature;
+                particle.with_thermal(thermal)
+            })
+            .collect()
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn block_melts_above_melting_point() {
+        let thermal = ThermalProperties::new(1.0, 1.0).with_melting_point(50.0);
+        let hot = heated_block(vector![0.0, 0.0, 0.0], thermal, 100.0);
+        let cold = heated_block(vector![0.0, 0.0, 0.0], thermal, 0.0);
+
+        let bodies = RigidBodySet::default();
+        let colliders = ColliderSet::default();
+        let hot = simulate(&hot, params(), &bodies, &colliders, 10).await;
+        let cold = simulate(&cold, params(), &bodies, &colliders, 10).await;
+
+        let fluid = ConstitutiveModel::Fluid as u32;
+        assert!(hot.model_ids.iter().all(|id| *id == fluid));
+        assert!(cold.model_ids.iter().all(|id| *id != fluid));
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn heat_diffuses_between_blocks_in_contact() {
+        let thermal = ThermalProperties::new(10.0, 1.0);
+        // NOTE: the blocks are separated by the particle spacing so they are in contact.
+        let mut particles = heated_block(vector![0.0, 0.0, 0.0], thermal, 100.0);
+        particles.extend(heated_block(vector![5.0, 0.0, 0.0], thermal, 0.0));
+        let num_hot = particles.len() / 2;
+
+        let snapshot = simulate(
+            &particles,
+            params(),
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            100,
+        )
+        .await;
+
+        let mean_temperature = |range: std::ops::Range<usize>| {
+            let len = range.len() as f32;
+            snapshot.dynamics[range]
+                .iter()
+                .map(|dynamics| dynamics.temperature)
+                .sum::<f32>()
+                / len
+        };
+        let hot = mean_temperature(0..num_hot);
+        let cold = mean_temperature(num_hot..particles.len());
+        assert!(hot < 99.0, "{hot}");
+        assert!(cold > 1.0, "{cold}");
+        assert!(hot > cold, "{hot} {cold}");
+    }
+}
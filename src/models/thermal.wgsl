@@ -0,0 +1,47 @@
+//! Temperature-dependent material parameters and heat conduction.
+
+#define_import_path wgsparkl::models::thermal
+
+
+struct ThermalProperties {
+    conductivity: f32,
+    specific_heat: f32,
+    reference_temperature: f32,
+    melting_point: f32,
+    stiffness_softening: f32,
+    friction_softening: f32,
+}
+
+// Bounds of the temperature-dependent factor of the elastic coefficients, to avoid
+// degenerate (or exploding) stiffnesses at extreme temperatures.
+const MIN_STIFFNESS_FACTOR: f32 = 1.0e-3;
+const MAX_STIFFNESS_FACTOR: f32 = 1.0e3;
+
+// Factor applied to the elastic coefficients at the given temperature.
+fn stiffness_factor(props: ThermalProperties, temperature: f32) -> f32 {
+    let factor = exp(-props.stiffness_softening * (temperature - props.reference_temperature));
+    return clamp(factor, MIN_STIFFNESS_FACTOR, MAX_STIFFNESS_FACTOR);
+}
+
+// Change of the Drucker-Prager friction angle at the given temperature.
+fn friction_angle_offset(props: ThermalProperties, temperature: f32) -> f32 {
+    return -props.friction_softening * (temperature - props.reference_temperature);
+}
+
+fn is_melted(props: ThermalProperties, temperature: f32) -> bool {
+    return temperature > props.melting_point;
+}
+
+// The factor converting the temperature gradient of a particle into the heat flux it
+// transfers to the grid during P2G.
+//
+// This is the heat conducted over `dt` through the particle volume (`-dt * V * k * ∇T · ∇w`, with the
+// MLS approximation `∇w = w * inv_d * (x_i - x_p)`), divided by the specific heat so it can be added to
+// the mass-weighted temperature of the grid nodes.
+fn heat_flux_factor(props: ThermalProperties, volume: f32, inv_d: f32, dt: f32) -> f32 {
+    if props.specific_heat <= 0.0 {
+        return 0.0;
+    }
+
+    return -dt * volume * props.conductivity * inv_d / props.specific_heat;
+}
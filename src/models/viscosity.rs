@@ -0,0 +1,71 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+use wgebra::{WgSvd2, WgSvd3};
+
+/// Rate-dependent stress added on top of the stress of the particle’s
+/// [`ConstitutiveModel`](crate::models::ConstitutiveModel).
+///
+/// The viscous stress follows the Herschel-Bulkley law `τ = yield_stress + consistency * γ̇^flow_index`
+/// where `γ̇` is the shear rate. Combined with an elastic model, this gives a Kelvin-Voigt
+/// viscoelastic solid. Combined with [`ConstitutiveModel::Fluid`](crate::models::ConstitutiveModel::Fluid),
+/// this gives a non-Newtonian fluid. A non-zero `relaxation_time` additionally makes the elastic
+/// shear strain decay over time, like a Maxwell material.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct Viscosity {
+    /// The viscosity for a Newtonian flow (`flow_index == 1`).
+    pub consistency: f32,
+    /// Values smaller than 1 give a shear-thinning material, and values greater than 1 give a
+    /// shear-thickening material.
+    pub flow_index: f32,
+    /// The stress below which the material doesn’t flow.
+    pub yield_stress: f32,
+    /// The relaxation time of the Maxwell model. Zero to disable the relaxation.
+    pub relaxation_time: f32,
+}
+
+impl Viscosity {
+    /// Kelvin-Voigt damping with a constant viscosity.
+    pub fn kelvin_voigt(viscosity: f32) -> Self {
+        Self::herschel_bulkley(0.0, viscosity, 1.0)
+    }
+
+    /// Maxwell viscoelasticity, relaxing the shear stress over `relaxation_time`.
+    pub fn maxwell(relaxation_time: f32) -> Self {
+        Self {
+            relaxation_time,
+            ..Self::kelvin_voigt(0.0)
+        }
+    }
+
+    /// Bingham plastic: no flow below `yield_stress` and a constant viscosity above it.
+    pub fn bingham(yield_stress: f32, plastic_viscosity: f32) -> Self {
+        Self::herschel_bulkley(yield_stress, plastic_viscosity, 1.0)
+    }
+
+    pub fn herschel_bulkley(yield_stress: f32, consistency: f32, flow_index: f32) -> Self {
+        Self {
+            consistency,
+            flow_index,
+            yield_stress,
+            relaxation_time: 0.0,
+        }
+    }
+}
+
+impl Default for Viscosity {
+    /// No viscosity.
+    fn default() -> Self {
+        Self::kelvin_voigt(0.0)
+    }
+}
+
+#[derive(Shader)]
+#[shader(
+    derive(WgSvd2, WgSvd3),
+    src = "viscosity.wgsl",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgViscosity;
+
+wgcore::test_shader_compilation!(WgViscosity, wgcore, crate::dim_shader_defs());
@@ -0,0 +1,84 @@
+//! Rate-dependent stress for viscoelastic solids and non-Newtonian fluids.
+
+#define_import_path wgsparkl::models::viscosity
+#import wgebra::svd2 as Svd2
+#import wgebra::svd3 as Svd3
+
+
+struct Viscosity {
+    consistency: f32,
+    flow_index: f32,
+    yield_stress: f32,
+    relaxation_time: f32,
+}
+
+// Regularization parameter of the yield stress term (Papanastasiou), avoiding the
+// infinite viscosity of the Herschel-Bulkley model at rest.
+const YIELD_REGULARIZATION: f32 = 100.0;
+const MIN_SHEAR_RATE: f32 = 1.0e-4;
+
+// Effective viscosity given by the Herschel-Bulkley law. This is the Kelvin-Voigt (Newtonian)
+// viscosity when `flow_index == 1` and `yield_stress == 0`, and a Bingham plastic when
+// `flow_index == 1` and `yield_stress > 0`.
+fn effective_viscosity(model: Viscosity, shear_rate: f32) -> f32 {
+    let rate = max(shear_rate, MIN_SHEAR_RATE);
+    let yield_term = model.yield_stress * (1.0 - exp(-YIELD_REGULARIZATION * rate)) / rate;
+    return yield_term + model.consistency * pow(rate, model.flow_index - 1.0);
+}
+
+#if DIM == 2
+fn kirchoff_stress(model: Viscosity, deformation_gradient: mat2x2<f32>, velocity_gradient: mat2x2<f32>) -> mat2x2<f32> {
+    let strain_rate = (velocity_gradient + transpose(velocity_gradient)) * 0.5;
+    let trace = strain_rate.x.x + strain_rate.y.y;
+    var deviatoric_strain_rate = strain_rate;
+    deviatoric_strain_rate.x.x -= trace / 2.0;
+    deviatoric_strain_rate.y.y -= trace / 2.0;
+#else
+fn kirchoff_stress(model: Viscosity, deformation_gradient: mat3x3<f32>, velocity_gradient: mat3x3<f32>) -> mat3x3<f32> {
+    let strain_rate = (velocity_gradient + transpose(velocity_gradient)) * 0.5;
+    let trace = strain_rate.x.x + strain_rate.y.y + strain_rate.z.z;
+    var deviatoric_strain_rate = strain_rate;
+    deviatoric_strain_rate.x.x -= trace / 3.0;
+    deviatoric_strain_rate.y.y -= trace / 3.0;
+    deviatoric_strain_rate.z.z -= trace / 3.0;
+#endif
+    let shear_rate = sqrt(2.0 * frobenius_squared(deviatoric_strain_rate));
+    let j = max(determinant(deformation_gradient), 1.0e-10);
+    return deviatoric_strain_rate * (2.0 * effective_viscosity(model, shear_rate) * j);
+}
+
+// Maxwell relaxation: the deviatoric part of the elastic strain decays exponentially with
+// the relaxation time, letting the material flow under sustained loads.
+#if DIM == 2
+fn relax(model: Viscosity, deformation_gradient: mat2x2<f32>, dt: f32) -> mat2x2<f32> {
+    if model.relaxation_time <= 0.0 {
+        return deformation_gradient;
+    }
+
+    let svd = Svd2::svd(deformation_gradient);
+    let strain = log(max(svd.S, vec2(1.0e-6)));
+    let volumetric_strain = vec2((strain.x + strain.y) / 2.0);
+    let new_strain = volumetric_strain + (strain - volumetric_strain) * exp(-dt / model.relaxation_time);
+    return Svd2::recompose(Svd2::Svd(svd.U, exp(new_strain), svd.Vt));
+}
+
+fn frobenius_squared(m: mat2x2<f32>) -> f32 {
+    return dot(m.x, m.x) + dot(m.y, m.y);
+}
+#else
+fn relax(model: Viscosity, deformation_gradient: mat3x3<f32>, dt: f32) -> mat3x3<f32> {
+    if model.relaxation_time <= 0.0 {
+        return deformation_gradient;
+    }
+
+    let svd = Svd3::svd(deformation_gradient);
+    let strain = log(max(svd.S, vec3(1.0e-6)));
+    let volumetric_strain = vec3((strain.x + strain.y + strain.z) / 3.0);
+    let new_strain = volumetric_strain + (strain - volumetric_strain) * exp(-dt / model.relaxation_time);
+    return Svd3::recompose(Svd3::Svd(svd.U, exp(new_strain), svd.Vt));
+}
+
+fn frobenius_squared(m: mat3x3<f32>) -> f32 {
+    return dot(m.x, m.x) + dot(m.y, m.y) + dot(m.z, m.z);
+}
+#endif
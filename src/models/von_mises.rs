@@ -0,0 +1,49 @@
+use crate::dim_shader_defs;
+use wgcore::Shader;
+use wgebra::{WgSvd2, WgSvd3};
+
+/// Von Mises (J2) plasticity with linear isotropic hardening.
+///
+/// The yield surface doesn’t depend on the pressure, which is well suited for metals and clay.
+/// The yield stress grows by `hardening` per unit of accumulated plastic strain. The shear
+/// modulus used by the return mapping is the particle’s effective one, so it follows the
+/// hardening, damage and thermal softening of its elastic coefficients.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct VonMises {
+    pub yield_stress: f32,
+    pub hardening: f32,
+}
+
+impl VonMises {
+    /// Perfectly plastic von Mises model (without hardening).
+    pub fn new(yield_stress: f32) -> Self {
+        Self {
+            yield_stress,
+            hardening: 0.0,
+        }
+    }
+
+    /// Sets the linear isotropic hardening modulus.
+    pub fn with_hardening(mut self, hardening: f32) -> Self {
+        self.hardening = hardening;
+        self
+    }
+}
+
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub struct VonMisesPlasticState {
+    /// The accumulated equivalent plastic strain.
+    pub plastic_strain: f32,
+}
+
+#[derive(Shader)]
+#[shader(
+    derive(WgSvd2, WgSvd3),
+    src = "von_mises.wgsl",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgVonMises;
+
+wgcore::test_shader_compilation!(WgVonMises, wgcore, crate::dim_shader_defs());
@@ -0,0 +1,80 @@
+//! Von Mises (J2) plasticity model with linear isotropic hardening.
+
+#define_import_path wgsparkl::models::von_mises
+#import wgebra::svd2 as Svd2
+#import wgebra::svd3 as Svd3
+
+
+struct Plasticity {
+    yield_stress: f32,
+    hardening: f32,
+}
+
+struct PlasticState {
+    // Accumulated equivalent plastic strain.
+    plastic_strain: f32,
+}
+
+#if DIM == 2
+struct VonMisesResult {
+    state: PlasticState,
+    deformation_gradient: mat2x2<f32>,
+}
+
+// NOTE: `mu` is the (effective) shear modulus of the particle.
+fn project(plasticity: Plasticity, state: PlasticState, mu: f32, deformation_gradient: mat2x2<f32>) -> VonMisesResult {
+    if mu <= 0.0 {
+        // The material has no shear stiffness left (e.g. fully damaged).
+        return VonMisesResult(state, deformation_gradient);
+    }
+
+    let svd = Svd2::svd(deformation_gradient);
+    let strain = log(max(svd.S, vec2(1.0e-6)));
+    let deviatoric_strain = strain - vec2((strain.x + strain.y) / 2.0);
+    let deviatoric_strain_norm = length(deviatoric_strain);
+    let yield_stress = plasticity.yield_stress + plasticity.hardening * state.plastic_strain;
+    let delta_gamma = deviatoric_strain_norm - sqrt(2.0 / 3.0) * yield_stress / (2.0 * mu);
+
+    if delta_gamma <= 0.0 {
+        return VonMisesResult(state, deformation_gradient);
+    }
+
+    let new_strain = strain - deviatoric_strain * (delta_gamma / deviatoric_strain_norm);
+    let new_deformation_gradient = Svd2::recompose(Svd2::Svd(svd.U, exp(new_strain), svd.Vt));
+    return VonMisesResult(
+        PlasticState(state.plastic_strain + sqrt(2.0 / 3.0) * delta_gamma),
+        new_deformation_gradient,
+    );
+}
+#else
+struct VonMisesResult {
+    state: PlasticState,
+    deformation_gradient: mat3x3<f32>,
+}
+
+// NOTE: `mu` is the (effective) shear modulus of the particle.
+fn project(plasticity: Plasticity, state: PlasticState, mu: f32, deformation_gradient: mat3x3<f32>) -> VonMisesResult {
+    if mu <= 0.0 {
+        // The material has no shear stiffness left (e.g. fully damaged).
+        return VonMisesResult(state, deformation_gradient);
+    }
+
+    let svd = Svd3::svd(deformation_gradient);
+    let strain = log(max(svd.S, vec3(1.0e-6)));
+    let deviatoric_strain = strain - vec3((strain.x + strain.y + strain.z) / 3.0);
+    let deviatoric_strain_norm = length(deviatoric_strain);
+    let yield_stress = plasticity.yield_stress + plasticity.hardening * state.plastic_strain;
+    let delta_gamma = deviatoric_strain_norm - sqrt(2.0 / 3.0) * yield_stress / (2.0 * mu);
+
+    if delta_gamma <= 0.0 {
+        return VonMisesResult(state, deformation_gradient);
+    }
+
+    let new_strain = strain - deviatoric_strain * (delta_gamma / deviatoric_strain_norm);
+    let new_deformation_gradient = Svd3::recompose(Svd3::Svd(svd.U, exp(new_strain), svd.Vt));
+    return VonMisesResult(
+        PlasticState(state.plastic_strain + sqrt(2.0 / 3.0) * delta_gamma),
+        new_deformation_gradient,
+    );
+}
+#endif
@@ -1,3 +1,5 @@
+use crate::collision::GpuCollisionMeshes;
+use crate::error::Error;
 use crate::grid::grid::{GpuGrid, WgGrid};
 use crate::grid::prefix_sum::{PrefixSumWorkspace, WgPrefixSum};
 #[cfg(target_os = "macos")]
@@ -5,21 +7,23 @@ use crate::grid::sort::TouchParticleBlocks;
 use crate::grid::sort::WgSort;
 use crate::models::GpuModels;
 use crate::solver::{
-    GpuImpulses, GpuParticles, GpuRigidParticles, GpuSimulationParams, Particle, SimulationParams,
-    WgG2P, WgG2PCdf, WgGridUpdate, WgGridUpdateCdf, WgP2G, WgP2GCdf, WgParticleUpdate,
+    ContactMaterial, GpuContactMaterials, GpuEmittedParticles, GpuImpulses, GpuParticleReadback,
+    GpuParticleSinks, GpuParticles, GpuRigidParticles, GpuSimulationParams, Particle,
+    ParticleDynamics, ParticleFields, ParticleSink, ParticleSnapshot, SimulationParams, WgG2P,
+    WgG2PCdf, WgGridUpdate, WgGridUpdateCdf, WgP2G, WgP2GCdf, WgParticleEmission, WgParticleUpdate,
     WgRigidImpulses, WgRigidParticleUpdate,
 };
-use naga_oil::compose::ComposerError;
-use rapier::dynamics::RigidBodySet;
-use rapier::geometry::ColliderSet;
+use encase::{ShaderSize, StorageBuffer};
+use rapier::dynamics::{RigidBodyPosition, RigidBodySet};
+use rapier::geometry::{Collider, ColliderHandle, ColliderSet};
 use wgcore::hot_reloading::HotReloadState;
 use wgcore::kernel::KernelInvocationQueue;
 use wgcore::tensor::GpuVector;
 use wgcore::Shader;
 use wgparry::math::GpuSim;
-use wgpu::{BufferUsages, Device};
+use wgpu::{BufferUsages, CommandEncoder, Device, Queue};
 use wgrapier::dynamics::body::{BodyCoupling, BodyCouplingEntry};
-use wgrapier::dynamics::{GpuBodySet, WgIntegrate};
+use wgrapier::dynamics::{GpuBodySet, GpuVelocity, WgIntegrate};
 
 pub struct MpmPipeline {
     grid: WgGrid,
@@ -35,31 +39,34 @@ pub struct MpmPipeline {
     g2p: WgG2P,
     g2p_cdf: WgG2PCdf,
     rigid_particles_update: WgRigidParticleUpdate,
+    emission: WgParticleEmission,
     pub impulses: WgRigidImpulses,
 }
 
 impl MpmPipeline {
-    pub fn init_hot_reloading(&self, state: &mut HotReloadState) {
-        WgGrid::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgPrefixSum::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgSort::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgP2G::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgP2GCdf::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgGridUpdate::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgGridUpdateCdf::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgParticleUpdate::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgG2P::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgG2PCdf::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgIntegrate::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgRigidImpulses::watch_sources(state).unwrap(); // TODO: don’t unwrap
-        WgRigidParticleUpdate::watch_sources(state).unwrap(); // TODOO: don’t unwrap
+    pub fn init_hot_reloading(&self, state: &mut HotReloadState) -> Result<(), Error> {
+        WgGrid::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgPrefixSum::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgSort::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgP2G::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgP2GCdf::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgGridUpdate::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgGridUpdateCdf::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgParticleUpdate::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgG2P::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgG2PCdf::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgIntegrate::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgRigidImpulses::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgRigidParticleUpdate::watch_sources(state).map_err(Error::hot_reloading)?;
+        WgParticleEmission::watch_sources(state).map_err(Error::hot_reloading)?;
+        Ok(())
     }
 
     pub fn reload_if_changed(
         &mut self,
         device: &Device,
         state: &HotReloadState,
-    ) -> Result<bool, ComposerError> {
+    ) -> Result<bool, Error> {
         let mut changed = false;
         changed = self.grid.reload_if_changed(device, state)? || changed;
         changed = self.prefix_sum.reload_if_changed(device, state)? || changed;
@@ -76,22 +83,73 @@ impl MpmPipeline {
             .rigid_particles_update
             .reload_if_changed(device, state)?
             || changed;
+        changed = self.emission.reload_if_changed(device, state)? || changed;
 
         Ok(changed)
     }
 }
 
+/// How a collider attached to a rigid body interacts with the particles.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ColliderCoupling {
+    /// The rigid body drives the particles and is pushed back by them.
+    #[default]
+    TwoWays,
+    /// The rigid body drives the particles but is never pushed back by them, e.g., for
+    /// kinematic tools.
+    ///
+    /// The impulses applied by the particles are not accumulated during the particle-to-grid
+    /// transfer, so [`GpuImpulses::read_total_impulses`] reports zero impulses for these bodies.
+    OneWay,
+    /// The collider doesn’t interact with the particles.
+    Ignored,
+}
+
+/// Builds the coupling entries of every collider attached to a rigid body, with a coupling
+/// mode selected by `mode`.
+///
+/// The result is meant to be passed to [`MpmData::with_select_coupling`]. Colliders without a
+/// parent rigid body, or with [`ColliderCoupling::Ignored`], are not coupled with the particles.
+/// The coupling mode can for example be read from the collider’s user data.
+pub fn select_coupling(
+    colliders: &ColliderSet,
+    mut mode: impl FnMut(ColliderHandle, &Collider) -> ColliderCoupling,
+) -> Vec<BodyCouplingEntry> {
+    colliders
+        .iter()
+        .filter_map(|(co_handle, co)| {
+            let rb_handle = co.parent()?;
+            let mode = match mode(co_handle, co) {
+                ColliderCoupling::TwoWays => BodyCoupling::TwoWays,
+                ColliderCoupling::OneWay => BodyCoupling::OneWay,
+                ColliderCoupling::Ignored => return None,
+            };
+            Some(BodyCouplingEntry {
+                body: rb_handle,
+                collider: co_handle,
+                mode,
+            })
+        })
+        .collect()
+}
+
 pub struct MpmData {
     pub sim_params: GpuSimulationParams,
     pub grid: GpuGrid,
     pub particles: GpuParticles, // TODO: keep private?
     pub rigid_particles: GpuRigidParticles,
+    pub collision_meshes: GpuCollisionMeshes,
     pub bodies: GpuBodySet,
     pub impulses: GpuImpulses,
     pub poses_staging: GpuVector<GpuSim>,
+    pub sinks: GpuParticleSinks,
+    /// The staging buffers of the particles inserted by [`MpmPipeline::emit_particles`].
+    pub emitted: GpuEmittedParticles,
+    pub contact_materials: GpuContactMaterials,
     prefix_sum: PrefixSumWorkspace,
     models: GpuModels,
     coupling: Vec<BodyCouplingEntry>,
+    grid_load_factor: f32,
 }
 
 impl MpmData {
@@ -103,18 +161,34 @@ impl MpmData {
         colliders: &ColliderSet,
         cell_width: f32,
         grid_capacity: u32,
-    ) -> Self {
-        let coupling: Vec<_> = colliders
-            .iter()
-            .filter_map(|(co_handle, co)| {
-                let rb_handle = co.parent()?;
-                Some(BodyCouplingEntry {
-                    body: rb_handle,
-                    collider: co_handle,
-                    mode: BodyCoupling::TwoWays,
-                })
-            })
-            .collect();
+    ) -> Result<Self, Error> {
+        Self::with_particle_capacity(
+            device,
+            params,
+            particles,
+            bodies,
+            colliders,
+            cell_width,
+            grid_capacity,
+            particles.len(),
+        )
+    }
+
+    /// Initializes the simulation data with room for up to `particle_capacity` particles.
+    ///
+    /// The extra capacity is used by particles added with [`MpmPipeline::emit_particles`]. It
+    /// is also the maximum number of particles inserted by a single emission.
+    pub fn with_particle_capacity(
+        device: &Device,
+        params: SimulationParams,
+        particles: &[Particle],
+        bodies: &RigidBodySet,
+        colliders: &ColliderSet,
+        cell_width: f32,
+        grid_capacity: u32,
+        particle_capacity: usize,
+    ) -> Result<Self, Error> {
+        let coupling = select_coupling(colliders, |_, _| ColliderCoupling::TwoWays);
         Self::with_select_coupling(
             device,
             params,
@@ -124,9 +198,13 @@ impl MpmData {
             coupling,
             cell_width,
             grid_capacity,
+            particle_capacity,
         )
     }
 
+    /// Initializes the simulation data with an explicit set of coupled colliders.
+    ///
+    /// See [`select_coupling`] for selecting the coupling mode of each collider.
     pub fn with_select_coupling(
         device: &Device,
         params: SimulationParams,
@@ -136,44 +214,299 @@ impl MpmData {
         coupling: Vec<BodyCouplingEntry>,
         cell_width: f32,
         grid_capacity: u32,
-    ) -> Self {
+        particle_capacity: usize,
+    ) -> Result<Self, Error> {
+        let max_binding_size = device.limits().max_storage_buffer_binding_size as u64;
+        let particle_size = <ParticleDynamics as ShaderSize>::SHADER_SIZE.get();
+        let required_binding_size = particle_capacity as u64 * particle_size;
+        if required_binding_size > max_binding_size {
+            return Err(Error::DeviceLimit {
+                limit: "max_storage_buffer_binding_size",
+                required: required_binding_size,
+                supported: max_binding_size,
+            });
+        }
+
+        let max_grid_capacity = GpuGrid::max_capacity(device);
+        if grid_capacity.next_power_of_two() > max_grid_capacity {
+            return Err(Error::GridOverflow {
+                required_capacity: grid_capacity,
+                max_capacity: max_grid_capacity,
+            });
+        }
+
         let sampling_step = cell_width; // TODO: * 1.5 ?
         let bodies = GpuBodySet::from_rapier(device, bodies, colliders, &coupling);
         let sim_params = GpuSimulationParams::new(device, params);
-        let models = GpuModels::from_particles(device, particles);
-        let particles = GpuParticles::from_particles(device, particles);
+        let emitted = GpuEmittedParticles::with_capacity(
+            device,
+            particle_capacity.saturating_sub(particles.len()),
+        );
+        let models = GpuModels::with_capacity(device, particles, particle_capacity);
+        let particles = GpuParticles::with_capacity(device, particles, particle_capacity);
+        let sinks = GpuParticleSinks::new(device, &[], particles.capacity());
+        let contact_materials = GpuContactMaterials::from_rapier(device, colliders, &coupling);
         let rigid_particles =
             GpuRigidParticles::from_rapier(device, colliders, &bodies, &coupling, sampling_step);
-        let grid = GpuGrid::with_capacity(device, grid_capacity, cell_width);
+        let collision_meshes =
+            GpuCollisionMeshes::from_rapier(device, colliders, &bodies, &coupling);
+        let grid = GpuGrid::with_domain(device, grid_capacity, cell_width, &params.domain);
         let prefix_sum = PrefixSumWorkspace::with_capacity(device, grid_capacity);
-        let impulses = GpuImpulses::new(device);
+        let impulses = GpuImpulses::from_coupling(device, &coupling);
         let poses_staging = GpuVector::uninit(
             device,
             bodies.len(),
             BufferUsages::COPY_DST | BufferUsages::MAP_READ,
         );
 
-        Self {
+        Ok(Self {
             sim_params,
             particles,
             rigid_particles,
+            collision_meshes,
             bodies,
             impulses,
             grid,
             prefix_sum,
             models,
             poses_staging,
+            sinks,
+            emitted,
+            contact_materials,
             coupling,
+            grid_load_factor: Self::DEFAULT_GRID_LOAD_FACTOR,
+        })
+    }
+
+    /// The default value of [`Self::grid_load_factor`].
+    pub const DEFAULT_GRID_LOAD_FACTOR: f32 = 0.75;
+
+    /// The fraction of the grid capacity above which [`Self::grow_grid_if_needed`] reallocates
+    /// the grid.
+    pub fn grid_load_factor(&self) -> f32 {
+        self.grid_load_factor
+    }
+
+    /// Sets the fraction of the grid capacity above which [`Self::grow_grid_if_needed`]
+    /// reallocates the grid.
+    ///
+    /// Smaller values leave more room for particles spreading out between two readbacks, and
+    /// keep the collisions in the grid hashmap low. Returns [`Error::InvalidGridLoadFactor`] if
+    /// `load_factor` isn’t in `]0, 1]`.
+    pub fn set_grid_load_factor(&mut self, load_factor: f32) -> Result<(), Error> {
+        if !(load_factor > 0.0 && load_factor <= 1.0) {
+            return Err(Error::InvalidGridLoadFactor(load_factor));
         }
+        self.grid_load_factor = load_factor;
+        Ok(())
+    }
+
+    /// Reads back the grid occupancy, and grows the grid if it exceeds the [`Self::grid_load_factor`].
+    ///
+    /// [`GpuGrid::queue_meta_readback`] must have been encoded after the last step. The grid
+    /// buffers are reallocated so the next call to [`MpmPipeline::queue_step`] must happen
+    /// after this. Returns `true` if the grid was reallocated.
+    pub async fn grow_grid_if_needed(&mut self, device: &Device) -> Result<bool, Error> {
+        let num_active_blocks = self.grid.read_num_active_blocks(device).await?;
+        let grown = self
+            .grid
+            .grow_if_needed(device, num_active_blocks, self.grid_load_factor)?;
+        if grown {
+            self.prefix_sum.reserve(device, self.grid.capacity());
+        }
+        Ok(grown)
+    }
+
+    /// Uploads the poses and velocities of the coupled rigid bodies to the gpu.
+    ///
+    /// The velocities of the dynamic bodies include the gravity accumulated over one of the
+    /// `num_substeps` substeps of a simulation step of length `dt`.
+    pub fn upload_rigid_bodies(
+        &self,
+        queue: &Queue,
+        bodies: &RigidBodySet,
+        colliders: &ColliderSet,
+        dt: f32,
+        num_substeps: usize,
+    ) {
+        // PERF: don’t reallocate the buffers at each step.
+        let poses: Vec<GpuSim> = self
+            .coupling
+            .iter()
+            .map(|coupling| {
+                let co = &colliders[coupling.collider];
+                #[cfg(feature = "dim2")]
+                return (*co.position()).into();
+                #[cfg(feature = "dim3")]
+                return GpuSim::from_isometry(*co.position(), 1.0);
+            })
+            .collect();
+        queue.write_buffer(
+            self.bodies.poses().buffer(),
+            0,
+            bytemuck::cast_slice(&poses),
+        );
+
+        let gravity = self.sim_params.cpu_params.gravity;
+        let vels: Vec<_> = self
+            .coupling
+            .iter()
+            .map(|coupling| {
+                let rb = &bodies[coupling.body];
+                GpuVelocity {
+                    linear: *rb.linvel()
+                        + gravity * dt * (rb.is_dynamic() as u32 as f32) / (num_substeps as f32),
+                    #[allow(clippy::clone_on_copy)] // Needed for the 2d/3d switch.
+                    angular: rb.angvel().clone(),
+                }
+            })
+            .collect();
+        let mut vels_bytes = vec![];
+        let mut buffer = StorageBuffer::new(&mut vels_bytes);
+        buffer.write(&vels).unwrap();
+        queue.write_buffer(self.bodies.vels().buffer(), 0, &vels_bytes);
+    }
+
+    /// Queues the copy of the body poses into [`Self::poses_staging`] and of the grid metadata
+    /// into its staging buffer.
+    ///
+    /// This must be encoded after the simulation step, before calling
+    /// [`Self::apply_rigid_body_feedback`] and [`Self::grow_grid_if_needed`].
+    pub fn queue_step_readback(&self, encoder: &mut CommandEncoder) {
+        self.poses_staging.copy_from(encoder, self.bodies.poses());
+        self.grid.queue_meta_readback(encoder);
+    }
+
+    /// Reads back the body poses reached at the end of the simulation step, and sets the
+    /// velocities of the dynamic two-ways coupled bodies so they reach the same poses after `dt`.
+    ///
+    /// [`Self::queue_step_readback`] must have been encoded after the last step.
+    pub async fn apply_rigid_body_feedback(
+        &self,
+        device: &Device,
+        bodies: &mut RigidBodySet,
+        dt: f32,
+    ) -> Result<(), Error> {
+        let new_poses = self
+            .poses_staging
+            .read(device)
+            .await
+            .map_err(Error::readback)?;
+
+        for (coupling, new_pose) in self.coupling.iter().zip(new_poses.iter()) {
+            let rb = &mut bodies[coupling.body];
+            // NOTE: one-way bodies are never affected by the particles.
+            if rb.is_dynamic() && matches!(coupling.mode, BodyCoupling::TwoWays) {
+                let interpolator = RigidBodyPosition {
+                    position: *rb.position(),
+                    #[cfg(feature = "dim2")]
+                    next_position: new_pose.similarity.isometry,
+                    #[cfg(feature = "dim3")]
+                    next_position: new_pose.isometry,
+                };
+                let vel = interpolator
+                    .interpolate_velocity(1.0 / dt, &rb.mass_properties().local_mprops.local_com);
+                rb.set_linvel(vel.linvel, true);
+                rb.set_angvel(vel.angvel, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the contact material between the particles and the given `collider`.
+    ///
+    /// The contact materials are initialized from the friction and restitution coefficients
+    /// of the colliders. Returns `false` if the collider isn’t coupled with the particles.
+    pub fn set_contact_material(
+        &mut self,
+        queue: &Queue,
+        collider: ColliderHandle,
+        material: ContactMaterial,
+    ) -> bool {
+        let Some(i) = self.coupling.iter().position(|c| c.collider == collider) else {
+            return false;
+        };
+        self.contact_materials.set(queue, i, material);
+        true
     }
 
     pub fn coupling(&self) -> &[BodyCouplingEntry] {
         &self.coupling
     }
+
+    /// Replaces the regions removing every particle entering them.
+    pub fn set_sinks(&mut self, device: &Device, sinks: &[ParticleSink]) {
+        self.sinks = GpuParticleSinks::new(device, sinks, self.particles.capacity());
+    }
+
+    /// The per-particle material buffers.
+    ///
+    /// In particular, [`GpuModels::phases`] can be copied to a staging buffer for reading
+    /// back which particles got damaged or broken.
+    pub fn models(&self) -> &GpuModels {
+        &self.models
+    }
+
+    /// Downloads the given `fields` of the live particles.
+    ///
+    /// This allocates new staging buffers and waits for the gpu at each call. Use a
+    /// [`GpuParticleReadback`] directly for reusing the staging buffers, or for encoding the
+    /// copy alongside the simulation step.
+    pub async fn read_particles(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        fields: ParticleFields,
+    ) -> Result<ParticleSnapshot, Error> {
+        let readback = GpuParticleReadback::new(device, &self.particles, fields);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        readback.queue_readback(&mut encoder, &self.particles, &self.models);
+        queue.submit(Some(encoder.finish()));
+        readback.read(device).await
+    }
 }
 
 impl MpmPipeline {
-    pub fn new(device: &Device) -> Result<Self, ComposerError> {
+    /// The maximum number of storage buffers bound to a single kernel (reached by p2g).
+    pub const REQUIRED_STORAGE_BUFFERS_PER_SHADER_STAGE: u32 = 10;
+    /// The maximum workgroup memory used by a single kernel (reached by p2g), in bytes.
+    ///
+    /// This is the size of the shared data of the 10x10 cells around a block, plus
+    /// 8 bytes for the linked-list lengths.
+    #[cfg(feature = "dim2")]
+    pub const REQUIRED_COMPUTE_WORKGROUP_STORAGE_SIZE: u32 = 10 * 10 * 88 + 8;
+    /// The maximum workgroup memory used by a single kernel (reached by p2g), in bytes.
+    ///
+    /// This is the size of the shared data of the 6x6x6 cells around a block, plus
+    /// 8 bytes for the linked-list lengths.
+    #[cfg(feature = "dim3")]
+    pub const REQUIRED_COMPUTE_WORKGROUP_STORAGE_SIZE: u32 = 6 * 6 * 6 * 136 + 8;
+
+    pub fn new(device: &Device) -> Result<Self, Error> {
+        let limits = device.limits();
+        let required_limits = [
+            (
+                "max_storage_buffers_per_shader_stage",
+                Self::REQUIRED_STORAGE_BUFFERS_PER_SHADER_STAGE,
+                limits.max_storage_buffers_per_shader_stage,
+            ),
+            (
+                "max_compute_workgroup_storage_size",
+                Self::REQUIRED_COMPUTE_WORKGROUP_STORAGE_SIZE,
+                limits.max_compute_workgroup_storage_size,
+            ),
+        ];
+        for (limit, required, supported) in required_limits {
+            if supported < required {
+                return Err(Error::DeviceLimit {
+                    limit,
+                    required: required as u64,
+                    supported: supported as u64,
+                });
+            }
+        }
+
         Ok(Self {
             grid: WgGrid::from_device(device)?,
             prefix_sum: WgPrefixSum::from_device(device)?,
@@ -189,9 +522,42 @@ impl MpmPipeline {
             #[cfg(target_os = "macos")]
             touch_particle_blocks: TouchParticleBlocks::from_device(device),
             impulses: WgRigidImpulses::from_device(device)?,
+            emission: WgParticleEmission::from_device(device)?,
         })
     }
 
+    /// Inserts new particles into the simulation.
+    ///
+    /// The particles are written to the [`MpmData::emitted`] staging buffers, then added after
+    /// the live particles of `data` within the capacity given to
+    /// [`MpmData::with_particle_capacity`]. Particles that don’t fit are ignored.
+    ///
+    /// The insertion is submitted to `queue` right away, in its own command buffer, so that it
+    /// happens exactly once regardless of the number of substeps encoded afterward. Call this
+    /// between two simulation steps, for example once per frame.
+    pub fn emit_particles(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        data: &MpmData,
+        particles: &[Particle],
+    ) {
+        if data.emitted.write(queue, particles) == 0 {
+            return;
+        }
+
+        let mut invocations = KernelInvocationQueue::new(device);
+        self.emission.queue_append(
+            &mut invocations,
+            &data.particles,
+            &data.models,
+            &data.emitted,
+        );
+        let mut encoder = device.create_command_encoder(&Default::default());
+        invocations.encode(&mut encoder, None);
+        queue.submit(Some(encoder.finish()));
+    }
+
     pub fn queue_step<'a>(
         &'a self,
         data: &mut MpmData,
@@ -206,6 +572,13 @@ impl MpmPipeline {
             .queue(queue, &data.bodies, &data.rigid_particles);
 
         queue.compute_pass("grid sort", add_timestamps);
+        self.emission.queue_removal(
+            queue,
+            &data.particles,
+            &data.models,
+            &data.sinks,
+            &data.sim_params,
+        );
         self.grid.queue_sort(
             &data.particles,
             &data.rigid_particles,
@@ -222,7 +595,8 @@ impl MpmPipeline {
 
         queue.compute_pass("grid_update_cdf", add_timestamps);
 
-        self.grid_update_cdf.queue(queue, &data.grid, &data.bodies);
+        self.grid_update_cdf
+            .queue(queue, &data.grid, &data.bodies, &data.collision_meshes);
 
         queue.compute_pass("p2g_cdf", add_timestamps);
 
@@ -231,8 +605,13 @@ impl MpmPipeline {
 
         queue.compute_pass("g2p_cdf", add_timestamps);
 
-        self.g2p_cdf
-            .queue(queue, &data.sim_params, &data.grid, &data.particles);
+        self.g2p_cdf.queue(
+            queue,
+            &data.sim_params,
+            &data.grid,
+            &data.particles,
+            &data.contact_materials,
+        );
 
         queue.compute_pass("p2g", add_timestamps);
         self.p2g.queue(
@@ -284,14 +663,19 @@ impl MpmPipeline {
 #[cfg(test)]
 #[cfg(feature = "dim3")]
 mod test {
-    use crate::models::ElasticCoefficients;
-    use crate::pipeline::{MpmData, MpmPipeline};
-    use crate::solver::{Particle, ParticleDynamics, SimulationParams};
-    use nalgebra::vector;
-    use rapier::prelude::{ColliderSet, RigidBodySet};
+    use crate::pipeline::{select_coupling, ColliderCoupling, MpmData, MpmPipeline};
+    use crate::solver::{
+        BodyImpulse, BoundaryCondition, ParticleDynamics, ParticleEmitter, ParticleFields,
+        SimulationDomain, SimulationParams,
+    };
+    use crate::test_utils::{particle_block, simulate};
+    use crate::Error;
+    use nalgebra::{point, vector};
+    use rapier::prelude::{ColliderBuilder, ColliderSet, RigidBodyBuilder, RigidBodySet};
     use wgcore::gpu::GpuInstance;
     use wgcore::kernel::KernelInvocationQueue;
-    use wgpu::Maintain;
+    use wgcore::tensor::GpuVector;
+    use wgpu::{BufferUsages, Maintain};
 
     #[futures_test::test]
     #[serial_test::serial]
@@ -300,25 +684,12 @@ mod test {
         let pipeline = MpmPipeline::new(gpu.device()).unwrap();
 
         let cell_width = 1.0;
-        let mut cpu_particles = vec![];
-        for i in 0..10 {
-            for j in 0..10 {
-                for k in 0..10 {
-                    let position = vector![i as f32, j as f32, k as f32] / cell_width / 2.0;
-                    cpu_particles.push(Particle {
-                        position,
-                        dynamics: ParticleDynamics::with_density(cell_width / 4.0, 1.0),
-                        model: ElasticCoefficients::from_young_modulus(100_000.0, 0.33),
-                        plasticity: None,
-                        phase: None,
-                    });
-                }
-            }
-        }
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
 
         let params = SimulationParams {
             gravity: vector![0.0, -9.81, 0.0],
             dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
         };
         let mut data = MpmData::new(
             gpu.device(),
@@ -328,7 +699,8 @@ mod test {
             &ColliderSet::default(),
             cell_width,
             100_000,
-        );
+        )
+        .unwrap();
         let mut queue = KernelInvocationQueue::new(gpu.device());
         pipeline.queue_step(&mut data, &mut queue, false);
 
@@ -341,4 +713,332 @@ mod test {
             println!("Sim step time: {}", t0.elapsed().as_secs_f32());
         }
     }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_emits_particles_once_per_frame() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+
+        let num_substeps = 10;
+        let frame_dt = 1.0 / 60.0;
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: frame_dt / num_substeps as f32,
+            domain: SimulationDomain::unbounded(),
+        };
+        let cpu_particles = particle_block(5, 0.5, vector![0.0, 0.0, 0.0]);
+        let mut data = MpmData::with_particle_capacity(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            1.0,
+            100_000,
+            1000,
+        )
+        .unwrap();
+        let mut emitter = ParticleEmitter::new(
+            particle_block(2, 0.5, vector![0.0, 0.0, 0.0]),
+            vector![0.0, 5.0, 0.0],
+            vector![0.0, -1.0, 0.0],
+            frame_dt,
+        );
+
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+        pipeline.queue_step(&mut data, &mut queue, false);
+
+        for _ in 0..3 {
+            let emitted = emitter.step(frame_dt);
+            assert_eq!(emitted.len(), 8);
+            pipeline.emit_particles(gpu.device(), gpu.queue(), &data, &emitted);
+
+            let mut encoder = gpu.device().create_command_encoder(&Default::default());
+            for _ in 0..num_substeps {
+                queue.encode(&mut encoder, None);
+            }
+            gpu.queue().submit(Some(encoder.finish()));
+        }
+
+        // Each emission is inserted once, not once per substep.
+        let snapshot = data
+            .read_particles(gpu.device(), gpu.queue(), ParticleFields::POSITIONS)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.len, cpu_particles.len() + 3 * 8);
+        let num_emitted = snapshot.positions.iter().filter(|pt| pt.y > 4.0).count();
+        assert_eq!(num_emitted, 3 * 8);
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_domain_confines_particles() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+
+        let cell_width = 1.0;
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 2.0, 0.0]);
+
+        let mins = vector![-2.0, 0.0, -2.0];
+        let maxs = vector![7.0, 20.0, 7.0];
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::new(mins, maxs, BoundaryCondition::Separate)
+                .with_friction(0.5),
+        };
+        let mut data = MpmData::new(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            cell_width,
+            100_000,
+        )
+        .unwrap();
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+        pipeline.queue_step(&mut data, &mut queue, false);
+
+        for _ in 0..100 {
+            let mut encoder = gpu.device().create_command_encoder(&Default::default());
+            queue.encode(&mut encoder, None);
+            gpu.queue().submit(Some(encoder.finish()));
+        }
+
+        let positions_staging = GpuVector::uninit(
+            gpu.device(),
+            data.particles.capacity() as u32,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        positions_staging.copy_from(&mut encoder, &data.particles.positions);
+        gpu.queue().submit(Some(encoder.finish()));
+
+        let positions: Vec<nalgebra::Vector4<f32>> =
+            positions_staging.read(gpu.device()).await.unwrap();
+
+        for pt in &positions[..cpu_particles.len()] {
+            assert!(
+                (0..3).all(|i| pt[i] >= mins[i] && pt[i] <= maxs[i]),
+                "{pt:?}"
+            );
+        }
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_periodic_domain_wraps_particles() {
+        // The particles start inside the domain and move along the periodic X axis, through
+        // the `maxs.x` face.
+        let mut cpu_particles = particle_block(6, 0.5, vector![5.0, 1.0, 1.0]);
+        for particle in &mut cpu_particles {
+            particle.dynamics.velocity = vector![10.0, 0.0, 0.0];
+        }
+
+        let mins = vector![0.0, 0.0, 0.0];
+        let maxs = vector![8.0, 20.0, 8.0];
+        let params = SimulationParams {
+            gravity: vector![0.0, 0.0, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::new(mins, maxs, BoundaryCondition::Slip)
+                .with_periodic_axis(0),
+        };
+        let snapshot = simulate(
+            &cpu_particles,
+            params,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            100,
+        )
+        .await;
+
+        assert_eq!(snapshot.len, cpu_particles.len());
+        for pt in &snapshot.positions {
+            assert!(pt.x >= mins.x && pt.x < maxs.x, "{pt:?}");
+        }
+        assert!(snapshot.positions.iter().any(|pt| pt.x < 2.0));
+
+        // The periodic face doesn’t stop the material: its mass and momentum are conserved.
+        let initial_dynamics: Vec<_> = cpu_particles.iter().map(|p| p.dynamics).collect();
+        let mass = |dynamics: &[ParticleDynamics]| dynamics.iter().map(|d| d.mass).sum::<f32>();
+        let momentum = |dynamics: &[ParticleDynamics]| {
+            dynamics.iter().map(|d| d.velocity.x * d.mass).sum::<f32>()
+        };
+        approx::assert_relative_eq!(
+            mass(&snapshot.dynamics),
+            mass(&initial_dynamics),
+            max_relative = 1.0e-5
+        );
+        approx::assert_relative_eq!(
+            momentum(&snapshot.dynamics),
+            momentum(&initial_dynamics),
+            max_relative = 0.05
+        );
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_trimesh_collider_supports_particles() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+
+        let cell_width = 1.0;
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 2.0, 0.0]);
+
+        // A floor made of two triangles with upward normals.
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+        let floor = bodies.insert(RigidBodyBuilder::fixed());
+        let vertices = vec![
+            point![-10.0, 0.0, -10.0],
+            point![10.0, 0.0, -10.0],
+            point![10.0, 0.0, 10.0],
+            point![-10.0, 0.0, 10.0],
+        ];
+        let indices = vec![[0, 2, 1], [0, 3, 2]];
+        colliders.insert_with_parent(
+            ColliderBuilder::trimesh(vertices, indices).unwrap(),
+            floor,
+            &mut bodies,
+        );
+
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let mut data = MpmData::new(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &bodies,
+            &colliders,
+            cell_width,
+            100_000,
+        )
+        .unwrap();
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+        pipeline.queue_step(&mut data, &mut queue, false);
+
+        for _ in 0..200 {
+            let mut encoder = gpu.device().create_command_encoder(&Default::default());
+            queue.encode(&mut encoder, None);
+            gpu.queue().submit(Some(encoder.finish()));
+        }
+
+        let positions_staging = GpuVector::uninit(
+            gpu.device(),
+            data.particles.capacity() as u32,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        positions_staging.copy_from(&mut encoder, &data.particles.positions);
+        gpu.queue().submit(Some(encoder.finish()));
+
+        let positions: Vec<nalgebra::Vector4<f32>> =
+            positions_staging.read(gpu.device()).await.unwrap();
+
+        for pt in &positions[..cpu_particles.len()] {
+            assert!(pt.y > -cell_width, "{pt:?}");
+        }
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_one_way_body_is_not_pushed() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+
+        let cell_width = 1.0;
+        let mut cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
+        for particle in &mut cpu_particles {
+            particle.dynamics.velocity = vector![0.0, 5.0, 0.0];
+        }
+
+        // Two dynamic balls right above the particles moving toward them, only the second
+        // one is pushed back.
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+        let start = vector![1.0, 6.0, 2.5];
+        let ball = bodies.insert(RigidBodyBuilder::dynamic().translation(start));
+        let one_way = colliders.insert_with_parent(ColliderBuilder::ball(1.0), ball, &mut bodies);
+        let other_ball =
+            bodies.insert(RigidBodyBuilder::dynamic().translation(vector![4.0, 6.0, 2.5]));
+        colliders.insert_with_parent(ColliderBuilder::ball(1.0), other_ball, &mut bodies);
+
+        let params = SimulationParams {
+            gravity: vector![0.0, 0.0, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let coupling = select_coupling(&colliders, |handle, _| {
+            if handle == one_way {
+                ColliderCoupling::OneWay
+            } else {
+                ColliderCoupling::TwoWays
+            }
+        });
+        let mut data = MpmData::with_select_coupling(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &bodies,
+            &colliders,
+            coupling,
+            cell_width,
+            100_000,
+            cpu_particles.len(),
+        )
+        .unwrap();
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+        pipeline.queue_step(&mut data, &mut queue, false);
+
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        data.impulses.clear_total_impulses(&mut encoder);
+        for _ in 0..200 {
+            queue.encode(&mut encoder, None);
+        }
+        data.impulses.queue_total_impulses_readback(&mut encoder);
+        data.poses_staging
+            .copy_from(&mut encoder, data.bodies.poses());
+        gpu.queue().submit(Some(encoder.finish()));
+        let poses = data.poses_staging.read(gpu.device()).await.unwrap();
+
+        let translation = poses[0].isometry.translation.vector;
+        assert!((translation - start).norm() < 1.0e-3, "{translation:?}");
+
+        // The particles moving up hit both balls, but the impulses on one-way bodies are not
+        // accumulated.
+        let impulses = data
+            .impulses
+            .read_total_impulses(gpu.device())
+            .await
+            .unwrap();
+        assert_eq!(impulses.len(), 2);
+        assert_eq!(impulses[0], BodyImpulse::default());
+        assert!(impulses[1].linear.y > 0.0, "{:?}", impulses[1]);
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn pipeline_rejects_grid_exceeding_device_limits() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let result = MpmData::new(
+            gpu.device(),
+            params,
+            &[],
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            1.0,
+            1 << 30,
+        );
+        assert!(matches!(result, Err(Error::GridOverflow { .. })));
+    }
 }
@@ -0,0 +1,187 @@
+use crate::dim_shader_defs;
+use rapier::geometry::{Collider, ColliderSet};
+use wgcore::tensor::GpuVector;
+use wgcore::Shader;
+use wgpu::{BufferUsages, Device, Queue};
+use wgrapier::dynamics::body::BodyCouplingEntry;
+
+/// The default stiffness of the penalty force pushing particles out of colliders.
+pub const DEFAULT_PENALTY_STIFFNESS: f32 = 1.0e3;
+
+/// The properties of the contacts between particles and a collider.
+///
+/// See [`ContactMaterial::from_collider`] for the default material of a rapier collider.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct ContactMaterial {
+    /// The Coulomb friction coefficient.
+    pub friction: f32,
+    /// The fraction of the normal relative velocity restored after an impact.
+    pub restitution: f32,
+    /// The stiffness of the penalty force pushing penetrating particles out of the collider.
+    pub penalty_stiffness: f32,
+}
+
+impl Default for ContactMaterial {
+    fn default() -> Self {
+        Self::new(0.5, 0.0)
+    }
+}
+
+impl ContactMaterial {
+    pub fn new(friction: f32, restitution: f32) -> Self {
+        Self {
+            friction,
+            restitution,
+            penalty_stiffness: DEFAULT_PENALTY_STIFFNESS,
+        }
+    }
+
+    /// The contact material matching the friction and restitution coefficients of a
+    /// rapier collider.
+    ///
+    /// Note that the contacts between particles and colliders used to always have a friction of
+    /// 20, whereas rapier colliders have a default friction of 0.5, which lets granular materials
+    /// slide down gentle slopes. The previous behavior is obtained by setting a friction of 20
+    /// with [`ColliderBuilder::friction`](rapier::geometry::ColliderBuilder::friction) or
+    /// [`crate::pipeline::MpmData::set_contact_material`].
+    pub fn from_collider(collider: &Collider) -> Self {
+        Self::new(collider.friction(), collider.restitution())
+    }
+
+    pub fn with_penalty_stiffness(mut self, penalty_stiffness: f32) -> Self {
+        self.penalty_stiffness = penalty_stiffness;
+        self
+    }
+}
+
+/// The contact materials of every collider coupled with the particles.
+///
+/// The materials are stored in the same order as the coupling entries of the
+/// [`crate::pipeline::MpmData`].
+pub struct GpuContactMaterials {
+    pub materials: GpuVector<ContactMaterial>,
+    cpu_materials: Vec<ContactMaterial>,
+}
+
+impl GpuContactMaterials {
+    pub fn new(device: &Device, materials: Vec<ContactMaterial>) -> Self {
+        // NOTE: allocate at least one element since empty buffers can’t be bound.
+        let gpu_materials = if materials.is_empty() {
+            vec![ContactMaterial::default()]
+        } else {
+            materials.clone()
+        };
+
+        Self {
+            materials: GpuVector::init(
+                device,
+                &gpu_materials,
+                BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            ),
+            cpu_materials: materials,
+        }
+    }
+
+    /// Reads the contact materials from the friction and restitution of the coupled colliders.
+    pub fn from_rapier(
+        device: &Device,
+        colliders: &ColliderSet,
+        coupling: &[BodyCouplingEntry],
+    ) -> Self {
+        let materials = coupling
+            .iter()
+            .map(|entry| ContactMaterial::from_collider(&colliders[entry.collider]))
+            .collect();
+        Self::new(device, materials)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cpu_materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu_materials.is_empty()
+    }
+
+    /// The contact material of the `i`-th coupled collider.
+    pub fn get(&self, i: usize) -> Option<&ContactMaterial> {
+        self.cpu_materials.get(i)
+    }
+
+    /// Replaces the contact material of the `i`-th coupled collider.
+    pub fn set(&mut self, queue: &Queue, i: usize, material: ContactMaterial) {
+        self.cpu_materials[i] = material;
+        let offset = (i * std::mem::size_of::<ContactMaterial>()) as u64;
+        queue.write_buffer(
+            self.materials.buffer(),
+            offset,
+            bytemuck::bytes_of(&material),
+        );
+    }
+}
+
+#[derive(Shader)]
+#[shader(src = "contact.wgsl", shader_defs = "dim_shader_defs")]
+pub struct WgContact;
+
+wgcore::test_shader_compilation!(WgContact, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use crate::models::DruckerPrager;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::{vector, Rotation3, Vector3};
+    use rapier::prelude::{ColliderBuilder, ColliderSet, RigidBodyBuilder, RigidBodySet};
+
+    /// The mean distance traveled down a slope by a block of sand resting on it.
+    async fn distance_slid_down_slope(friction: f32) -> f32 {
+        let angle = 0.4;
+        let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), angle);
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+        let slope = bodies.insert(RigidBodyBuilder::fixed().rotation(vector![0.0, 0.0, angle]));
+        colliders.insert_with_parent(
+            ColliderBuilder::cuboid(20.0, 1.0, 20.0).friction(friction),
+            slope,
+            &mut bodies,
+        );
+
+        // A block centered right above the slope.
+        let center = rotation * vector![0.0, 3.0, 0.0];
+        let particles: Vec<_> = particle_block(6, 0.5, center - Vector3::repeat(1.25))
+            .into_iter()
+            .map(|particle| particle.with_plasticity(DruckerPrager::new(100_000.0, 0.33)))
+            .collect();
+
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        let snapshot = simulate(&particles, params, &bodies, &colliders, 600).await;
+
+        let down_slope = rotation * -Vector3::x();
+        let displacement: Vector3<f32> = snapshot
+            .positions
+            .iter()
+            .zip(particles.iter())
+            .map(|(pt, particle)| pt - particle.position)
+            .sum();
+        displacement.dot(&down_slope) / particles.len() as f32
+    }
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn sand_stays_on_frictional_slope_and_slides_off_frictionless_one() {
+        let frictional = distance_slid_down_slope(20.0).await;
+        let frictionless = distance_slid_down_slope(0.0).await;
+        assert!(frictional < 0.5, "{frictional}");
+        assert!(
+            frictionless > frictional + 1.0,
+            "{frictional} {frictionless}"
+        );
+    }
+}
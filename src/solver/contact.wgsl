@@ -0,0 +1,8 @@
+#define_import_path wgsparkl::solver::contact
+
+// The properties of the contacts between particles and a collider.
+struct ContactMaterial {
+    friction: f32,
+    restitution: f32,
+    penalty_stiffness: f32,
+}
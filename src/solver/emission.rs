@@ -0,0 +1,467 @@
+use crate::dim_shader_defs;
+use crate::models::GpuModels;
+use crate::solver::{GpuParticles, Particle, WgParticle};
+use crate::solver::{GpuSimulationParams, WgParams};
+use encase::ShaderType;
+use rapier::geometry::Aabb;
+use rapier::math::Vector;
+use std::sync::Arc;
+use wgcore::kernel::{KernelInvocationBuilder, KernelInvocationQueue};
+use wgcore::tensor::{GpuScalar, GpuVector};
+use wgcore::Shader;
+use wgparry::substitute_aliases;
+use wgpu::util::{BufferInitDescriptor, DeviceExt, DispatchIndirectArgs};
+use wgpu::{Buffer, BufferUsages, ComputePipeline, Device, Queue};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Shader)]
+#[shader(
+    derive(WgParticle, WgParams),
+    src = "emission.wgsl",
+    src_fn = "substitute_aliases",
+    shader_defs = "dim_shader_defs"
+)]
+pub struct WgParticleEmission {
+    reset_removal: ComputePipeline,
+    mark_removed_particles: ComputePipeline,
+    plan_removal_moves: ComputePipeline,
+    move_particle_data: ComputePipeline,
+    finalize_removal: ComputePipeline,
+    append_particle_data: ComputePipeline,
+    finalize_emission: ComputePipeline,
+}
+
+/// The number of live particles, and the capacity of the particle buffers.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[repr(C)]
+pub struct GpuParticleCount {
+    pub len: u32,
+    pub capacity: u32,
+}
+
+pub(crate) fn init_particle_count(
+    device: &Device,
+    len: u32,
+    capacity: u32,
+) -> (GpuScalar<GpuParticleCount>, Arc<Buffer>) {
+    let count = GpuScalar::init(
+        device,
+        GpuParticleCount { len, capacity },
+        BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+    );
+    let n_groups = DispatchIndirectArgs {
+        x: len.div_ceil(WORKGROUP_SIZE),
+        y: 1,
+        z: 1,
+    };
+    let indirect_n_groups = Arc::new(device.create_buffer_init(&BufferInitDescriptor {
+        label: None,
+        contents: n_groups.as_bytes(),
+        usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST,
+    }));
+    (count, indirect_n_groups)
+}
+
+/// Overwrites the number of live particles, and the matching indirect dispatch arguments.
+pub(crate) fn write_particle_count(queue: &Queue, particles: &GpuParticles, len: u32) {
+    let count = GpuParticleCount {
+        len,
+        capacity: particles.capacity() as u32,
+    };
+    let n_groups = DispatchIndirectArgs {
+        x: len.div_ceil(WORKGROUP_SIZE),
+        y: 1,
+        z: 1,
+    };
+    queue.write_buffer(particles.count.buffer(), 0, bytemuck::bytes_of(&count));
+    queue.write_buffer(&particles.indirect_n_groups, 0, n_groups.as_bytes());
+}
+
+/// An axis-aligned region of space removing every particle entering it.
+#[derive(ShaderType, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct ParticleSink {
+    pub mins: Vector<f32>,
+    pub maxs: Vector<f32>,
+}
+
+impl ParticleSink {
+    pub fn new(mins: Vector<f32>, maxs: Vector<f32>) -> Self {
+        Self { mins, maxs }
+    }
+}
+
+impl From<Aabb> for ParticleSink {
+    fn from(aabb: Aabb) -> Self {
+        Self::new(aabb.mins.coords, aabb.maxs.coords)
+    }
+}
+
+/// The sinks removing particles from the simulation, and the workspace for compacting
+/// the particle buffers after each removal.
+pub struct GpuParticleSinks {
+    // NOTE: this always contains at least one (possibly empty) sink so it can
+    //       be bound even when there are no sinks.
+    pub sinks: GpuVector<ParticleSink>,
+    cpu_sinks: Vec<ParticleSink>,
+    // Three atomic counters: number of removed particles, number of holes, number of refills.
+    state: GpuVector<u32>,
+    // NOTE: this is a packed bitmask so each u32 contains
+    //       the flag for 32 particles.
+    removed_flags: GpuVector<u32>,
+    moves: GpuVector<[u32; 2]>,
+}
+
+impl GpuParticleSinks {
+    pub fn new(device: &Device, sinks: &[ParticleSink], particle_capacity: usize) -> Self {
+        // A sink with `mins > maxs` never contains any particle.
+        let empty_sink = ParticleSink::new(Vector::repeat(1.0), Vector::repeat(-1.0));
+        let gpu_sinks = if sinks.is_empty() {
+            &[empty_sink][..]
+        } else {
+            sinks
+        };
+
+        Self {
+            sinks: GpuVector::encase(device, gpu_sinks, BufferUsages::STORAGE),
+            cpu_sinks: sinks.to_vec(),
+            state: GpuVector::init(device, [0; 3], BufferUsages::STORAGE),
+            removed_flags: GpuVector::uninit(
+                device,
+                particle_capacity.div_ceil(32) as u32,
+                BufferUsages::STORAGE,
+            ),
+            moves: GpuVector::uninit(device, particle_capacity as u32, BufferUsages::STORAGE),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu_sinks.is_empty()
+    }
+
+    /// The sinks given to [`Self::new`].
+    pub fn cpu_sinks(&self) -> &[ParticleSink] {
+        &self.cpu_sinks
+    }
+}
+
+/// Persistent staging buffers holding the particles to insert into the simulation.
+///
+/// They are allocated once with a fixed capacity, and overwritten before each emission.
+pub struct GpuEmittedParticles {
+    pub particles: GpuParticles,
+    pub models: GpuModels,
+}
+
+impl GpuEmittedParticles {
+    pub fn with_capacity(device: &Device, capacity: usize) -> Self {
+        Self {
+            particles: GpuParticles::with_capacity(device, &[], capacity),
+            models: GpuModels::with_capacity(device, &[], capacity),
+        }
+    }
+
+    /// The maximum number of particles inserted by a single emission.
+    pub fn capacity(&self) -> usize {
+        self.particles.capacity()
+    }
+
+    /// Replaces the particles to emit.
+    ///
+    /// Particles beyond [`Self::capacity`] are ignored. Returns the number of particles
+    /// written.
+    pub fn write(&self, queue: &Queue, particles: &[Particle]) -> usize {
+        let particles = &particles[..particles.len().min(self.capacity())];
+        self.particles.write(queue, particles);
+        self.models.write(queue, particles);
+        write_particle_count(queue, &self.particles, particles.len() as u32);
+        particles.len()
+    }
+}
+
+/// A source of particles, injecting copies of a particle template at regular time intervals.
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    /// The particles injected at each emission. Their positions are relative to the emitter’s
+    /// `position`.
+    pub template: Vec<Particle>,
+    /// The position of the emitter.
+    pub position: Vector<f32>,
+    /// The initial velocity of every emitted particle.
+    pub velocity: Vector<f32>,
+    /// The time, in seconds, between two consecutive emissions.
+    pub period: f32,
+    elapsed: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(
+        template: Vec<Particle>,
+        position: Vector<f32>,
+        velocity: Vector<f32>,
+        period: f32,
+    ) -> Self {
+        Self {
+            template,
+            position,
+            velocity,
+            period,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the emitter’s clock by `dt` and returns the particles to emit, if any.
+    ///
+    /// At most one copy of the template is emitted per call.
+    ///
+    /// The result is meant to be given to [`crate::pipeline::MpmPipeline::emit_particles`].
+    pub fn step(&mut self, dt: f32) -> Vec<Particle> {
+        self.elapsed += dt;
+
+        if self.elapsed < self.period {
+            return vec![];
+        }
+
+        self.elapsed -= self.period.max(0.0);
+        self.template
+            .iter()
+            .map(|particle| {
+                let mut particle = *particle;
+                particle.position += self.position;
+                particle.dynamics.velocity = self.velocity;
+                particle
+            })
+            .collect()
+    }
+}
+
+fn per_particle_buffers<'b>(
+    particles: &'b GpuParticles,
+    models: &'b GpuModels,
+) -> impl Iterator<Item = &'b Buffer> {
+    [particles.positions.buffer(), particles.dynamics.buffer()]
+        .into_iter()
+        .chain(models.per_particle_buffers())
+}
+
+impl WgParticleEmission {
+    /// Queues the removal of every live particle located inside any of the `sinks`, or outside
+    /// of the simulation domain if it culls particles.
+    ///
+    /// The particle buffers are compacted in-place so that the remaining particles occupy
+    /// the first `particles.count.len` slots. The order of the remaining particles isn’t
+    /// preserved.
+    pub fn queue_removal<'a>(
+        &'a self,
+        queue: &mut KernelInvocationQueue<'a>,
+        particles: &GpuParticles,
+        models: &GpuModels,
+        sinks: &GpuParticleSinks,
+        sim_params: &GpuSimulationParams,
+    ) {
+        if sinks.is_empty() && !sim_params.cpu_params.domain.culls_particles() {
+            return;
+        }
+
+        KernelInvocationBuilder::new(queue, &self.reset_removal)
+            .bind_at(0, [(sinks.state.buffer(), 2)])
+            .queue(1);
+
+        KernelInvocationBuilder::new(queue, &self.mark_removed_particles)
+            .bind_at(
+                0,
+                [
+                    (particles.count.buffer(), 0),
+                    (sinks.state.buffer(), 2),
+                    (sinks.removed_flags.buffer(), 3),
+                ],
+            )
+            .bind_at(
+                1,
+                [
+                    (particles.positions.buffer(), 0),
+                    (sinks.sinks.buffer(), 1),
+                    (sim_params.params.buffer(), 5),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.plan_removal_moves)
+            .bind_at(
+                0,
+                [
+                    (particles.count.buffer(), 0),
+                    (sinks.state.buffer(), 2),
+                    (sinks.removed_flags.buffer(), 3),
+                    (sinks.moves.buffer(), 4),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        for buffer in per_particle_buffers(particles, models) {
+            KernelInvocationBuilder::new(queue, &self.move_particle_data)
+                .bind_at(
+                    0,
+                    [
+                        (particles.count.buffer(), 0),
+                        (sinks.state.buffer(), 2),
+                        (sinks.moves.buffer(), 4),
+                    ],
+                )
+                .bind_at(1, [(buffer, 2)])
+                .queue_indirect(particles.indirect_n_groups.clone());
+        }
+
+        KernelInvocationBuilder::new(queue, &self.finalize_removal)
+            .bind_at(
+                0,
+                [
+                    (particles.count.buffer(), 0),
+                    (&*particles.indirect_n_groups, 1),
+                    (sinks.state.buffer(), 2),
+                ],
+            )
+            .queue(1);
+    }
+
+    /// Queues the insertion of the `emitted` particles right after the live `particles`.
+    ///
+    /// Emitted particles that don’t fit in the remaining capacity are ignored.
+    ///
+    /// This inserts the particles each time the queue is encoded, so it must not be queued
+    /// on a queue encoded once per substep.
+    pub fn queue_append<'a>(
+        &'a self,
+        queue: &mut KernelInvocationQueue<'a>,
+        particles: &GpuParticles,
+        models: &GpuModels,
+        emitted: &GpuEmittedParticles,
+    ) {
+        if emitted.capacity() == 0 {
+            return;
+        }
+
+        let n_groups = (emitted.capacity() as u32).div_ceil(WORKGROUP_SIZE);
+
+        for (dst, src) in per_particle_buffers(particles, models)
+            .zip(per_particle_buffers(&emitted.particles, &emitted.models))
+        {
+            KernelInvocationBuilder::new(queue, &self.append_particle_data)
+                .bind_at(0, [(particles.count.buffer(), 0)])
+                .bind_at(
+                    1,
+                    [(dst, 2), (src, 3), (emitted.particles.count.buffer(), 4)],
+                )
+                .queue(n_groups);
+        }
+
+        KernelInvocationBuilder::new(queue, &self.finalize_emission)
+            .bind_at(
+                0,
+                [
+                    (particles.count.buffer(), 0),
+                    (&*particles.indirect_n_groups, 1),
+                ],
+            )
+            .bind_at(1, [(emitted.particles.count.buffer(), 4)])
+            .queue(1);
+    }
+}
+
+wgcore::test_shader_compilation!(WgParticleEmission, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::{
+        GpuEmittedParticles, GpuParticleCount, GpuParticleSinks, ParticleSink, WgParticleEmission,
+    };
+    use crate::models::{ElasticCoefficients, GpuModels};
+    use crate::solver::{
+        GpuParticles, GpuSimulationParams, Particle, ParticleDynamics, SimulationDomain,
+        SimulationParams,
+    };
+    use nalgebra::vector;
+    use wgcore::gpu::GpuInstance;
+    use wgcore::kernel::KernelInvocationQueue;
+    use wgcore::tensor::GpuVector;
+    use wgcore::Shader;
+    use wgpu::BufferUsages;
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn gpu_particle_emission_and_removal() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let emission = WgParticleEmission::from_device(gpu.device()).unwrap();
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+
+        let particle = |i: usize| {
+            Particle::new(
+                vector![i as f32, 0.0, 0.0],
+                ParticleDynamics::with_density(0.25, 1.0),
+                ElasticCoefficients::from_young_modulus(100_000.0, 0.33),
+            )
+        };
+        let cpu_particles: Vec<_> = (0..1000).map(particle).collect();
+        let cpu_emitted: Vec<_> = (1000..1100).map(particle).collect();
+
+        let particles = GpuParticles::with_capacity(gpu.device(), &cpu_particles, 2000);
+        let models = GpuModels::with_capacity(gpu.device(), &cpu_particles, 2000);
+        let emitted = GpuEmittedParticles::with_capacity(gpu.device(), 200);
+        assert_eq!(emitted.write(gpu.queue(), &cpu_emitted), 100);
+        let sinks = GpuParticleSinks::new(
+            gpu.device(),
+            &[ParticleSink::new(
+                vector![-0.5, -1.0, -1.0],
+                vector![499.5, 1.0, 1.0],
+            )],
+            particles.capacity(),
+        );
+        let sim_params = GpuSimulationParams::new(
+            gpu.device(),
+            SimulationParams {
+                gravity: vector![0.0, -9.81, 0.0],
+                dt: 1.0 / 60.0,
+                domain: SimulationDomain::unbounded(),
+            },
+        );
+
+        emission.queue_removal(&mut queue, &particles, &models, &sinks, &sim_params);
+        emission.queue_append(&mut queue, &particles, &models, &emitted);
+
+        let positions_staging = GpuVector::uninit(
+            gpu.device(),
+            particles.capacity() as u32,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+        let count_staging = GpuVector::uninit(
+            gpu.device(),
+            1,
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        );
+
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        queue.encode(&mut encoder, None);
+        positions_staging.copy_from(&mut encoder, &particles.positions);
+        encoder.copy_buffer_to_buffer(
+            particles.count.buffer(),
+            0,
+            count_staging.buffer(),
+            0,
+            std::mem::size_of::<GpuParticleCount>() as u64,
+        );
+        gpu.queue().submit(Some(encoder.finish()));
+
+        let count: Vec<GpuParticleCount> = count_staging.read(gpu.device()).await.unwrap();
+        let positions: Vec<nalgebra::Vector4<f32>> =
+            positions_staging.read(gpu.device()).await.unwrap();
+
+        assert_eq!(count[0].len, 600);
+        assert_eq!(count[0].capacity, 2000);
+
+        let mut xs: Vec<_> = positions[..600].iter().map(|p| p.x as usize).collect();
+        xs.sort();
+        assert_eq!(xs, (500..1100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,165 @@
+#define_import_path wgsparkl::solver::emission
+
+#import wgsparkl::solver::particle as Particle;
+#import wgsparkl::solver::params as Params;
+
+@group(0) @binding(0)
+var<storage, read_write> count: Particle::Count;
+@group(0) @binding(1)
+var<storage, read_write> n_groups: DispatchIndirectArgs;
+@group(0) @binding(2)
+var<storage, read_write> removal: RemovalState;
+@group(0) @binding(3)
+var<storage, read_write> removed_flags: array<atomic<u32>>;
+@group(0) @binding(4)
+var<storage, read_write> moves: array<Move>;
+
+@group(1) @binding(0)
+var<storage, read> particles_pos: array<Particle::Position>;
+@group(1) @binding(1)
+var<storage, read> sinks: array<Sink>;
+
+// NOTE: the per-particle buffers are bound as raw arrays of u32 so the same kernels can
+//       move the content of any buffer with one element per particle. The size, in
+//       u32, of one element is deduced from the buffer length and the particle capacity.
+@group(1) @binding(2)
+var<storage, read_write> data: array<u32>;
+@group(1) @binding(3)
+var<storage, read> src_data: array<u32>;
+@group(1) @binding(4)
+var<storage, read> src_count: Particle::Count;
+@group(1) @binding(5)
+var<uniform> params: Params::SimulationParams;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+// TODO: upstream this to wgcore?
+struct DispatchIndirectArgs {
+    x: u32,
+    y: u32,
+    z: u32,
+}
+
+struct RemovalState {
+    num_removed: atomic<u32>,
+    num_holes: atomic<u32>,
+    num_refills: atomic<u32>,
+}
+
+// Moves the particle at index `src` to index `dst`.
+struct Move {
+    dst: u32,
+    src: u32,
+}
+
+// An axis-aligned region removing every particle entering it.
+struct Sink {
+    mins: Vector,
+    maxs: Vector,
+}
+
+fn div_ceil(x: u32, y: u32) -> u32 {
+    return (x + y - 1u) / y;
+}
+
+fn is_removed(id: u32) -> bool {
+    return (atomicLoad(&removed_flags[id / 32u]) & (1u << (id % 32u))) != 0u;
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn reset_removal() {
+    atomicStore(&removal.num_removed, 0u);
+    atomicStore(&removal.num_holes, 0u);
+    atomicStore(&removal.num_refills, 0u);
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE, 1, 1)
+fn mark_removed_particles(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let id = invocation_id.x;
+    if id < count.len {
+        let pt = particles_pos[id].pt;
+        var removed = params.domain.cull_particles != 0u && Params::is_outside_domain(params.domain, pt);
+
+        for (var i = 0u; i < arrayLength(&sinks); i += 1u) {
+            if all(pt >= sinks[i].mins) && all(pt <= sinks[i].maxs) {
+                removed = true;
+                break;
+            }
+        }
+
+        let entry_id = id / 32u;
+        let entry_bit = 1u << (id % 32u);
+
+        // PERF: this should be a workgroup reduction instead of
+        //       global-memory atomics.
+        if removed {
+            atomicOr(&removed_flags[entry_id], entry_bit);
+            atomicAdd(&removal.num_removed, 1u);
+        } else {
+            atomicAnd(&removed_flags[entry_id], ~entry_bit);
+        }
+    }
+}
+
+// Pairs each removed particle that lies within the new (smaller) particle range with
+// a surviving particle lying past that range.
+//
+// The removed particles at index `< new_len` are "holes" that need to be filled. The live
+// particles at index `>= new_len` are "refills" that need to be moved. There are exactly as
+// many holes as refills, so both can be matched by their order of insertion in `moves`.
+@compute @workgroup_size(WORKGROUP_SIZE, 1, 1)
+fn plan_removal_moves(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let id = invocation_id.x;
+    if id < count.len {
+        let new_len = count.len - atomicLoad(&removal.num_removed);
+        let removed = is_removed(id);
+
+        if id < new_len && removed {
+            let hole_id = atomicAdd(&removal.num_holes, 1u);
+            moves[hole_id].dst = id;
+        } else if id >= new_len && !removed {
+            let refill_id = atomicAdd(&removal.num_refills, 1u);
+            moves[refill_id].src = id;
+        }
+    }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE, 1, 1)
+fn move_particle_data(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let id = invocation_id.x;
+    if id < atomicLoad(&removal.num_holes) {
+        let stride = arrayLength(&data) / count.capacity;
+        let mv = moves[id];
+
+        for (var i = 0u; i < stride; i += 1u) {
+            data[mv.dst * stride + i] = data[mv.src * stride + i];
+        }
+    }
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn finalize_removal() {
+    count.len -= atomicLoad(&removal.num_removed);
+    n_groups = DispatchIndirectArgs(div_ceil(count.len, WORKGROUP_SIZE), 1, 1);
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE, 1, 1)
+fn append_particle_data(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let id = invocation_id.x;
+    let dst_id = count.len + id;
+
+    // NOTE: particles not fitting in the remaining capacity are dropped.
+    if id < src_count.len && dst_id < count.capacity {
+        let stride = arrayLength(&data) / count.capacity;
+
+        for (var i = 0u; i < stride; i += 1u) {
+            data[dst_id * stride + i] = src_data[id * stride + i];
+        }
+    }
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn finalize_emission() {
+    count.len = min(count.len + src_count.len, count.capacity);
+    n_groups = DispatchIndirectArgs(div_ceil(count.len, WORKGROUP_SIZE), 1, 1);
+}
@@ -39,6 +39,7 @@ var<workgroup> shared_nodes_vel_mass: array<vec4<f32>, NUM_SHARED_CELLS>;
 #endif
 
 var<workgroup> shared_nodes_cdf: array<Grid::NodeCdf, NUM_SHARED_CELLS>; // PERF: we don’t need the distance field from the cdf
+var<workgroup> shared_nodes_temperature: array<f32, NUM_SHARED_CELLS>;
 
 const WORKGROUP_SIZE: u32 = WORKGROUP_SIZE_X * WORKGROUP_SIZE_Y * WORKGROUP_SIZE_Z;
 @compute @workgroup_size(WORKGROUP_SIZE_X, WORKGROUP_SIZE_Y, WORKGROUP_SIZE_Z)
@@ -69,6 +70,8 @@ fn g2p(
     }
 }
 
+// NOTE: the adjacent blocks across a periodic face are found by `Grid::find_block_header_id`,
+//       which wraps their ids around the periodic axes.
 fn global_shared_memory_transfers(tid: vec3<u32>, active_block_vid: Grid::BlockVirtualId) {
     let base_block_pos_int = active_block_vid.id;
 
@@ -90,12 +93,14 @@ fn global_shared_memory_transfers(tid: vec3<u32>, active_block_vid: Grid::BlockV
                 let global_node_id = Grid::node_id(global_chunk_id, tid.xy);
                 shared_nodes_vel_mass[flat_shared_index] = Grid::nodes[global_node_id.id].momentum_velocity_mass;
                 shared_nodes_cdf[flat_shared_index] = Grid::nodes[global_node_id.id].cdf;
+                shared_nodes_temperature[flat_shared_index] = Grid::nodes[global_node_id.id].temperature;
             } else {
                 // This octant doesn’t exist. Fill shared memory with zeros/NONE.
                 // NOTE: we don’t need to init global_id since it’s only read for the
                 //       current chunk that is guaranteed to exist, not the 2x2 adjacent ones.
                 shared_nodes_vel_mass[flat_shared_index] = vec3(0.0);
-                shared_nodes_cdf[flat_shared_index] = Grid::NodeCdf(0.0, 0, Grid::NONE);
+                shared_nodes_cdf[flat_shared_index] = Grid::NodeCdf(0.0, Grid::NO_AFFINITY, Grid::NONE);
+                shared_nodes_temperature[flat_shared_index] = 0.0;
             }
         }
     }
@@ -118,12 +123,14 @@ fn global_shared_memory_transfers(tid: vec3<u32>, active_block_vid: Grid::BlockV
                     let global_node_id = Grid::node_id(global_chunk_id, tid);
                     shared_nodes_vel_mass[flat_shared_index] = Grid::nodes[global_node_id.id].momentum_velocity_mass;
                     shared_nodes_cdf[flat_shared_index] = Grid::nodes[global_node_id.id].cdf;
+                    shared_nodes_temperature[flat_shared_index] = Grid::nodes[global_node_id.id].temperature;
                 } else {
                     // This octant doesn’t exist. Fill shared memory with zeros/NONE.
                     // NOTE: we don’t need to init global_id since it’s only read for the
                     //       current chunk that is guaranteed to exist, not the 2x2x2 adjacent ones.
                     shared_nodes_vel_mass[flat_shared_index] = vec4(0.0);
-                    shared_nodes_cdf[flat_shared_index] = Grid::NodeCdf(0.0, 0, Grid::NONE);
+                    shared_nodes_cdf[flat_shared_index] = Grid::NodeCdf(0.0, Grid::NO_AFFINITY, Grid::NONE);
+                    shared_nodes_temperature[flat_shared_index] = 0.0;
                 }
             }
         }
@@ -141,17 +148,21 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
     var rigid_vel = vec2<f32>(0.0);
     var momentum_velocity_mass = vec3<f32>(0.0);
     var velocity_gradient = mat2x2<f32>(vec2(0.0), vec2(0.0));
+    var temperature_gradient = vec2<f32>(0.0);
 #else
     var rigid_vel = vec3<f32>(0.0);
     var momentum_velocity_mass = vec4<f32>(0.0);
     var velocity_gradient = mat3x3<f32>(vec3(0.0), vec3(0.0), vec3(0.0));
+    var temperature_gradient = vec3<f32>(0.0);
 #endif
+    var temperature = 0.0;
 
     // G2P
     {
         let particle_pos = particles_pos[particle_id];
         let particle_vel = particles_dyn[particle_id].velocity;
         let particle_cdf = particles_dyn[particle_id].cdf;
+        let particle_temperature = particles_dyn[particle_id].temperature;
 
         let inv_d = Kernel::inv_d(cell_width);
         let ref_elt_pos_minus_particle_pos = Particle::dir_to_associated_grid_node(particle_pos, cell_width);
@@ -182,14 +193,18 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
 #endif
 
             var cpic_cell_data = cell_data;
+            var cell_temperature = shared_nodes_temperature[shared_id];
 
             if !is_compatible {
+                // Heat isn’t exchanged across colliders.
+                cell_temperature = particle_temperature;
+
                 if cell_cdf.closest_id != Grid::NONE {
                     let body_vel = body_vels[cell_cdf.closest_id]; // TODO: invalid if there is no body.
                     let body_com = body_mprops[cell_cdf.closest_id].com;
                     let cell_center = dpt + particle_pos.pt;
                     let body_pt_vel =  Body::velocity_at_point(body_com, body_vel, cell_center);
-                    let particle_ghost_vel = body_pt_vel + Grid::project_velocity(particle_vel - body_pt_vel, particle_cdf.normal);
+                    let particle_ghost_vel = body_pt_vel + Grid::project_velocity(particle_vel - body_pt_vel, particle_cdf.normal, particle_cdf.friction, particle_cdf.restitution);
 
 #if DIM == 2
                     cpic_cell_data = vec3(particle_ghost_vel, cell_data.z);
@@ -210,17 +225,22 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
             let weight = w.x[shift.x] * w.y[shift.y];
             momentum_velocity_mass += cpic_cell_data * weight;
             velocity_gradient += (weight * inv_d) * outer_product(cpic_cell_data.xy, dpt);
+            temperature_gradient += (weight * inv_d * cell_temperature) * dpt;
 #else
             let weight = w.x[shift.x] * w.y[shift.y] * w.z[shift.z];
             momentum_velocity_mass += cpic_cell_data * weight;
             velocity_gradient += (weight * inv_d) * outer_product(cpic_cell_data.xyz, dpt);
+            temperature_gradient += (weight * inv_d * cell_temperature) * dpt;
 #endif
+            temperature += weight * cell_temperature;
         }
 
-        for (var i = 0u; i < 16u; i++) {
-            if Grid::affinity_bit(i, particle_cdf.affinity) {
-                let body_vel = body_vels[i];
-                let body_com = body_mprops[i].com;
+        for (var i = 0u; i < Grid::MAX_AFFINITIES; i++) {
+            let slot = Grid::affinity_slot(particle_cdf.affinity, i);
+            if slot != 0u {
+                let i_collider = Grid::slot_collider(slot);
+                let body_vel = body_vels[i_collider];
+                let body_com = body_mprops[i_collider].com;
                 rigid_vel += Body::velocity_at_point(body_com, body_vel, particle_pos.pt);
             }
         }
@@ -230,6 +250,9 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
     // Set the particle velocity, and store the velocity gradient into the affine matrix.
     // The rest will be dealt with in the particle update kernel(s).
     particles_dyn[particle_id].affine = velocity_gradient;
+    // Similarly, the temperature gradient is stored into the heat flux.
+    particles_dyn[particle_id].temperature = temperature;
+    particles_dyn[particle_id].heat_flux = temperature_gradient;
 #if DIM == 2
     particles_dyn[particle_id].velocity = momentum_velocity_mass.xy;
 #else
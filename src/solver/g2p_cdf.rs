@@ -1,8 +1,8 @@
 use crate::grid::grid::{GpuGrid, WgGrid};
 use crate::grid::kernel::WgKernel;
 use crate::solver::params::{GpuSimulationParams, WgParams};
-use crate::solver::GpuParticles;
 use crate::solver::WgParticle;
+use crate::solver::{GpuContactMaterials, GpuParticles, WgContact};
 use crate::{dim_shader_defs, substitute_aliases};
 use wgcore::kernel::{KernelInvocationBuilder, KernelInvocationQueue};
 use wgcore::Shader;
@@ -11,7 +11,7 @@ use wgpu::ComputePipeline;
 
 #[derive(Shader)]
 #[shader(
-    derive(WgParams, WgParticle, WgGrid, WgKernel, WgInv),
+    derive(WgParams, WgParticle, WgGrid, WgKernel, WgInv, WgContact),
     src = "g2p_cdf.wgsl",
     src_fn = "substitute_aliases",
     shader_defs = "dim_shader_defs"
@@ -27,6 +27,7 @@ impl WgG2PCdf {
         sim_params: &GpuSimulationParams,
         grid: &GpuGrid,
         particles: &GpuParticles,
+        contact_materials: &GpuContactMaterials,
     ) {
         KernelInvocationBuilder::new(queue, &self.g2p_cdf)
             .bind_at(
@@ -47,6 +48,7 @@ impl WgG2PCdf {
                     sim_params.params.buffer(),
                 ],
             )
+            .bind(2, [contact_materials.materials.buffer()])
             .queue_indirect(grid.indirect_n_g2p_p2g_groups.clone());
     }
 }
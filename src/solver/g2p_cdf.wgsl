@@ -11,6 +11,7 @@
 #import wgsparkl::solver::particle as Particle;
 #import wgsparkl::grid::kernel as Kernel;
 #import wgsparkl::grid::grid as Grid;
+#import wgsparkl::solver::contact as Contact;
 
 @group(1) @binding(0)
 var<storage, read> particles_pos: array<Particle::Position>;
@@ -21,6 +22,9 @@ var<storage, read> sorted_particle_ids: array<u32>;
 @group(1) @binding(3)
 var<uniform> params: Params::SimulationParams;
 
+@group(2) @binding(0)
+var<storage, read> contact_materials: array<Contact::ContactMaterial>;
+
 #if DIM == 2
 const WORKGROUP_SIZE_X: u32 = 8;
 const WORKGROUP_SIZE_Y: u32 = 8;
@@ -87,7 +91,7 @@ fn global_shared_memory_transfers(tid: vec3<u32>, active_block_vid: Grid::BlockV
                 // This octant doesn’t exist. Fill shared memory with zeros/NONE.
                 // NOTE: we don’t need to init global_id since it’s only read for the
                 //       current chunk that is guaranteed to exist, not the 2x2 adjacent ones.
-                *shared_node = Grid::NodeCdf(0.0, 0, Grid::NONE);
+                *shared_node = Grid::NodeCdf(0.0, Grid::NO_AFFINITY, Grid::NONE);
             }
         }
     }
@@ -113,7 +117,7 @@ fn global_shared_memory_transfers(tid: vec3<u32>, active_block_vid: Grid::BlockV
                     // This octant doesn’t exist. Fill shared memory with zeros/NONE.
                     // NOTE: we don’t need to init global_id since it’s only read for the
                     //       current chunk that is guaranteed to exist, not the 2x2x2 adjacent ones.
-                    *shared_node = Grid::NodeCdf(0.0, 0, Grid::NONE);
+                    *shared_node = Grid::NodeCdf(0.0, Grid::NO_AFFINITY, Grid::NONE);
                 }
             }
         }
@@ -129,14 +133,9 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
 
     var contact_dist = 0.0;
     var contact_normal = vec3(0.0);
-    var particle_affinity = 0u;
-    // TODO: would using a mat4 be faster?
-    var affinity_signs = array(
-        0.0, 0.0, 0.0, 0.0,
-        0.0, 0.0, 0.0, 0.0,
-        0.0, 0.0, 0.0, 0.0,
-        0.0, 0.0, 0.0, 0.0
-    );
+    var particle_affinity = Grid::NO_AFFINITY;
+    // One accumulated sign per affinity slot of `particle_affinity`.
+    var affinity_signs = array(0.0, 0.0, 0.0, 0.0);
 
     let prev_affinity = particles_dyn[particle_id].cdf.affinity;
     let particle_pos = particles_pos[particle_id];
@@ -160,7 +159,8 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
         let shift = NBH_SHIFTS[i];
         let packed_shift = NBH_SHIFTS_SHARED[i];
         let cell_data = shared_nodes[packed_cell_index_in_block + packed_shift];
-        particle_affinity |= cell_data.affinities & Grid::AFFINITY_BITS_MASK;
+        // NOTE: the signs are reconstructed below, so only the collider indices are merged here.
+        particle_affinity = Grid::merge_affinities(particle_affinity, Grid::unsigned_affinities(cell_data.affinities));
 
 #if DIM == 2
         let weight = w.x[shift.x] * w.y[shift.y];
@@ -168,22 +168,32 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
         let weight = w.x[shift.x] * w.y[shift.y] * w.z[shift.z];
 #endif
 
-        for (var i_collider = 0u; i_collider < 16u; i_collider += 1u) {
-            let compatible = f32(Grid::affinity_bit(i_collider, cell_data.affinities));
-            let sign = select(1.0, -1.0, Grid::sign_bit(i_collider, cell_data.affinities) && !shape_has_solid_interior(i_collider));
-            affinity_signs[i_collider] += compatible * weight * sign * cell_data.distance;
+        for (var i_cell_slot = 0u; i_cell_slot < Grid::MAX_AFFINITIES; i_cell_slot += 1u) {
+            let cell_slot = Grid::affinity_slot(cell_data.affinities, i_cell_slot);
+            if cell_slot != 0u {
+                let i_collider = Grid::slot_collider(cell_slot);
+                let i_slot = Grid::find_affinity_slot(particle_affinity, i_collider);
+                // NOTE: the slot might not exist if the particle ran out of affinity slots.
+                if i_slot != Grid::MAX_AFFINITIES {
+                    let sign = select(1.0, -1.0, Grid::slot_sign(cell_slot) && !shape_has_solid_interior(i_collider));
+                    affinity_signs[i_slot] += weight * sign * cell_data.distance;
+                }
+            }
         }
     }
 
     // Convert the affinity signs to bits.
-    for (var i_collider = 0u; i_collider < 16u; i_collider += 1u) {
-        // Only change the sign bit matching affinities that didn’t exist before.
-        let mask = 1u << (i_collider + Grid::SIGN_BITS_SHIFT);
-        if (prev_affinity & (1u << i_collider)) == 0 {
-            let sgn_bit = select(0u, mask, affinity_signs[i_collider] < 0.0);
-            particle_affinity |= sgn_bit;
-        } else {
-            particle_affinity |= prev_affinity & mask;
+    for (var i_slot = 0u; i_slot < Grid::MAX_AFFINITIES; i_slot += 1u) {
+        let slot = Grid::affinity_slot(particle_affinity, i_slot);
+        if slot != 0u {
+            // Only change the sign bit matching affinities that didn’t exist before.
+            let i_collider = Grid::slot_collider(slot);
+            let i_prev_slot = Grid::find_affinity_slot(prev_affinity, i_collider);
+            var sign = affinity_signs[i_slot] < 0.0;
+            if i_prev_slot != Grid::MAX_AFFINITIES {
+                sign = Grid::slot_sign(Grid::affinity_slot(prev_affinity, i_prev_slot));
+            }
+            particle_affinity = Grid::set_affinity_slot(particle_affinity, i_slot, Grid::pack_affinity(i_collider, sign));
         }
     }
 
@@ -208,9 +218,6 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
         let dpt = ref_elt_pos_minus_particle_pos + vec3<f32>(shift) * cell_width;
         let weight = w.x[shift.x] * w.y[shift.y] * w.z[shift.z];
 #endif
-        let combined_affinity = cell_data.affinities & particle_affinity & Grid::AFFINITY_BITS_MASK;
-        let sign_differences = ((cell_data.affinities >> Grid::SIGN_BITS_SHIFT)
-            ^ (particle_affinity >> Grid::SIGN_BITS_SHIFT)) & combined_affinity;
 
 #if DIM == 2
         let p = vec3(dpt, 1.0);
@@ -218,8 +225,8 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
         let p = vec4(dpt, 1.0);
 #endif
 
-        if combined_affinity != 0u {
-            if sign_differences == 0u {
+        if Grid::have_common_affinity(cell_data.affinities, particle_affinity) {
+            if Grid::affinities_are_compatible(cell_data.affinities, particle_affinity) {
                 // All signs match, positive distance.
                 qtq += outer_product(p, p) * weight;
                 qtu += p * weight * cell_data.distance;
@@ -232,16 +239,23 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
     }
 
     if determinant(qtq) > 1.0e-8 {
+        let material = average_contact_material(particle_affinity);
 #if DIM == 2
         let result = Inv::inv3(qtq) * qtu;
         let len = length(result.xy);
         let normal = select(vec2(0.0), result.xy / len, len > 1.0e-6);
         // PERF: init the rigid-velocities here instead of in g2p?
-        particles_dyn[particle_id].cdf = Particle::Cdf(normal, vec2(0.0), result.z, particle_affinity);
+        particles_dyn[particle_id].cdf = Particle::Cdf(
+            normal, vec2(0.0), result.z, particle_affinity,
+            material.friction, material.restitution, material.penalty_stiffness
+        );
 #else
         let result = Inv::inv4(qtq) * qtu;
         let normal = result.xyz / length(result.xyz);
-        particles_dyn[particle_id].cdf = Particle::Cdf(normal, vec3(0.0), result.w, particle_affinity);
+        particles_dyn[particle_id].cdf = Particle::Cdf(
+            normal, vec3(0.0), result.w, particle_affinity,
+            material.friction, material.restitution, material.penalty_stiffness
+        );
 #endif
     } else {
         // TODO: store the affinity in this case too?
@@ -249,6 +263,31 @@ fn particle_g2p(particle_id: u32, cell_width: f32, dt: f32) {
     }
 }
 
+// The contact material averaged over the colliders with an affinity slot in `affinity`.
+fn average_contact_material(affinity: vec2<u32>) -> Contact::ContactMaterial {
+    var result = Contact::ContactMaterial(0.0, 0.0, 0.0);
+    var count = 0.0;
+
+    for (var i = 0u; i < Grid::MAX_AFFINITIES; i++) {
+        let slot = Grid::affinity_slot(affinity, i);
+        if slot != 0u {
+            let material = contact_materials[Grid::slot_collider(slot)];
+            result.friction += material.friction;
+            result.restitution += material.restitution;
+            result.penalty_stiffness += material.penalty_stiffness;
+            count += 1.0;
+        }
+    }
+
+    if count > 0.0 {
+        result.friction /= count;
+        result.restitution /= count;
+        result.penalty_stiffness /= count;
+    }
+
+    return result;
+}
+
 fn shape_has_solid_interior(i_collider: u32) -> bool {
     // TODO: needs to be false for unoriented trimeshes and polylines,
     //       true for geometric primitives.
@@ -1,6 +1,7 @@
 #define_import_path wgsparkl::solver::grid_update
 
 #import wgsparkl::grid::grid as Grid;
+#import wgsparkl::solver::params as Params;
 
 #if DIM == 2
 const WORKGROUP_SIZE_X: u32 = 8;
@@ -39,6 +40,16 @@ fn grid_update(
     let momentum_velocity_mass = Grid::nodes[global_id].momentum_velocity_mass;
     let new_grid_velocity_mass = update_single_cell(cell_pos, momentum_velocity_mass);
     Grid::nodes[global_id].momentum_velocity_mass = new_grid_velocity_mass;
+
+    // The mass-weighted temperature (including the heat diffused from the
+    // particles’ heat flux) becomes the cell’s temperature.
+#if DIM == 2
+    let mass = momentum_velocity_mass.z;
+#else
+    let mass = momentum_velocity_mass.w;
+#endif
+    let inv_mass = select(0.0, 1.0 / mass, mass > 0.0);
+    Grid::nodes[global_id].temperature *= inv_mass;
 }
 
 #if DIM == 2
@@ -46,6 +57,8 @@ fn update_single_cell(cell_pos: vec2<f32>, momentum_velocity_mass: vec3<f32>) ->
     let mass = momentum_velocity_mass.z;
     let inv_mass = select(0.0, 1.0 / mass, mass > 0.0);
     var velocity = (momentum_velocity_mass.xy + mass * Grid::sim_params.gravity * Grid::sim_params.dt) * inv_mass;
+    velocity = apply_domain_boundaries(cell_pos, velocity);
+
     // Clamp the velocity so it doesn’t exceed 1 grid cell in one step.
     let vel_limit = vec2(Grid::grid.cell_width / Grid::sim_params.dt);
     velocity = clamp(velocity, -vel_limit, vel_limit);
@@ -56,6 +69,7 @@ fn update_single_cell(cell_pos: vec3<f32>, momentum_velocity_mass: vec4<f32>) ->
     let mass = momentum_velocity_mass.w;
     let inv_mass = select(0.0, 1.0 / mass, mass > 0.0);
     var velocity = (momentum_velocity_mass.xyz + mass * Grid::sim_params.gravity * Grid::sim_params.dt) * inv_mass;
+    velocity = apply_domain_boundaries(cell_pos, velocity);
 
     // Clamp the velocity so it doesn’t exceed 1 grid cell in one step.
     let vel_limit = vec3(Grid::grid.cell_width / Grid::sim_params.dt);
@@ -63,3 +77,67 @@ fn update_single_cell(cell_pos: vec3<f32>, momentum_velocity_mass: vec4<f32>) ->
     return vec4(velocity, mass);
 }
 #endif
+
+#if DIM == 2
+fn apply_domain_boundaries(cell_pos: vec2<f32>, velocity: vec2<f32>) -> vec2<f32> {
+    const DIM: u32 = 2;
+#else
+fn apply_domain_boundaries(cell_pos: vec3<f32>, velocity: vec3<f32>) -> vec3<f32> {
+    const DIM: u32 = 3;
+#endif
+    let domain = Grid::sim_params.domain;
+    if domain.enabled == 0u {
+        return velocity;
+    }
+
+    // The nodes within one cell of a face (or beyond it) are constrained by that face.
+    let cell_width = Grid::grid.cell_width;
+    var result = velocity;
+
+    for (var i = 0u; i < DIM; i += 1u) {
+        if domain.mins_boundaries[i] == Params::BOUNDARY_PERIODIC {
+            // Periodic axes have no walls: the grid blocks wrap around them.
+            continue;
+        }
+
+        // `sign` is the component of the face’s outward normal along the axis `i`.
+        var boundary = 0u;
+        var sign = 0.0;
+
+        if cell_pos[i] <= domain.mins[i] + cell_width {
+            boundary = domain.mins_boundaries[i];
+            sign = -1.0;
+        } else if cell_pos[i] >= domain.maxs[i] - cell_width {
+            boundary = domain.maxs_boundaries[i];
+            sign = 1.0;
+        }
+
+        if sign == 0.0 {
+            continue;
+        }
+
+        if boundary == Params::BOUNDARY_STICKY {
+            // The material sticks to the face.
+            return velocity * 0.0;
+        }
+
+        // The normal velocity pointing out of the domain.
+        let normal_vel = result[i] * sign;
+
+        if boundary == Params::BOUNDARY_SEPARATE && normal_vel <= 0.0 {
+            continue;
+        }
+
+        result[i] = 0.0;
+
+        // Coulomb friction: the tangential velocity is reduced proportionally to
+        // the normal velocity that was just removed.
+        let tangent_vel = length(result);
+        if domain.friction > 0.0 && tangent_vel > 0.0 {
+            let normal_impulse = abs(normal_vel);
+            result *= max(0.0, 1.0 - domain.friction * normal_impulse / tangent_vel);
+        }
+    }
+
+    return result;
+}
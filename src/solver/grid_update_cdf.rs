@@ -1,4 +1,4 @@
-use crate::collision::WgCollide;
+use crate::collision::{GpuCollisionMeshes, WgCollide};
 use crate::dim_shader_defs;
 use crate::grid::grid::{GpuGrid, WgGrid};
 use wgcore::kernel::{KernelInvocationBuilder, KernelInvocationQueue};
@@ -22,6 +22,7 @@ impl WgGridUpdateCdf {
         queue: &mut KernelInvocationQueue<'a>,
         grid: &GpuGrid,
         bodies: &GpuBodySet,
+        meshes: &GpuCollisionMeshes,
     ) {
         KernelInvocationBuilder::new(queue, &self.grid_update)
             .bind_at(
@@ -38,8 +39,10 @@ impl WgGridUpdateCdf {
                 [
                     bodies.shapes().buffer(),
                     bodies.poses().buffer(),
-                    // bodies.vels().buffer(),
-                    // bodies.mprops().buffer(),
+                    bodies.shapes_local_vertex_buffers().buffer(),
+                    meshes.roots.buffer(),
+                    meshes.nodes.buffer(),
+                    meshes.primitives.buffer(),
                 ],
             )
             .queue_indirect(grid.indirect_n_g2p_p2g_groups.clone());
@@ -1,8 +1,16 @@
+pub use contact::{ContactMaterial, GpuContactMaterials, WgContact, DEFAULT_PENALTY_STIFFNESS};
+pub(crate) use emission::write_particle_count;
+pub use emission::{
+    GpuEmittedParticles, GpuParticleCount, GpuParticleSinks, ParticleEmitter, ParticleSink,
+    WgParticleEmission,
+};
 pub use g2p::WgG2P;
 pub use g2p_cdf::WgG2PCdf;
 pub use p2g::WgP2G;
 pub use p2g_cdf::WgP2GCdf;
-pub use params::{GpuSimulationParams, SimulationParams, WgParams};
+pub use params::{
+    BoundaryCondition, GpuSimulationParams, SimulationDomain, SimulationParams, WgParams,
+};
 #[cfg(feature = "dim2")]
 pub use particle2d::{GpuParticles, GpuRigidParticles, Particle, ParticleDynamics, WgParticle};
 #[cfg(feature = "dim3")]
@@ -11,17 +19,31 @@ pub use particle3d::{GpuParticles, GpuRigidParticles, Particle, ParticleDynamics
 pub use grid_update::WgGridUpdate;
 pub use grid_update_cdf::WgGridUpdateCdf;
 pub use particle_update::{ParticlePhase, WgParticleUpdate};
-pub use rigid_impulses::{GpuImpulses, RigidImpulse, WgRigidImpulses};
+pub use readback::{GpuParticleReadback, ParticleFields, ParticleSnapshot};
+pub use rigid_impulses::{
+    BodyImpulse, GpuImpulses, GpuTotalImpulse, RigidImpulse, WgRigidImpulses,
+};
 pub use rigid_particle_update::WgRigidParticleUpdate;
+#[cfg(feature = "dim3")]
+pub use volume_sampling::sample_closed_mesh;
+#[cfg(feature = "dim2")]
+pub use volume_sampling::{sample_bitmap, sample_polygon, sample_polyline_interior, BitmapMask};
+pub use volume_sampling::{
+    sample_collider, sample_sdf, sample_shape, SamplingPattern, VolumeSamples,
+};
 
+mod contact;
+mod emission;
 mod g2p;
 mod g2p_cdf;
 mod p2g;
 mod p2g_cdf;
 mod params;
 mod particle_update;
+mod readback;
 mod rigid_impulses;
 mod rigid_particle_update;
+mod volume_sampling;
 
 mod grid_update;
 mod grid_update_cdf;
@@ -31,6 +31,8 @@ const WORKGROUP_SIZE_Z: u32 = 1;
 const NUM_SHARED_CELLS: u32 = 10 * 10; // block-size plus 2 from adjacent blocks: (8 + 2)^2
 var<workgroup> shared_vel_mass: array<vec3<f32>, NUM_SHARED_CELLS>;
 var<workgroup> shared_affine: array<mat2x2<f32>, NUM_SHARED_CELLS>;
+// The particle heat flux, and its temperature.
+var<workgroup> shared_heat: array<vec3<f32>, NUM_SHARED_CELLS>;
 #else
 const WORKGROUP_SIZE_X: u32 = 4;
 const WORKGROUP_SIZE_Y: u32 = 4;
@@ -38,11 +40,15 @@ const WORKGROUP_SIZE_Z: u32 = 4;
 const NUM_SHARED_CELLS: u32 = 6 * 6 * 6; // block-size plus 2 from adjacent blocks: (4 + 2)^3
 var<workgroup> shared_vel_mass: array<vec4<f32>, NUM_SHARED_CELLS>;
 var<workgroup> shared_affine: array<mat3x3<f32>, NUM_SHARED_CELLS>;
+// The particle heat flux, and its temperature.
+var<workgroup> shared_heat: array<vec4<f32>, NUM_SHARED_CELLS>;
 #endif
 var<workgroup> shared_nodes: array<SharedNode, NUM_SHARED_CELLS>;
 var<workgroup> shared_pos: array<Particle::Position, NUM_SHARED_CELLS>;
-var<workgroup> shared_affinities: array<u32, NUM_SHARED_CELLS>;
+var<workgroup> shared_affinities: array<vec2<u32>, NUM_SHARED_CELLS>;
 var<workgroup> shared_normals: array<Vector, NUM_SHARED_CELLS>;
+// The particle’s contact friction and restitution.
+var<workgroup> shared_contacts: array<vec2<f32>, NUM_SHARED_CELLS>;
 // TODO: is computing themax with an atomic faster than doing a reduction?
 var<workgroup> max_linked_list_length: atomic<u32>;
 // NOTE: workgroupUniformLoad doesn’t work on atomics, so we need that additional variable
@@ -64,6 +70,8 @@ struct P2GStepResult {
     impulse: vec3<f32>,
     ang_impulse: vec3<f32>,
 #endif
+    // The mass-weighted temperature.
+    temperature: f32,
 }
 
 @compute @workgroup_size(WORKGROUP_SIZE_X, WORKGROUP_SIZE_Y, WORKGROUP_SIZE_Z)
@@ -117,6 +125,7 @@ fn p2g(
         total_result.new_momentum_velocity_mass += partial_result.new_momentum_velocity_mass;
         total_result.impulse += partial_result.impulse;
         total_result.ang_impulse += partial_result.ang_impulse;
+        total_result.temperature += partial_result.temperature;
     }
 
     // Grid update.
@@ -134,12 +143,13 @@ fn p2g(
 
     // Write the node state to global memory.
     Grid::nodes[global_id].momentum_velocity_mass = total_result.new_momentum_velocity_mass;
-    // Apply the impulse to the closest body.
+    Grid::nodes[global_id].temperature = total_result.temperature;
+    // Apply the impulse to the closest body, unless it only drives the particles (one-way coupling).
     // PERF: we should probably run a reduction here to get per-collider accumulated impulses
     //       before adding to global memory. Because it is very likely that every single thread
     //       here targets the same body.
 
-    if collider_id != Grid::NONE {
+    if collider_id != Grid::NONE && body_impulses[collider_id].two_ways != 0u {
 #if DIM == 2
         atomicAdd(&body_impulses[collider_id].linear_x, Impulse::flt2int(total_result.impulse.x));
         atomicAdd(&body_impulses[collider_id].linear_y, Impulse::flt2int(total_result.impulse.y));
@@ -155,7 +165,7 @@ fn p2g(
     }
 }
 
-fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32, collider_id: u32) -> P2GStepResult {
+fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: vec2<u32>, collider_id: u32) -> P2GStepResult {
     // NOTE: having these into a var is needed so we can index [i] them.
     //       Does this have any impact on performances?
     var NBH_SHIFTS = Kernel::NBH_SHIFTS;
@@ -171,6 +181,7 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32
 #endif
     var impulse = Vector(0.0);
     var ang_impulse = AngVector(0.0);
+    var temperature = 0.0;
 
     for (var i = 0u; i < Kernel::NBH_LEN; i += 1u) {
         let packed_shift = NBH_SHIFTS_SHARED[i];
@@ -178,6 +189,7 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32
         let particle_pos = shared_pos[nbh_shared_index];
         let particle_vel_mass = shared_vel_mass[nbh_shared_index];
         let particle_affine = shared_affine[nbh_shared_index];
+        let particle_heat = shared_heat[nbh_shared_index];
         let ref_elt_pos_minus_particle_pos = Particle::dir_to_associated_grid_node(particle_pos, cell_width);
         // TODO: only compute the one weight we need.
         let w = Kernel::precompute_weights(ref_elt_pos_minus_particle_pos, cell_width);
@@ -189,6 +201,8 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32
         let momentum = particle_vel * particle_mass;
         let dpt = ref_elt_pos_minus_particle_pos + vec2<f32>(shift) * cell_width; // cell_pos - particle_pos
         let weight = w.x[shift.x] * w.y[shift.y];
+        let heat_flux = particle_heat.xy;
+        let particle_temperature = particle_heat.z;
 #else
         let particle_vel = particle_vel_mass.xyz;
         let particle_mass = particle_vel_mass.w;
@@ -196,17 +210,20 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32
         let momentum = particle_vel * particle_mass;
         let dpt = ref_elt_pos_minus_particle_pos + vec3<f32>(shift) * cell_width; // cell_pos - particle_pos
         let weight = w.x[shift.x] * w.y[shift.y] * w.z[shift.z];
+        let heat_flux = particle_heat.xyz;
+        let particle_temperature = particle_heat.w;
 #endif
 
         let particle_affinity = shared_affinities[nbh_shared_index];
         if !Grid::affinities_are_compatible(node_affinity, particle_affinity) {
             if collider_id != Grid::NONE {
                 let particle_normal = shared_normals[nbh_shared_index];
+                let particle_contact = shared_contacts[nbh_shared_index];
                 let body_vel = body_vels[collider_id];
                 let body_com = body_impulses[collider_id].com;
                 let cell_center = dpt + particle_pos.pt;
                 let body_pt_vel =  Body::velocity_at_point(body_com, body_vel, cell_center);
-                let particle_ghost_vel = body_pt_vel + Grid::project_velocity(particle_vel - body_pt_vel, particle_normal);
+                let particle_ghost_vel = body_pt_vel + Grid::project_velocity(particle_vel - body_pt_vel, particle_normal, particle_contact.x, particle_contact.y);
                 let delta_impulse = (particle_vel - particle_ghost_vel) * (weight * particle_mass);
 
                 // TODO: we could do the ang impulse calcs only once after all the `p2g_step` executions.
@@ -229,10 +246,12 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, node_affinity: u32
 #else
             new_momentum_velocity_mass += vec4(particle_affine * dpt + momentum, particle_mass) * weight;
 #endif
+            // NOTE: heat isn’t exchanged across colliders.
+            temperature += (particle_temperature * particle_mass + dot(heat_flux, dpt)) * weight;
         }
     }
 
-    return P2GStepResult(new_momentum_velocity_mass, impulse, ang_impulse);
+    return P2GStepResult(new_momentum_velocity_mass, impulse, ang_impulse, temperature);
 }
 
 #if DIM == 2
@@ -284,6 +303,8 @@ fn fetch_max_linked_lists_length(tid: vec3<u32>, active_block_vid: Grid::BlockVi
     }
 }
 
+// NOTE: the adjacent blocks across a periodic face are found by `Grid::find_block_header_id`,
+//       which wraps their ids around the periodic axes.
 fn fetch_nodes(tid: vec3<u32>, active_block_vid: Grid::BlockVirtualId, bid: u32) {
 #if DIM == 2
     let base_block_pos_int = active_block_vid.id - vec2<i32>(1i, 1i);
@@ -363,13 +384,19 @@ fn fetch_next_particle(tid: vec3<u32>) {
                 if curr_particle_id != Grid::NONE {
                     shared_affinities[shared_flat_index] = particles_dyn[curr_particle_id].cdf.affinity;
                     shared_normals[shared_flat_index] = particles_dyn[curr_particle_id].cdf.normal;
+                    shared_contacts[shared_flat_index] = vec2(
+                        particles_dyn[curr_particle_id].cdf.friction,
+                        particles_dyn[curr_particle_id].cdf.restitution
+                    );
                     shared_pos[shared_flat_index] = particles_pos[curr_particle_id];
                     shared_affine[shared_flat_index] = particles_dyn[curr_particle_id].affine;
 
 #if DIM == 2
                     shared_vel_mass[shared_flat_index] = vec3(particles_dyn[curr_particle_id].velocity, particles_dyn[curr_particle_id].mass);
+                    shared_heat[shared_flat_index] = vec3(particles_dyn[curr_particle_id].heat_flux, particles_dyn[curr_particle_id].temperature);
 #else
                     shared_vel_mass[shared_flat_index] = vec4(particles_dyn[curr_particle_id].velocity, particles_dyn[curr_particle_id].mass);
+                    shared_heat[shared_flat_index] = vec4(particles_dyn[curr_particle_id].heat_flux, particles_dyn[curr_particle_id].temperature);
 #endif
 
                     let next_particle_id = particle_node_linked_lists[curr_particle_id];
@@ -378,16 +405,19 @@ fn fetch_next_particle(tid: vec3<u32>) {
                     // TODO: would it be worth skipping writing zeros if we already
                     //       did it at the previous step? (if we already reached the end
                     //       of the particle linked list)
-                    shared_affinities[shared_flat_index] = 0u;
+                    shared_affinities[shared_flat_index] = Grid::NO_AFFINITY;
                     shared_normals[shared_flat_index] = Vector(0.0);
+                    shared_contacts[shared_flat_index] = vec2(0.0);
 #if DIM == 2
                     shared_pos[shared_flat_index].pt = vec2(0.0);
                     shared_affine[shared_flat_index] = mat2x2(vec2(0.0), vec2(0.0));
                     shared_vel_mass[shared_flat_index] = vec3(0.0);
+                    shared_heat[shared_flat_index] = vec3(0.0);
 #else
                     shared_pos[shared_flat_index].pt = vec3(0.0);
                     shared_affine[shared_flat_index] = mat3x3(vec3(0.0), vec3(0.0), vec3(0.0));
                     shared_vel_mass[shared_flat_index] = vec4(0.0);
+                    shared_heat[shared_flat_index] = vec4(0.0);
 #endif
                 }
             }
@@ -97,7 +97,7 @@ fn p2g_cdf(
         let partial_result = p2g_step(packed_cell_index_in_block, Grid::grid.cell_width, cell_pos);
 
         if partial_result.closest_id != Grid::NONE {
-            node_cdf.affinities |= partial_result.affinities;
+            node_cdf.affinities = Grid::merge_affinities(node_cdf.affinities, partial_result.affinities);
 
             if partial_result.distance < node_cdf.distance {
                 node_cdf.distance = partial_result.distance;
@@ -121,7 +121,7 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, cell_pos: Vector)
 #else
     let bottommost_contributing_node = flatten_shared_shift(2u, 2u, 2u);
 #endif
-    var result = Grid::NodeCdf(1.0e10, 0u, Grid::NONE);
+    var result = Grid::NodeCdf(1.0e10, Grid::NO_AFFINITY, Grid::NONE);
 
     for (var i = 0u; i < Kernel::NBH_LEN; i += 1u) {
         let packed_shift = NBH_SHIFTS_SHARED[i];
@@ -146,8 +146,7 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, cell_pos: Vector)
             let distance = length(dpt);
             let ab = primitive.b - primitive.a;
             let sign = dot(dpt, vec2(-ab.y, ab.x)) < 0.0;
-            result.affinities |= (1u << collider_id)
-                | (u32(sign) << (collider_id + Grid::SIGN_BITS_SHIFT));
+            result.affinities = Grid::add_affinity(result.affinities, collider_id, sign);
 
             if distance < result.distance {
                 result.distance = min(result.distance, distance);
@@ -176,8 +175,7 @@ fn p2g_step(packed_cell_index_in_block: u32, cell_width: f32, cell_pos: Vector)
              let signed_dist = dot(n, ap) / n_length;
              let sign = signed_dist < 0.0;
              let distance = abs(signed_dist);
-             result.affinities |= (1u << collider_id)
-                 | (u32(sign) << (collider_id + Grid::SIGN_BITS_SHIFT));
+             result.affinities = Grid::add_affinity(result.affinities, collider_id, sign);
 
              if distance < result.distance {
                  result.distance = min(result.distance, distance);
@@ -1,7 +1,14 @@
 use crate::dim_shader_defs;
+use crate::Error;
+use rapier::math::{Vector, DIM};
 use wgcore::tensor::GpuScalar;
 use wgcore::Shader;
-use wgpu::{BufferUsages, Device};
+use wgpu::{BufferUsages, Device, Queue};
+
+#[cfg(feature = "dim2")]
+type BoundaryVector = nalgebra::Vector2<u32>;
+#[cfg(feature = "dim3")]
+type BoundaryVector = nalgebra::Vector3<u32>;
 
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
 #[repr(C)]
@@ -13,10 +20,150 @@ pub struct SimulationParams {
     #[cfg(feature = "dim3")]
     pub gravity: nalgebra::Vector3<f32>,
     pub dt: f32,
+    /// The axis-aligned box the simulation is confined to.
+    pub domain: SimulationDomain,
+}
+
+/// The behavior of the material touching one face of the [`SimulationDomain`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[repr(u32)]
+pub enum BoundaryCondition {
+    /// The material sticks to the face: its velocity is set to zero.
+    #[default]
+    Sticky = 0,
+    /// The material slides along the face: the normal component of its velocity is
+    /// set to zero.
+    Slip = 1,
+    /// The material slides along the face and is free to move away from it: only the
+    /// normal velocity pointing toward the outside of the domain is set to zero.
+    Separate = 2,
+    /// The face is glued to the opposite face: material leaving the domain through one of
+    /// them re-enters it through the other.
+    ///
+    /// This always applies to both faces orthogonal to the same axis.
+    Periodic = 3,
+}
+
+/// An axis-aligned box the simulation is confined to.
+///
+/// The boundary conditions are enforced on the grid nodes lying within one cell of
+/// (or beyond) each face of the domain. Particles leaving the domain despite these
+/// conditions are either clamped back on its boundary, or removed from the simulation.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
+#[repr(C)]
+pub struct SimulationDomain {
+    pub mins: Vector<f32>,
+    #[cfg(feature = "dim3")]
+    pub friction: f32,
+    pub maxs: Vector<f32>,
+    #[cfg(feature = "dim3")]
+    enabled: u32,
+    mins_boundaries: BoundaryVector,
+    #[cfg(feature = "dim3")]
+    cull_particles: u32,
+    maxs_boundaries: BoundaryVector,
+    #[cfg(feature = "dim2")]
+    pub friction: f32,
+    #[cfg(feature = "dim2")]
+    enabled: u32,
+    #[cfg(feature = "dim2")]
+    cull_particles: u32,
+    padding: u32,
+}
+
+impl Default for SimulationDomain {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+impl SimulationDomain {
+    /// A domain without any boundary.
+    pub fn unbounded() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    /// A domain spanning the box `[mins, maxs]`, with the same `boundary` condition on each face.
+    ///
+    /// The friction coefficient is zero and particles leaving the domain are clamped.
+    pub fn new(mins: Vector<f32>, maxs: Vector<f32>, boundary: BoundaryCondition) -> Self {
+        Self {
+            mins,
+            maxs,
+            enabled: 1,
+            mins_boundaries: BoundaryVector::repeat(boundary as u32),
+            maxs_boundaries: BoundaryVector::repeat(boundary as u32),
+            ..Self::unbounded()
+        }
+    }
+
+    /// Sets the boundary condition of the face orthogonal to the axis `axis`.
+    ///
+    /// This is the face at `maxs[axis]` if `max_face` is `true`, and the one at `mins[axis]`
+    /// otherwise. Setting a [`BoundaryCondition::Periodic`] boundary is equivalent to
+    /// [`Self::with_periodic_axis`].
+    pub fn with_boundary(
+        mut self,
+        axis: usize,
+        max_face: bool,
+        boundary: BoundaryCondition,
+    ) -> Self {
+        if boundary == BoundaryCondition::Periodic {
+            return self.with_periodic_axis(axis);
+        }
+
+        if max_face {
+            self.maxs_boundaries[axis] = boundary as u32;
+        } else {
+            self.mins_boundaries[axis] = boundary as u32;
+        }
+        self
+    }
+
+    /// Makes the domain periodic along the axis `axis`.
+    ///
+    /// The domain’s extent along a periodic axis is rounded to a whole number of grid blocks
+    /// (8 cells in 2D, 4 cells in 3D) by the grid (see [`crate::grid::grid::GpuGrid::with_domain`]).
+    /// Colliders aren’t replicated across periodic faces.
+    pub fn with_periodic_axis(mut self, axis: usize) -> Self {
+        self.mins_boundaries[axis] = BoundaryCondition::Periodic as u32;
+        self.maxs_boundaries[axis] = BoundaryCondition::Periodic as u32;
+        self
+    }
+
+    /// Sets the Coulomb friction coefficient of the slip and separating faces.
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    /// Sets whether particles leaving the domain are removed from the simulation instead
+    /// of being clamped back on its boundary.
+    pub fn with_particle_culling(mut self, cull: bool) -> Self {
+        self.cull_particles = cull as u32;
+        self
+    }
+
+    /// Is this domain bounded?
+    pub fn is_bounded(&self) -> bool {
+        self.enabled != 0
+    }
+
+    /// Is this domain periodic along the axis `axis`?
+    pub fn is_periodic(&self, axis: usize) -> bool {
+        self.is_bounded() && self.mins_boundaries[axis] == BoundaryCondition::Periodic as u32
+    }
+
+    /// Are particles leaving this domain removed from the simulation?
+    pub fn culls_particles(&self) -> bool {
+        self.is_bounded() && self.cull_particles != 0
+    }
 }
 
 pub struct GpuSimulationParams {
     pub params: GpuScalar<SimulationParams>,
+    /// A copy of the parameters currently stored in `params`.
+    pub cpu_params: SimulationParams,
 }
 
 impl GpuSimulationParams {
@@ -27,8 +174,34 @@ impl GpuSimulationParams {
                 params,
                 BufferUsages::UNIFORM | BufferUsages::COPY_DST,
             ),
+            cpu_params: params,
         }
     }
+
+    /// Replaces the simulation parameters.
+    ///
+    /// The periodic axes of the domain, and its bounds along them, are baked into the grid
+    /// when it is created (see [`crate::grid::grid::GpuGrid::with_domain`]) so they can’t be
+    /// changed here. Returns [`Error::PeriodicDomainChanged`] and leaves the parameters
+    /// untouched if they differ from the current ones.
+    pub fn update(&mut self, queue: &Queue, params: SimulationParams) -> Result<(), Error> {
+        let old_domain = &self.cpu_params.domain;
+        let new_domain = &params.domain;
+        for i in 0..DIM {
+            let periodic = old_domain.is_periodic(i);
+            if periodic != new_domain.is_periodic(i)
+                || (periodic
+                    && (old_domain.mins[i] != new_domain.mins[i]
+                        || old_domain.maxs[i] != new_domain.maxs[i]))
+            {
+                return Err(Error::PeriodicDomainChanged);
+            }
+        }
+
+        queue.write_buffer(self.params.buffer(), 0, bytemuck::bytes_of(&params));
+        self.cpu_params = params;
+        Ok(())
+    }
 }
 
 #[derive(Shader)]
@@ -1,5 +1,32 @@
 #define_import_path wgsparkl::solver::params
 
+const BOUNDARY_STICKY: u32 = 0;
+const BOUNDARY_SLIP: u32 = 1;
+const BOUNDARY_SEPARATE: u32 = 2;
+const BOUNDARY_PERIODIC: u32 = 3;
+
+// NOTE: the field order differs between 2D and 3D so that the
+//       struct matches the uniform layout rules without extra padding.
+struct SimulationDomain {
+#if DIM == 2
+    mins: vec2<f32>,
+    maxs: vec2<f32>,
+    mins_boundaries: vec2<u32>,
+    maxs_boundaries: vec2<u32>,
+    friction: f32,
+    enabled: u32,
+    cull_particles: u32,
+#else
+    mins: vec3<f32>,
+    friction: f32,
+    maxs: vec3<f32>,
+    enabled: u32,
+    mins_boundaries: vec3<u32>,
+    cull_particles: u32,
+    maxs_boundaries: vec3<u32>,
+#endif
+}
+
 struct SimulationParams {
 #if DIM == 2
     gravity: vec2<f32>,
@@ -8,4 +35,28 @@ struct SimulationParams {
     gravity: vec3<f32>,
 #endif
     dt: f32,
-}
\ No newline at end of file
+    domain: SimulationDomain,
+}
+
+// The axes along which the (enabled) simulation domain is periodic.
+#if DIM == 2
+fn periodic_axes(domain: SimulationDomain) -> vec2<bool> {
+    return (domain.mins_boundaries == vec2(BOUNDARY_PERIODIC)) & vec2(domain.enabled != 0u);
+}
+#else
+fn periodic_axes(domain: SimulationDomain) -> vec3<bool> {
+    return (domain.mins_boundaries == vec3(BOUNDARY_PERIODIC)) & vec3(domain.enabled != 0u);
+}
+#endif
+
+// Is the point `pt` outside of the (enabled) simulation domain?
+//
+// Periodic axes are ignored since points are wrapped around them instead.
+#if DIM == 2
+fn is_outside_domain(domain: SimulationDomain, pt: vec2<f32>) -> bool {
+#else
+fn is_outside_domain(domain: SimulationDomain, pt: vec3<f32>) -> bool {
+#endif
+    let outside = (pt < domain.mins) | (pt > domain.maxs);
+    return domain.enabled != 0u && any(outside & !periodic_axes(domain));
+}
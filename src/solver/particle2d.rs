@@ -1,13 +1,18 @@
 use crate::dim_shader_defs;
-use crate::models::{DruckerPrager, ElasticCoefficients};
-use crate::solver::ParticlePhase;
+use crate::models::{
+    ConstitutiveModel, ElasticCoefficients, Fiber, FluidCoefficients, Plasticity,
+    ThermalProperties, Viscosity,
+};
+use crate::solver::emission::init_particle_count;
+use crate::solver::{GpuParticleCount, ParticlePhase};
 use encase::ShaderType;
 use nalgebra::{vector, Matrix2, Point2, Vector2};
 use rapier::geometry::{ColliderSet, Polyline, Segment};
-use wgcore::tensor::GpuVector;
+use std::sync::Arc;
+use wgcore::tensor::{GpuScalar, GpuVector};
 use wgcore::Shader;
 use wgparry::shape::ShapeBuffers;
-use wgpu::{BufferUsages, Device};
+use wgpu::{Buffer, BufferUsages, Device, Queue};
 use wgrapier::dynamics::body::BodyCouplingEntry;
 use wgrapier::dynamics::GpuBodySet;
 
@@ -21,6 +26,10 @@ pub struct ParticleDynamics {
     pub init_volume: f32,
     pub init_radius: f32,
     pub mass: f32,
+    pub temperature: f32,
+    /// The temperature gradient gathered during G2P, converted into the heat flux
+    /// transferred to the grid during the next P2G.
+    pub heat_flux: Vector2<f32>,
 }
 
 impl ParticleDynamics {
@@ -34,9 +43,17 @@ impl ParticleDynamics {
             init_volume,
             init_radius: radius,
             mass: init_volume * density,
+            temperature: 0.0,
+            heat_flux: Vector2::zeros(),
             cdf: Cdf::default(),
         }
     }
+
+    /// Sets the initial temperature of the particle.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Default, ShaderType)]
@@ -45,16 +62,85 @@ pub struct Cdf {
     pub normal: Vector2<f32>,
     pub rigid_vel: Vector2<f32>,
     pub signed_distance: f32,
-    pub affinity: u32,
+    pub affinity: Vector2<u32>,
+    /// The contact material averaged over the colliders the particle has an affinity with.
+    pub friction: f32,
+    pub restitution: f32,
+    pub penalty_stiffness: f32,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct Particle {
     pub position: Vector2<f32>,
     pub dynamics: ParticleDynamics,
+    pub constitutive_model: ConstitutiveModel,
     pub model: ElasticCoefficients,
-    pub plasticity: Option<DruckerPrager>,
+    pub fiber: Option<Fiber>,
+    pub fluid: Option<FluidCoefficients>,
+    pub viscosity: Option<Viscosity>,
+    pub plasticity: Option<Plasticity>,
     pub phase: Option<ParticlePhase>,
+    pub thermal: Option<ThermalProperties>,
+}
+
+impl Particle {
+    /// A fixed-corotated elastic particle, without plasticity, fiber, fluid, viscosity,
+    /// thermal properties, or custom phase.
+    ///
+    /// The other properties are set with the `with_*` methods.
+    pub fn new(
+        position: Vector2<f32>,
+        dynamics: ParticleDynamics,
+        model: ElasticCoefficients,
+    ) -> Self {
+        Self {
+            position,
+            dynamics,
+            constitutive_model: ConstitutiveModel::default(),
+            model,
+            fiber: None,
+            fluid: None,
+            viscosity: None,
+            plasticity: None,
+            phase: None,
+            thermal: None,
+        }
+    }
+
+    pub fn with_constitutive_model(mut self, constitutive_model: ConstitutiveModel) -> Self {
+        self.constitutive_model = constitutive_model;
+        self
+    }
+
+    pub fn with_fiber(mut self, fiber: Fiber) -> Self {
+        self.fiber = Some(fiber);
+        self
+    }
+
+    pub fn with_fluid(mut self, fluid: FluidCoefficients) -> Self {
+        self.fluid = Some(fluid);
+        self
+    }
+
+    pub fn with_viscosity(mut self, viscosity: Viscosity) -> Self {
+        self.viscosity = Some(viscosity);
+        self
+    }
+
+    pub fn with_plasticity(mut self, plasticity: impl Into<Plasticity>) -> Self {
+        self.plasticity = Some(plasticity.into());
+        self
+    }
+
+    pub fn with_phase(mut self, phase: ParticlePhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    pub fn with_thermal(mut self, thermal: ThermalProperties) -> Self {
+        self.thermal = Some(thermal);
+        self
+    }
 }
 
 pub struct GpuRigidParticles {
@@ -111,7 +197,7 @@ impl GpuRigidParticles {
             sample_points: GpuVector::init(
                 device,
                 &sampling_buffers.samples,
-                BufferUsages::STORAGE,
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             ),
             node_linked_lists: GpuVector::uninit(
                 device,
@@ -147,34 +233,82 @@ pub struct GpuParticles {
     pub dynamics: GpuVector<ParticleDynamics>,
     pub sorted_ids: GpuVector<u32>,
     pub node_linked_lists: GpuVector<u32>,
+    /// The number of live particles, and the capacity of the particle buffers.
+    ///
+    /// Only the first `count.len` particles are simulated. This is updated on the GPU
+    /// whenever particles are emitted or removed.
+    pub count: GpuScalar<GpuParticleCount>,
+    /// Indirect dispatch arguments for launching one thread per live particle.
+    pub indirect_n_groups: Arc<Buffer>,
 }
 
 impl GpuParticles {
-    pub fn is_empty(&self) -> bool {
-        self.positions.is_empty()
+    /// The maximum number of particles these buffers can hold.
+    pub fn capacity(&self) -> usize {
+        self.positions.len() as usize
     }
 
+    #[deprecated(
+        note = "this is the buffer capacity; read back `count` for the number of live particles, or use `capacity`"
+    )]
     pub fn len(&self) -> usize {
-        self.positions.len() as usize
+        self.capacity()
+    }
+
+    #[deprecated(
+        note = "this is the buffer capacity; read back `count` for the number of live particles, or use `capacity`"
+    )]
+    pub fn is_empty(&self) -> bool {
+        self.capacity() == 0
     }
 
     pub fn from_particles(device: &Device, particles: &[Particle]) -> Self {
+        Self::with_capacity(device, particles, particles.len())
+    }
+
+    /// Overwrites the positions and dynamics of the first `particles.len()` particles.
+    ///
+    /// This doesn’t change the number of live particles. The buffers must have room for all
+    /// the `particles`.
+    pub fn write(&self, queue: &Queue, particles: &[Particle]) {
         let positions: Vec<_> = particles.iter().map(|p| p.position).collect();
         let dynamics: Vec<_> = particles.iter().map(|p| p.dynamics).collect();
+        let mut dynamics_bytes = encase::StorageBuffer::new(vec![]);
+        dynamics_bytes
+            .write(&dynamics)
+            .expect("Failed to encode the particle dynamics.");
+        queue.write_buffer(self.positions.buffer(), 0, bytemuck::cast_slice(&positions));
+        queue.write_buffer(self.dynamics.buffer(), 0, &dynamics_bytes.into_inner());
+    }
+
+    /// Initializes the particle buffers with room for at least `capacity` particles.
+    ///
+    /// Only the given `particles` are live initially. The remaining slots can be filled
+    /// later by particle emission.
+    pub fn with_capacity(device: &Device, particles: &[Particle], capacity: usize) -> Self {
+        let capacity = capacity.max(particles.len());
+        let mut positions: Vec<_> = particles.iter().map(|p| p.position).collect();
+        let mut dynamics: Vec<_> = particles.iter().map(|p| p.dynamics).collect();
+        positions.resize(capacity, Vector2::zeros());
+        dynamics.resize(capacity, ParticleDynamics::with_density(0.0, 0.0));
+        let (count, indirect_n_groups) =
+            init_particle_count(device, particles.len() as u32, capacity as u32);
 
         Self {
             positions: GpuVector::init(
                 device,
                 &positions,
-                BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             ),
-            dynamics: GpuVector::encase(device, &dynamics, BufferUsages::STORAGE),
-            sorted_ids: GpuVector::uninit(device, particles.len() as u32, BufferUsages::STORAGE),
-            node_linked_lists: GpuVector::uninit(
+            dynamics: GpuVector::encase(
                 device,
-                particles.len() as u32,
-                BufferUsages::STORAGE,
+                &dynamics,
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             ),
+            sorted_ids: GpuVector::uninit(device, capacity as u32, BufferUsages::STORAGE),
+            node_linked_lists: GpuVector::uninit(device, capacity as u32, BufferUsages::STORAGE),
+            count,
+            indirect_n_groups,
         }
     }
 }
@@ -1,13 +1,20 @@
 #define_import_path wgsparkl::solver::particle
 
+// The number of live particles, and the capacity of the particle buffers.
+struct Count {
+    len: u32,
+    capacity: u32,
+}
+
 struct Position {
     pt: vec2<f32>,
 }
 
 struct Dynamics {
-    // NOTE: with this arrangement, we have
-    //       only a 4-bytes padding at the end
-    //       of the struct.
+    // NOTE: with this arrangement, the struct has no padding between its
+    //       fields. The `Cdf` has 8 bytes of padding though: 4 bytes after
+    //       `signed_distance` (to align `affinity` to 8 bytes), and 4 bytes
+    //       at its end.
     velocity: vec2<f32>,
     def_grad: mat2x2<f32>,
     affine: mat2x2<f32>,
@@ -15,6 +22,10 @@ struct Dynamics {
     init_volume: f32,
     init_radius: f32,
     mass: f32,
+    temperature: f32,
+    // The temperature gradient after G2P, replaced by the heat flux
+    // transferred to the grid by the particle update.
+    heat_flux: vec2<f32>,
 }
 
 struct RigidParticleIndices {
@@ -27,13 +38,18 @@ struct Cdf {
     normal: vec2<f32>,
     rigid_vel: vec2<f32>,
     signed_distance: f32,
-    affinity: u32,
+    // See `Grid::NodeCdf::affinities`.
+    affinity: vec2<u32>,
+    // The contact material averaged over the colliders the particle has an affinity with.
+    friction: f32,
+    restitution: f32,
+    penalty_stiffness: f32,
 //    // Index to the closest collider.
 //    closest_id: u32,
 }
 
 fn default_cdf() -> Cdf {
-    return Cdf(vec2(0.0), vec2(0.0), 0.0, 0);
+    return Cdf(vec2(0.0), vec2(0.0), 0.0, vec2(0u), 0.0, 0.0, 0.0);
 }
 
 fn closest_grid_pos(part_pos: Position, cell_width: f32) -> vec2<f32> {
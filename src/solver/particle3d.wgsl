@@ -1,5 +1,11 @@
 #define_import_path wgsparkl::solver::particle
 
+// The number of live particles, and the capacity of the particle buffers.
+struct Count {
+    len: u32,
+    capacity: u32,
+}
+
 struct Position {
     pt: vec3<f32>,
 }
@@ -12,6 +18,10 @@ struct Dynamics {
     init_volume: f32,
     init_radius: f32,
     mass: f32,
+    temperature: f32,
+    // The temperature gradient after G2P, replaced by the heat flux
+    // transferred to the grid by the particle update.
+    heat_flux: vec3<f32>,
 }
 
 struct Cdf {
@@ -19,7 +29,12 @@ struct Cdf {
     normal: vec3<f32>,
     rigid_vel: vec3<f32>,
     signed_distance: f32,
-    affinity: u32,
+    // See `Grid::NodeCdf::affinities`.
+    affinity: vec2<u32>,
+    // The contact material averaged over the colliders the particle has an affinity with.
+    friction: f32,
+    restitution: f32,
+    penalty_stiffness: f32,
 //    // Index to the closest collider.
 //    closest_id: u32,
 }
@@ -31,7 +46,7 @@ struct RigidParticleIndices {
 
 
 fn default_cdf() -> Cdf {
-    return Cdf(vec3(0.0), vec3(0.0), 0.0, 0);
+    return Cdf(vec3(0.0), vec3(0.0), 0.0, vec2(0u), 0.0, 0.0, 0.0);
 }
 
 fn closest_grid_pos(part_pos: Position, cell_width: f32) -> vec3<f32> {
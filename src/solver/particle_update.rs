@@ -2,7 +2,10 @@ use crate::collision::WgCollide;
 use crate::dim_shader_defs;
 use crate::grid::grid::{GpuGrid, WgGrid};
 use crate::grid::kernel::WgKernel;
-use crate::models::{GpuModels, WgDruckerPrager, WgLinearElasticity, WgNeoHookeanElasticity};
+use crate::models::{
+    GpuModels, WgDamage, WgDruckerPrager, WgFiber, WgFluid, WgLinearElasticity, WgNacc,
+    WgNeoHookeanElasticity, WgSnow, WgThermal, WgViscosity, WgVonMises,
+};
 use crate::solver::params::{GpuSimulationParams, WgParams};
 use crate::solver::GpuParticles;
 use crate::solver::WgParticle;
@@ -20,7 +23,15 @@ use wgrapier::dynamics::GpuBodySet;
         WgGrid,
         WgNeoHookeanElasticity,
         WgLinearElasticity,
+        WgFiber,
+        WgFluid,
+        WgViscosity,
+        WgDamage,
         WgDruckerPrager,
+        WgSnow,
+        WgVonMises,
+        WgNacc,
+        WgThermal,
         WgKernel,
         WgCollide
     ),
@@ -30,13 +41,69 @@ use wgrapier::dynamics::GpuBodySet;
 )]
 pub struct WgParticleUpdate {
     pub main: ComputePipeline,
+    pub thermal_update: ComputePipeline,
+    pub drucker_prager_projection: ComputePipeline,
+    pub snow_projection: ComputePipeline,
+    pub von_mises_projection: ComputePipeline,
+    pub nacc_projection: ComputePipeline,
+    pub update_elastic_coefficients: ComputePipeline,
+    pub compute_stress: ComputePipeline,
 }
 
+/// The fracture state of a particle.
+///
+/// Intact particles (with a positive `phase`) don’t go through their plasticity model. Their
+/// damage grows once their largest principal stress exceeds `damage_threshold`, degrading
+/// their elastic coefficients by a factor `1 - damage`. Once fully damaged, the particle
+/// switches to the broken phase (`phase == 0`) where its plasticity model applies.
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug)]
 #[repr(C)]
 pub struct ParticlePhase {
     pub phase: f32,
-    pub max_stretch: f32,
+    /// The damage, between 0 (undamaged) and 1 (broken).
+    pub damage: f32,
+    /// The largest principal stress the particle can sustain before getting damaged.
+    /// Negative for an unbreakable particle.
+    pub damage_threshold: f32,
+    /// Controls how quickly the damage grows once the threshold is exceeded.
+    pub softening: f32,
+}
+
+impl ParticlePhase {
+    /// An intact particle that never breaks.
+    pub fn unbreakable() -> Self {
+        Self {
+            phase: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// An intact particle that breaks under tensile principal stresses above `damage_threshold`.
+    pub fn breakable(damage_threshold: f32, softening: f32) -> Self {
+        Self {
+            phase: 1.0,
+            damage: 0.0,
+            damage_threshold,
+            softening,
+        }
+    }
+
+    /// Is this particle broken?
+    pub fn is_broken(&self) -> bool {
+        self.phase == 0.0
+    }
+}
+
+impl Default for ParticlePhase {
+    /// A broken particle.
+    fn default() -> Self {
+        Self {
+            phase: 0.0,
+            damage: 0.0,
+            damage_threshold: -1.0,
+            softening: 0.0,
+        }
+    }
 }
 
 impl WgParticleUpdate {
@@ -51,21 +118,183 @@ impl WgParticleUpdate {
     ) {
         KernelInvocationBuilder::new(queue, &self.main)
             .bind(0, [grid.meta.buffer()])
-            .bind(
+            .bind_at(
                 1,
                 [
-                    particles.positions.buffer(),
-                    particles.dynamics.buffer(),
-                    models.linear_elasticity.buffer(),
-                    models.drucker_prager_plasticity.buffer(),
-                    models.drucker_prager_plastic_state.buffer(),
-                    models.phases.buffer(),
-                    sim_params.params.buffer(),
+                    (particles.positions.buffer(), 0),
+                    (particles.dynamics.buffer(), 1),
+                    (sim_params.params.buffer(), 6),
+                    (particles.count.buffer(), 7),
                 ],
             )
             // .bind(2, [bodies.shapes().buffer(), bodies.poses().buffer()])
-            .queue(particles.positions.len().div_ceil(64) as u32);
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.thermal_update)
+            .bind(0, [grid.meta.buffer()])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.linear_elasticity.buffer(), 2),
+                    (sim_params.params.buffer(), 6),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.fluid.buffer(), 9),
+                    (models.thermal.buffer(), 19),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        // NOTE: the plasticity projections don’t read anything from the grid, so their
+        //       bind group 0 is empty.
+        KernelInvocationBuilder::new(queue, &self.drucker_prager_projection)
+            .bind(0, [])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.drucker_prager_plasticity.buffer(), 3),
+                    (models.drucker_prager_plastic_state.buffer(), 4),
+                    (models.phases.buffer(), 5),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.plasticity_ids.buffer(), 10),
+                    (models.thermal.buffer(), 19),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.snow_projection)
+            .bind(0, [])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.phases.buffer(), 5),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.plasticity_ids.buffer(), 10),
+                    (models.snow_plasticity.buffer(), 11),
+                    (models.snow_plastic_state.buffer(), 12),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.von_mises_projection)
+            .bind(0, [])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.phases.buffer(), 5),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.plasticity_ids.buffer(), 10),
+                    (models.von_mises_plasticity.buffer(), 13),
+                    (models.von_mises_plastic_state.buffer(), 14),
+                    (models.effective_elasticity.buffer(), 18),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.nacc_projection)
+            .bind(0, [])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.phases.buffer(), 5),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.plasticity_ids.buffer(), 10),
+                    (models.nacc_plasticity.buffer(), 15),
+                    (models.nacc_plastic_state.buffer(), 16),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.update_elastic_coefficients)
+            .bind(0, [])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.linear_elasticity.buffer(), 2),
+                    (models.phases.buffer(), 5),
+                    (particles.count.buffer(), 7),
+                    (models.snow_plasticity.buffer(), 11),
+                    (models.snow_plastic_state.buffer(), 12),
+                    (models.effective_elasticity.buffer(), 18),
+                    (models.thermal.buffer(), 19),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
+
+        KernelInvocationBuilder::new(queue, &self.compute_stress)
+            .bind(0, [grid.meta.buffer()])
+            .bind_at(
+                1,
+                [
+                    (particles.dynamics.buffer(), 1),
+                    (models.phases.buffer(), 5),
+                    (sim_params.params.buffer(), 6),
+                    (particles.count.buffer(), 7),
+                    (models.model_ids.buffer(), 8),
+                    (models.fluid.buffer(), 9),
+                    (models.viscosity.buffer(), 17),
+                    (models.effective_elasticity.buffer(), 18),
+                    (models.fibers.buffer(), 20),
+                ],
+            )
+            .queue_indirect(particles.indirect_n_groups.clone());
     }
 }
 
 wgcore::test_shader_compilation!(WgParticleUpdate, wgcore, crate::dim_shader_defs());
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::ParticlePhase;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::vector;
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn breakable_bar_breaks_under_tension() {
+        let params = SimulationParams {
+            gravity: vector![0.0, 0.0, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+
+        // Both halves of the bar are pulled apart.
+        let bar = |phase: ParticlePhase| -> Vec<_> {
+            particle_block(10, 0.5, vector![0.0, 0.0, 0.0])
+                .into_iter()
+                .map(|mut particle| {
+                    let dir = if particle.position.x < 2.25 {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    particle.dynamics.velocity = vector![dir * 5.0, 0.0, 0.0];
+                    particle.with_phase(phase)
+                })
+                .collect()
+        };
+
+        let bodies = RigidBodySet::default();
+        let colliders = ColliderSet::default();
+        let breakable = bar(ParticlePhase::breakable(1_000.0, 1.0));
+        let unbreakable = bar(ParticlePhase::unbreakable());
+        let breakable = simulate(&breakable, params, &bodies, &colliders, 60).await;
+        let unbreakable = simulate(&unbreakable, params, &bodies, &colliders, 60).await;
+
+        assert!(breakable.phases.iter().any(|phase| phase.is_broken()));
+        assert!(unbreakable.phases.iter().all(|phase| !phase.is_broken()));
+    }
+}
@@ -4,11 +4,17 @@
 #import wgsparkl::solver::particle as Particle;
 #import wgsparkl::grid::kernel as Kernel;
 #import wgsparkl::grid::grid as Grid;
-//#import wgsparkl::models::neo_hookean_elasticity as ConstitutiveModel;
-#import wgsparkl::models::linear_elasticity as ConstitutiveModel;
+#import wgsparkl::models::linear_elasticity as LinearElasticity;
+#import wgsparkl::models::neo_hookean_elasticity as NeoHookean;
+#import wgsparkl::models::fiber as Fiber;
+#import wgsparkl::models::fluid as Fluid;
+#import wgsparkl::models::viscosity as Viscosity;
+#import wgsparkl::models::damage as Damage;
 #import wgsparkl::models::drucker_prager as DruckerPrager;
-#import wgebra::svd2 as Svd2
-#import wgebra::svd3 as Svd3
+#import wgsparkl::models::snow as Snow;
+#import wgsparkl::models::von_mises as VonMises;
+#import wgsparkl::models::nacc as Nacc;
+#import wgsparkl::models::thermal as Thermal;
 
 #if DIM == 2
 #import wgebra::sim2 as Pose;
@@ -17,30 +23,74 @@
 #endif
 #import wgparry::cuboid as Cuboid;
 
+// NOTE: the update is split into several entry points (kinematics, then heat and
+//       melting, then one per plasticity model, then the elastic coefficients, then the stress) so that each
+//       of them stays within the per-stage storage buffer limit. Each entry point binds
+//       only the buffers it uses.
 @group(1) @binding(0)
 var<storage, read_write> particles_pos: array<Particle::Position>;
 @group(1) @binding(1)
 var<storage, read_write> particles_dyn: array<Particle::Dynamics>;
 @group(1) @binding(2)
-var<storage, read> constitutive_model: array<ConstitutiveModel::ElasticCoefficients>;
+var<storage, read> constitutive_model: array<LinearElasticity::ElasticCoefficients>;
 @group(1) @binding(3)
 var<storage, read> plasticity: array<DruckerPrager::Plasticity>;
 @group(1) @binding(4)
 var<storage, read_write> plastic_state: array<DruckerPrager::PlasticState>;
 @group(1) @binding(5)
-var<storage, read_write> phases: array<Phase>;
+var<storage, read_write> phases: array<Damage::Phase>;
 @group(1) @binding(6)
 var<uniform> params: Params::SimulationParams;
+@group(1) @binding(7)
+var<storage, read> particles_count: Particle::Count;
+// NOTE: the model ids and fluid coefficients are modified when a particle melts.
+@group(1) @binding(8)
+var<storage, read_write> model_ids: array<u32>;
+@group(1) @binding(9)
+var<storage, read_write> fluid_coefficients: array<Fluid::FluidCoefficients>;
+@group(1) @binding(10)
+var<storage, read> plasticity_ids: array<u32>;
+@group(1) @binding(11)
+var<storage, read> snow_plasticity: array<Snow::Plasticity>;
+@group(1) @binding(12)
+var<storage, read_write> snow_plastic_state: array<Snow::PlasticState>;
+@group(1) @binding(13)
+var<storage, read> von_mises_plasticity: array<VonMises::Plasticity>;
+@group(1) @binding(14)
+var<storage, read_write> von_mises_plastic_state: array<VonMises::PlasticState>;
+@group(1) @binding(15)
+var<storage, read> nacc_plasticity: array<Nacc::Plasticity>;
+@group(1) @binding(16)
+var<storage, read_write> nacc_plastic_state: array<Nacc::PlasticState>;
+@group(1) @binding(17)
+var<storage, read> viscosity: array<Viscosity::Viscosity>;
+// NOTE: the elastic coefficients accounting for the hardening and damage, recomputed
+//       at each step by `update_elastic_coefficients`.
+@group(1) @binding(18)
+var<storage, read_write> effective_elasticity: array<LinearElasticity::ElasticCoefficients>;
+@group(1) @binding(19)
+var<storage, read> thermal: array<Thermal::ThermalProperties>;
+@group(1) @binding(20)
+var<storage, read> fibers: array<Fiber::Fiber>;
 
 @group(2) @binding(0)
 var<storage, read> collision_shapes: array<Cuboid::Cuboid>;
 @group(2) @binding(1)
 var<storage, read> collision_shape_poses: array<Transform>;
 
-struct Phase {
-    phase: f32,
-    max_stretch: f32,
-}
+// NOTE: must match the discriminants of the `ConstitutiveModel` enum on the Rust side.
+const MODEL_COROTATED: u32 = 0;
+const MODEL_NEO_HOOKEAN: u32 = 1;
+const MODEL_FLUID: u32 = 2;
+
+// NOTE: must match `Plasticity::model_id` on the Rust side.
+const PLASTICITY_DRUCKER_PRAGER: u32 = 0;
+const PLASTICITY_SNOW: u32 = 1;
+const PLASTICITY_VON_MISES: u32 = 2;
+const PLASTICITY_NACC: u32 = 3;
+// NOTE: particles without plasticity still go through the Drucker-Prager projection, with
+//       the negative coefficients set on the Rust side.
+const PLASTICITY_NONE: u32 = 4;
 
 @compute @workgroup_size(64, 1, 1)
 fn main(
@@ -48,7 +98,7 @@ fn main(
 ) {
     let particle_id = gid.x;
 
-    if particle_id >= arrayLength(&particles_pos) {
+    if particle_id >= particles_count.len {
         return;
     }
 
@@ -62,7 +112,8 @@ fn main(
      * Advection.
      */
     if dynamics.cdf.signed_distance < -0.05 * cell_width {
-        new_particle_vel = dynamics.cdf.rigid_vel + Grid::project_velocity((new_particle_vel - dynamics.cdf.rigid_vel), dynamics.cdf.normal);
+        let cdf = dynamics.cdf;
+        new_particle_vel = cdf.rigid_vel + Grid::project_velocity(new_particle_vel - cdf.rigid_vel, cdf.normal, cdf.friction, cdf.restitution);
     }
 
     // Clamp the max velocity a particle can get.
@@ -71,15 +122,25 @@ fn main(
         new_particle_vel = new_particle_vel / length(new_particle_vel) * cell_width / dt;
     }
 
-    let new_particle_pos = particle_pos + new_particle_vel * dt;
+    var new_particle_pos = particle_pos + new_particle_vel * dt;
+
+    // Particles leaving the domain are clamped on its boundary, unless they
+    // are culled (in which case they get removed by the emission kernels).
+    let domain = params.domain;
+    if domain.enabled != 0u && domain.cull_particles == 0u {
+        let clamped_pos = clamp(new_particle_pos, domain.mins, domain.maxs);
+        new_particle_pos = select(clamped_pos, new_particle_pos, Params::periodic_axes(domain));
+    }
+
+    // Particles crossing a periodic face re-enter through the opposite face.
+    new_particle_pos = Grid::wrap_point(new_particle_pos);
 
     /*
      * Penalty impulse.
      */
-     const PENALTY_COEFF: f32 = 1.0e3;
      if dynamics.cdf.signed_distance < -0.05 * cell_width { // && dynamics.cdf.signed_distance > -0.3 * cell_width {
          let corrected_dist = max(dynamics.cdf.signed_distance, -0.3 * cell_width);
-         let impulse = (dt * -corrected_dist * PENALTY_COEFF) * dynamics.cdf.normal;
+         let impulse = (dt * -corrected_dist * dynamics.cdf.penalty_stiffness) * dynamics.cdf.normal;
          new_particle_vel += impulse; // / curr_particle_vol.mass;
      }
 
@@ -87,55 +148,220 @@ fn main(
      * Deformation gradient update.
      */
     // NOTE: the velocity gradient was stored in the affine buffer.
-    var new_deformation_gradient = dynamics.def_grad +
+    let new_deformation_gradient = dynamics.def_grad +
        (dynamics.affine * dt) * dynamics.def_grad;
 
     /*
-     * Constitutive model.
+     * Write back the new particle properties.
      */
-    var phase = phases[particle_id].phase;
-
-    // Update Phase.
-    // TODO: should be stress based instead.
-    let max_stretch = phases[particle_id].max_stretch;
-    if phase > 0.0 && max_stretch > 0.0 {
-    #if DIM == 2
-        let svd = Svd2::svd(new_deformation_gradient);
-        if svd.S.x > max_stretch || svd.S.y > max_stretch {
-            phases[particle_id].phase = 0.0;
-            phase = 0.0;
-        }
-    #else
-        let svd = Svd3::svd(new_deformation_gradient);
-        if svd.S.x > max_stretch || svd.S.y > max_stretch || svd.S.z > max_stretch {
-            phases[particle_id].phase = 0.0;
-            phase = 0.0;
+    particles_pos[particle_id].pt = new_particle_pos;
+    particles_dyn[particle_id].velocity = new_particle_vel;
+    particles_dyn[particle_id].def_grad = new_deformation_gradient;
+    // NOTE: the affine buffer still contains the velocity gradient at this point. It is
+    //       replaced by the APIC affine matrix in `compute_stress`.
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn thermal_update(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len {
+        return;
+    }
+
+    let props = thermal[particle_id];
+    let dynamics = particles_dyn[particle_id];
+
+    // NOTE: the heat flux buffer contains the temperature gradient computed during g2p.
+    let inv_d = Kernel::inv_d(Grid::grid.cell_width);
+    let volume = dynamics.init_volume * determinant(dynamics.def_grad);
+    particles_dyn[particle_id].heat_flux = dynamics.heat_flux * Thermal::heat_flux_factor(props, volume, inv_d, params.dt);
+
+    // Melting.
+    if model_ids[particle_id] != MODEL_FLUID && Thermal::is_melted(props, dynamics.temperature) {
+        model_ids[particle_id] = MODEL_FLUID;
+
+        if fluid_coefficients[particle_id].bulk_modulus == 0.0 {
+            // No fluid coefficients were given, use the bulk modulus of the solid.
+            let coeffs = constitutive_model[particle_id];
+#if DIM == 2
+            let bulk_modulus = coeffs.lambda + coeffs.mu;
+#else
+            let bulk_modulus = coeffs.lambda + coeffs.mu * (2.0 / 3.0);
+#endif
+            fluid_coefficients[particle_id].bulk_modulus = bulk_modulus;
+            fluid_coefficients[particle_id].gamma = 1.0;
         }
-    #endif
     }
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn drucker_prager_projection(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len
+        || (plasticity_ids[particle_id] != PLASTICITY_DRUCKER_PRAGER && plasticity_ids[particle_id] != PLASTICITY_NONE)
+        || model_ids[particle_id] == MODEL_FLUID
+        || phases[particle_id].phase != 0.0 {
+        return;
+    }
+
+    let dynamics = particles_dyn[particle_id];
+    var particle_plasticity = plasticity[particle_id];
+    particle_plasticity.ha = max(particle_plasticity.ha + Thermal::friction_angle_offset(thermal[particle_id], dynamics.temperature), 0.0);
+
+    let projection = DruckerPrager::project(particle_plasticity, plastic_state[particle_id], dynamics.def_grad);
+    plastic_state[particle_id] = projection.state;
+    particles_dyn[particle_id].def_grad = projection.deformation_gradient;
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn snow_projection(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len
+        || plasticity_ids[particle_id] != PLASTICITY_SNOW
+        || model_ids[particle_id] == MODEL_FLUID
+        || phases[particle_id].phase != 0.0 {
+        return;
+    }
+
+    let projection = Snow::project(snow_plasticity[particle_id], snow_plastic_state[particle_id], particles_dyn[particle_id].def_grad);
+    snow_plastic_state[particle_id] = projection.state;
+    particles_dyn[particle_id].def_grad = projection.deformation_gradient;
+}
 
-    // Plasticity.
-    if phase == 0.0 {
-        let projection = DruckerPrager::project(plasticity[particle_id], plastic_state[particle_id], new_deformation_gradient);
-        plastic_state[particle_id] = projection.state;
-        new_deformation_gradient = projection.deformation_gradient;
+@compute @workgroup_size(64, 1, 1)
+fn von_mises_projection(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len
+        || plasticity_ids[particle_id] != PLASTICITY_VON_MISES
+        || model_ids[particle_id] == MODEL_FLUID
+        || phases[particle_id].phase != 0.0 {
+        return;
     }
 
+    // NOTE: the effective elasticity is the one computed at the previous step (or the
+    //       initial coefficients), since it is only updated after the projections.
+    let mu = effective_elasticity[particle_id].mu;
+    let projection = VonMises::project(von_mises_plasticity[particle_id], von_mises_plastic_state[particle_id], mu, particles_dyn[particle_id].def_grad);
+    von_mises_plastic_state[particle_id] = projection.state;
+    particles_dyn[particle_id].def_grad = projection.deformation_gradient;
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn nacc_projection(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len
+        || plasticity_ids[particle_id] != PLASTICITY_NACC
+        || model_ids[particle_id] == MODEL_FLUID
+        || phases[particle_id].phase != 0.0 {
+        return;
+    }
+
+    let projection = Nacc::project(nacc_plasticity[particle_id], nacc_plastic_state[particle_id], particles_dyn[particle_id].def_grad);
+    nacc_plastic_state[particle_id] = projection.state;
+    particles_dyn[particle_id].def_grad = projection.deformation_gradient;
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn update_elastic_coefficients(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len {
+        return;
+    }
+
+    // NOTE: the hardening is 1 for particles without snow plasticity.
+    let hardening = Snow::hardening(snow_plasticity[particle_id], snow_plastic_state[particle_id]);
+    let softening = Thermal::stiffness_factor(thermal[particle_id], particles_dyn[particle_id].temperature);
+    let factor = hardening * softening * Damage::degradation(phases[particle_id]);
+    let coeffs = constitutive_model[particle_id];
+    effective_elasticity[particle_id] = LinearElasticity::ElasticCoefficients(coeffs.lambda * factor, coeffs.mu * factor);
+}
+
+@compute @workgroup_size(64, 1, 1)
+fn compute_stress(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+) {
+    let particle_id = gid.x;
+
+    if particle_id >= particles_count.len {
+        return;
+    }
+
+    let dt = params.dt;
+    let cell_width = Grid::grid.cell_width;
+    let dynamics = particles_dyn[particle_id];
+    var deformation_gradient = dynamics.def_grad;
+
+    // Fluids only keep track of their volume change, and viscoelastic solids
+    // relax their shear strain.
+    let model_id = model_ids[particle_id];
+    let particle_viscosity = viscosity[particle_id];
+    if model_id == MODEL_FLUID {
+        deformation_gradient = Fluid::project_deformation_gradient(deformation_gradient);
+    } else {
+        deformation_gradient = Viscosity::relax(particle_viscosity, deformation_gradient, dt);
+    }
+    particles_dyn[particle_id].def_grad = deformation_gradient;
+
     // Elasticity.
-    let stress = ConstitutiveModel::kirchoff_stress(constitutive_model[particle_id], new_deformation_gradient);
+    // NOTE: the velocity gradient was stored in the affine buffer.
+    let elastic_stress = kirchoff_stress(particle_id, model_id, deformation_gradient, dynamics.affine);
+
+    // Damage, driven by the largest principal (Cauchy) stress of the undamaged material.
+    let phase = phases[particle_id];
+    if model_id != MODEL_FLUID && phase.phase > 0.0 && phase.damage_threshold > 0.0 {
+        let j = max(determinant(deformation_gradient), 1.0e-10);
+        let undamaged_stress = elastic_stress * (1.0 / (j * max(Damage::degradation(phase), 1.0e-3)));
+        phases[particle_id] = Damage::update(phase, Damage::max_eigenvalue(undamaged_stress));
+    }
+
+    // Viscosity.
+    let stress = elastic_stress + Viscosity::kirchoff_stress(particle_viscosity, deformation_gradient, dynamics.affine);
 
     /*
      * Affine matrix for APIC transfer.
      */
     let inv_d = Kernel::inv_d(cell_width);
     // NOTE: the velocity gradient was stored in the affine buffer.
-    let affine = dynamics.affine * dynamics.mass - stress * (dynamics.init_volume * inv_d * dt);
+    particles_dyn[particle_id].affine = dynamics.affine * dynamics.mass - stress * (dynamics.init_volume * inv_d * dt);
+}
 
-    /*
-     * Write back the new particle properties.
-     */
-    particles_pos[particle_id].pt = new_particle_pos;
-    particles_dyn[particle_id].velocity = new_particle_vel;
-    particles_dyn[particle_id].def_grad = new_deformation_gradient;
-    particles_dyn[particle_id].affine = affine;
+#if DIM == 2
+fn kirchoff_stress(particle_id: u32, model_id: u32, deformation_gradient: mat2x2<f32>, velocity_gradient: mat2x2<f32>) -> mat2x2<f32> {
+#else
+fn kirchoff_stress(particle_id: u32, model_id: u32, deformation_gradient: mat3x3<f32>, velocity_gradient: mat3x3<f32>) -> mat3x3<f32> {
+#endif
+    if model_id == MODEL_FLUID {
+        return Fluid::kirchoff_stress(fluid_coefficients[particle_id], deformation_gradient, velocity_gradient);
+    }
+
+    let coeffs = effective_elasticity[particle_id];
+    // NOTE: the fibers are degraded by the damage, like the elastic coefficients.
+    let fiber_stress = Fiber::kirchoff_stress(fibers[particle_id], deformation_gradient)
+        * Damage::degradation(phases[particle_id]);
+
+    if model_id == MODEL_NEO_HOOKEAN {
+        let neo_hookean = NeoHookean::ElasticCoefficients(coeffs.lambda, coeffs.mu);
+        return NeoHookean::kirchoff_stress(neo_hookean, deformation_gradient) + fiber_stress;
+    }
+
+    // MODEL_COROTATED
+    return LinearElasticity::kirchoff_stress(coeffs, deformation_gradient) + fiber_stress;
 }
@@ -0,0 +1,424 @@
+use crate::error::Error;
+use crate::models::{
+    DruckerPragerPlasticState, GpuModels, NaccPlasticState, PlasticState, SnowPlasticState,
+    VonMisesPlasticState,
+};
+use crate::solver::{GpuParticleCount, GpuParticles, ParticleDynamics, ParticlePhase};
+use encase::{ShaderSize, StorageBuffer};
+use rapier::math::{Vector, DIM};
+use wgcore::tensor::GpuVector;
+use wgpu::{BufferAddress, BufferUsages, CommandEncoder, Device};
+
+/// The type of the elements of [`GpuParticles::positions`].
+#[cfg(feature = "dim2")]
+type GpuPosition = nalgebra::Vector2<f32>;
+/// The type of the elements of [`GpuParticles::positions`].
+#[cfg(feature = "dim3")]
+type GpuPosition = nalgebra::Vector4<f32>;
+
+/// Selects the particle fields downloaded by a [`GpuParticleReadback`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ParticleFields {
+    pub positions: bool,
+    /// The velocities, deformation gradients, and the rest of the [`ParticleDynamics`].
+    pub dynamics: bool,
+    pub phases: bool,
+    pub plastic_states: bool,
+    /// The [`crate::models::ConstitutiveModel`] of each particle.
+    pub model_ids: bool,
+}
+
+impl ParticleFields {
+    /// Every particle field.
+    pub const ALL: Self = Self {
+        positions: true,
+        dynamics: true,
+        phases: true,
+        plastic_states: true,
+        model_ids: true,
+    };
+
+    /// Only the particle positions.
+    pub const POSITIONS: Self = Self {
+        positions: true,
+        dynamics: false,
+        phases: false,
+        plastic_states: false,
+        model_ids: false,
+    };
+}
+
+/// A CPU copy of the state of the live particles.
+///
+/// Each field contains one element per live particle if it was selected by the
+/// [`ParticleFields`] of the readback, and is empty otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct ParticleSnapshot {
+    /// The number of live particles.
+    pub len: usize,
+    pub positions: Vec<Vector<f32>>,
+    pub dynamics: Vec<ParticleDynamics>,
+    pub phases: Vec<ParticlePhase>,
+    /// The state of the plasticity model of each particle.
+    ///
+    /// Particles without plasticity are reported with [`PlasticState::None`].
+    pub plastic_states: Vec<PlasticState>,
+    /// The [`crate::models::ConstitutiveModel`] of each particle, as a `u32`.
+    pub model_ids: Vec<u32>,
+}
+
+/// Staging buffers for reading back the particle state.
+///
+/// The staging buffers are allocated once for the capacity of the particle buffers so they
+/// can be reused at every readback. Only the buffers of the selected [`ParticleFields`] are
+/// allocated and copied.
+pub struct GpuParticleReadback {
+    fields: ParticleFields,
+    count: GpuVector<GpuParticleCount>,
+    positions: Option<GpuVector<GpuPosition>>,
+    /// The raw bytes of the encased [`ParticleDynamics`], as words.
+    dynamics: Option<GpuVector<u32>>,
+    phases: Option<GpuVector<ParticlePhase>>,
+    plastic_states: Option<PlasticStateStaging>,
+    model_ids: Option<GpuVector<u32>>,
+}
+
+struct PlasticStateStaging {
+    plasticity_ids: GpuVector<u32>,
+    drucker_prager: GpuVector<DruckerPragerPlasticState>,
+    snow: GpuVector<SnowPlasticState>,
+    von_mises: GpuVector<VonMisesPlasticState>,
+    nacc: GpuVector<NaccPlasticState>,
+}
+
+impl GpuParticleReadback {
+    /// Allocates the staging buffers for reading back the given `fields` of `particles`.
+    pub fn new(device: &Device, particles: &GpuParticles, fields: ParticleFields) -> Self {
+        let capacity = particles.capacity() as u32;
+        let usages = BufferUsages::MAP_READ | BufferUsages::COPY_DST;
+        let dynamics_words = particles.dynamics.buffer().size() as u32 / 4;
+
+        Self {
+            fields,
+            count: GpuVector::uninit(device, 1, usages),
+            positions: fields
+                .positions
+                .then(|| GpuVector::uninit(device, capacity, usages)),
+            dynamics: fields
+                .dynamics
+                .then(|| GpuVector::uninit(device, dynamics_words, usages)),
+            phases: fields
+                .phases
+                .then(|| GpuVector::uninit(device, capacity, usages)),
+            plastic_states: fields.plastic_states.then(|| PlasticStateStaging {
+                plasticity_ids: GpuVector::uninit(device, capacity, usages),
+                drucker_prager: GpuVector::uninit(device, capacity, usages),
+                snow: GpuVector::uninit(device, capacity, usages),
+                von_mises: GpuVector::uninit(device, capacity, usages),
+                nacc: GpuVector::uninit(device, capacity, usages),
+            }),
+            model_ids: fields
+                .model_ids
+                .then(|| GpuVector::uninit(device, capacity, usages)),
+        }
+    }
+
+    /// The fields downloaded by this readback.
+    pub fn fields(&self) -> ParticleFields {
+        self.fields
+    }
+
+    /// Queues the copy of the selected particle fields into the staging buffers.
+    ///
+    /// The `particles` and `models` must be the ones given to [`Self::new`], typically
+    /// [`crate::pipeline::MpmData::particles`] and [`crate::pipeline::MpmData::models`].
+    pub fn queue_readback(
+        &self,
+        encoder: &mut CommandEncoder,
+        particles: &GpuParticles,
+        models: &GpuModels,
+    ) {
+        encoder.copy_buffer_to_buffer(
+            particles.count.buffer(),
+            0,
+            self.count.buffer(),
+            0,
+            std::mem::size_of::<GpuParticleCount>() as BufferAddress,
+        );
+
+        if let Some(positions) = &self.positions {
+            positions.copy_from(encoder, &particles.positions);
+        }
+        if let Some(dynamics) = &self.dynamics {
+            encoder.copy_buffer_to_buffer(
+                particles.dynamics.buffer(),
+                0,
+                dynamics.buffer(),
+                0,
+                dynamics.buffer().size(),
+            );
+        }
+        if let Some(phases) = &self.phases {
+            phases.copy_from(encoder, &models.phases);
+        }
+        if let Some(states) = &self.plastic_states {
+            states
+                .plasticity_ids
+                .copy_from(encoder, &models.plasticity_ids);
+            states
+                .drucker_prager
+                .copy_from(encoder, &models.drucker_prager_plastic_state);
+            states.snow.copy_from(encoder, &models.snow_plastic_state);
+            states
+                .von_mises
+                .copy_from(encoder, &models.von_mises_plastic_state);
+            states.nacc.copy_from(encoder, &models.nacc_plastic_state);
+        }
+        if let Some(model_ids) = &self.model_ids {
+            model_ids.copy_from(encoder, &models.model_ids);
+        }
+    }
+
+    /// Reads back the particle state copied by the last [`Self::queue_readback`].
+    pub async fn read(&self, device: &Device) -> Result<ParticleSnapshot, Error> {
+        let count = self.count.read(device).await.map_err(Error::readback)?;
+        let len = count
+            .first()
+            .map(|count| count.len as usize)
+            .ok_or_else(|| Error::Readback("empty particle count".to_string()))?;
+        let mut snapshot = ParticleSnapshot {
+            len,
+            ..Default::default()
+        };
+
+        if let Some(positions) = &self.positions {
+            let positions = positions.read(device).await.map_err(Error::readback)?;
+            snapshot.positions = positions[..len]
+                .iter()
+                .map(|pt| pt.fixed_rows::<DIM>(0).into_owned())
+                .collect();
+        }
+
+        if let Some(dynamics) = &self.dynamics {
+            let words = dynamics.read(device).await.map_err(Error::readback)?;
+            let size = <ParticleDynamics as ShaderSize>::SHADER_SIZE.get() as usize;
+            let bytes: &[u8] = bytemuck::cast_slice(&words);
+            snapshot.dynamics = StorageBuffer::new(&bytes[..len * size])
+                .create()
+                .map_err(Error::readback)?;
+        }
+
+        if let Some(phases) = &self.phases {
+            let mut phases = phases.read(device).await.map_err(Error::readback)?;
+            phases.truncate(len);
+            snapshot.phases = phases;
+        }
+
+        if let Some(states) = &self.plastic_states {
+            let ids = states
+                .plasticity_ids
+                .read(device)
+                .await
+                .map_err(Error::readback)?;
+            let drucker_prager = states
+                .drucker_prager
+                .read(device)
+                .await
+                .map_err(Error::readback)?;
+            let snow = states.snow.read(device).await.map_err(Error::readback)?;
+            let von_mises = states
+                .von_mises
+                .read(device)
+                .await
+                .map_err(Error::readback)?;
+            let nacc = states.nacc.read(device).await.map_err(Error::readback)?;
+
+            // NOTE: must match `Plasticity::model_id`.
+            snapshot.plastic_states = (0..len)
+                .map(|i| match ids[i] {
+                    0 => PlasticState::DruckerPrager(drucker_prager[i]),
+                    1 => PlasticState::Snow(snow[i]),
+                    2 => PlasticState::VonMises(von_mises[i]),
+                    3 => PlasticState::Nacc(nacc[i]),
+                    _ => PlasticState::None,
+                })
+                .collect();
+        }
+
+        if let Some(model_ids) = &self.model_ids {
+            let mut model_ids = model_ids.read(device).await.map_err(Error::readback)?;
+            model_ids.truncate(len);
+            snapshot.model_ids = model_ids;
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::ParticleFields;
+    use crate::models::{ConstitutiveModel, PlasticState, Plasticity, Snow};
+    use crate::pipeline::{MpmData, MpmPipeline};
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::particle_block;
+    use nalgebra::{vector, Matrix3};
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+    use wgcore::gpu::GpuInstance;
+    use wgcore::kernel::KernelInvocationQueue;
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn readback_returns_live_particle_state() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+
+        let cell_width = 1.0;
+        let mut cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
+        for particle in cpu_particles.iter_mut().step_by(2) {
+            particle.plasticity = Some(Plasticity::Snow(Snow::default()));
+        }
+
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        // NOTE: the extra capacity checks that only the live particles are read back.
+        let mut data = MpmData::with_particle_capacity(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            cell_width,
+            100_000,
+            2000,
+        )
+        .unwrap();
+
+        let initial = data
+            .read_particles(gpu.device(), gpu.queue(), ParticleFields::ALL)
+            .await
+            .unwrap();
+        assert_eq!(initial.len, cpu_particles.len());
+        assert_eq!(initial.positions.len(), cpu_particles.len());
+        assert_eq!(initial.dynamics.len(), cpu_particles.len());
+        assert_eq!(initial.phases.len(), cpu_particles.len());
+        assert_eq!(
+            initial.model_ids,
+            vec![ConstitutiveModel::Corotated as u32; cpu_particles.len()]
+        );
+        for (i, particle) in cpu_particles.iter().enumerate() {
+            assert_eq!(initial.positions[i], particle.position);
+            assert_eq!(initial.dynamics[i].def_grad, Matrix3::identity());
+            assert_eq!(initial.dynamics[i].mass, particle.dynamics.mass);
+            match particle.plasticity {
+                Some(Plasticity::Snow(_)) => {
+                    assert!(matches!(initial.plastic_states[i], PlasticState::Snow(_)))
+                }
+                _ => assert!(matches!(initial.plastic_states[i], PlasticState::None)),
+            }
+        }
+
+        let mut queue = KernelInvocationQueue::new(gpu.device());
+        pipeline.queue_step(&mut data, &mut queue, false);
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        for _ in 0..10 {
+            queue.encode(&mut encoder, None);
+        }
+        gpu.queue().submit(Some(encoder.finish()));
+
+        let snapshot = data
+            .read_particles(gpu.device(), gpu.queue(), ParticleFields::POSITIONS)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.positions.len(), cpu_particles.len());
+        assert!(snapshot.dynamics.is_empty());
+        assert!(snapshot.plastic_states.is_empty());
+
+        // The particles are free-falling.
+        for (pos, particle) in snapshot.positions.iter().zip(cpu_particles.iter()) {
+            assert!(pos.y < particle.position.y, "{pos:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim2")]
+mod test {
+    use super::ParticleFields;
+    use crate::models::{ConstitutiveModel, PlasticState, Plasticity, Snow};
+    use crate::pipeline::MpmData;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::{particle_block, simulate};
+    use nalgebra::{vector, Matrix2};
+    use rapier::prelude::{ColliderSet, RigidBodySet};
+    use wgcore::gpu::GpuInstance;
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn readback_returns_live_particle_state() {
+        let gpu = GpuInstance::new().await.unwrap();
+        let mut cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0]);
+        for particle in cpu_particles.iter_mut().step_by(2) {
+            particle.plasticity = Some(Plasticity::Snow(Snow::default()));
+        }
+
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81],
+            padding: 0.0,
+            dt: (1.0 / 60.0) / 10.0,
+            domain: SimulationDomain::unbounded(),
+        };
+        // NOTE: the extra capacity checks that only the live particles are read back.
+        let data = MpmData::with_particle_capacity(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            1.0,
+            100_000,
+            200,
+        )
+        .unwrap();
+        let initial = data
+            .read_particles(gpu.device(), gpu.queue(), ParticleFields::ALL)
+            .await
+            .unwrap();
+        assert_eq!(initial.len, cpu_particles.len());
+        assert_eq!(initial.positions.len(), cpu_particles.len());
+        assert_eq!(
+            initial.model_ids,
+            vec![ConstitutiveModel::Corotated as u32; cpu_particles.len()]
+        );
+        for (i, particle) in cpu_particles.iter().enumerate() {
+            assert_eq!(initial.positions[i], particle.position);
+            assert_eq!(initial.dynamics[i].def_grad, Matrix2::identity());
+            assert_eq!(initial.dynamics[i].mass, particle.dynamics.mass);
+            match particle.plasticity {
+                Some(Plasticity::Snow(_)) => {
+                    assert!(matches!(initial.plastic_states[i], PlasticState::Snow(_)))
+                }
+                _ => assert!(matches!(initial.plastic_states[i], PlasticState::None)),
+            }
+        }
+
+        // The particles are free-falling.
+        let snapshot = simulate(
+            &cpu_particles,
+            params,
+            &RigidBodySet::default(),
+            &ColliderSet::default(),
+            10,
+        )
+        .await;
+        assert_eq!(snapshot.len, cpu_particles.len());
+        for (pos, particle) in snapshot.positions.iter().zip(cpu_particles.iter()) {
+            assert!(pos.y < particle.position.y, "{pos:?}");
+            approx::assert_relative_eq!(pos.x, particle.position.x, epsilon = 1.0e-4);
+        }
+    }
+}
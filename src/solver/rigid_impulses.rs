@@ -1,14 +1,17 @@
 use crate::dim_shader_defs;
+use crate::error::Error;
 use crate::grid::grid::{GpuGrid, WgGrid};
 use crate::solver::params::{GpuSimulationParams, WgParams};
 use encase::ShaderType;
-use rapier::math::{AngVector, Point, Vector};
+use nalgebra::Vector4;
+use rapier::math::{AngVector, Point, Vector, DIM};
 use wgcore::kernel::{KernelInvocationBuilder, KernelInvocationQueue};
 use wgcore::tensor::GpuVector;
 use wgcore::Shader;
 use wgebra::{WgSim2, WgSim3};
 use wgparry::substitute_aliases;
-use wgpu::{BufferUsages, ComputePipeline};
+use wgpu::{BufferUsages, CommandEncoder, ComputePipeline, Device};
+use wgrapier::dynamics::body::{BodyCoupling, BodyCouplingEntry};
 use wgrapier::dynamics::{GpuBodySet, WgBody};
 
 #[derive(Shader)]
@@ -23,38 +26,153 @@ pub struct WgRigidImpulses {
     pub update_world_mass_properties: ComputePipeline,
 }
 
+const WORKGROUP_SIZE: u32 = 64;
+
 #[derive(ShaderType, Copy, Clone, PartialEq, Debug, Default)]
 #[repr(C)]
 pub struct RigidImpulse {
     pub com: Point<f32>, // For convenience, to reduce the number of bindings
     pub linear: Vector<f32>,
     pub angular: AngVector<f32>,
+    /// 1 if the impulses from the particles are applied to the body (two-ways coupling), or 0
+    /// if they are not accumulated (one-way coupling).
+    pub two_ways: u32,
+}
+
+/// The impulse accumulated on a body since the last call to
+/// [`GpuImpulses::clear_total_impulses`], as laid out on the gpu.
+///
+/// The angular impulse is stored in the first component of `angular` in 2D.
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Debug, Default)]
+#[repr(C)]
+pub struct GpuTotalImpulse {
+    pub linear: Vector4<f32>,
+    pub angular: Vector4<f32>,
+}
+
+/// The total impulse applied by the particles on a coupled body.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct BodyImpulse {
+    /// The linear impulse, applied at the center of mass of the body.
+    pub linear: Vector<f32>,
+    /// The angular impulse, relative to the center of mass of the body.
+    pub angular: AngVector<f32>,
+}
+
+impl BodyImpulse {
+    /// The average force applied during the `duration` over which the impulse was accumulated.
+    pub fn average_force(&self, duration: f32) -> Vector<f32> {
+        self.linear / duration
+    }
+
+    /// The average torque applied during the `duration` over which the impulse was accumulated.
+    pub fn average_torque(&self, duration: f32) -> AngVector<f32> {
+        self.angular / duration
+    }
+}
+
+impl From<GpuTotalImpulse> for BodyImpulse {
+    fn from(impulse: GpuTotalImpulse) -> Self {
+        Self {
+            linear: impulse.linear.fixed_rows::<DIM>(0).into_owned(),
+            #[cfg(feature = "dim2")]
+            angular: impulse.angular.x,
+            #[cfg(feature = "dim3")]
+            angular: impulse.angular.xyz(),
+        }
+    }
 }
 
 pub struct GpuImpulses {
     pub incremental_impulses: GpuVector<RigidImpulse>,
-    pub total_impulses: GpuVector<RigidImpulse>,
-    pub total_impulses_staging: GpuVector<RigidImpulse>,
+    /// The impulses applied by the particles on each body (zero for one-way bodies), summed
+    /// over every substep since the last [`Self::clear_total_impulses`].
+    pub total_impulses: GpuVector<GpuTotalImpulse>,
+    pub total_impulses_staging: GpuVector<GpuTotalImpulse>,
+    num_bodies: usize,
 }
 
 impl GpuImpulses {
-    pub fn new(device: &wgpu::Device) -> Self {
-        const MAX_BODY_COUNT: usize = 16; // CPIC doesnt support more.
-        let impulses = [RigidImpulse::default(); MAX_BODY_COUNT];
+    /// Impulses for `num_bodies` bodies, all with two-ways coupling.
+    pub fn new(device: &wgpu::Device, num_bodies: u32) -> Self {
+        Self::with_two_ways_flags(device, vec![1; num_bodies as usize])
+    }
+
+    /// Impulses for the bodies of the given coupling entries.
+    ///
+    /// The impulses applied by the particles on bodies with [`BodyCoupling::OneWay`] are not
+    /// accumulated so they are never pushed back by the particles.
+    pub fn from_coupling(device: &wgpu::Device, coupling: &[BodyCouplingEntry]) -> Self {
+        let two_ways = coupling
+            .iter()
+            .map(|entry| matches!(entry.mode, BodyCoupling::TwoWays) as u32)
+            .collect();
+        Self::with_two_ways_flags(device, two_ways)
+    }
+
+    fn with_two_ways_flags(device: &wgpu::Device, two_ways: Vec<u32>) -> Self {
+        // NOTE: allocate at least one element since empty buffers can’t be bound.
+        let num_bodies = two_ways.len();
+        let mut impulses: Vec<_> = two_ways
+            .into_iter()
+            .map(|two_ways| RigidImpulse {
+                two_ways,
+                ..Default::default()
+            })
+            .collect();
+        if impulses.is_empty() {
+            impulses.push(RigidImpulse::default());
+        }
+        let total_impulses = vec![GpuTotalImpulse::default(); num_bodies.max(1)];
+
         Self {
-            incremental_impulses: GpuVector::encase(device, impulses, BufferUsages::STORAGE),
-            total_impulses: GpuVector::encase(
+            incremental_impulses: GpuVector::encase(device, &impulses, BufferUsages::STORAGE),
+            total_impulses: GpuVector::init(
                 device,
-                impulses,
-                BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                &total_impulses,
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
             ),
-            total_impulses_staging: GpuVector::encase(
+            total_impulses_staging: GpuVector::init(
                 device,
-                impulses,
+                &total_impulses,
                 BufferUsages::MAP_READ | BufferUsages::COPY_DST,
             ),
+            num_bodies,
         }
     }
+
+    /// Resets the total impulses to zero.
+    ///
+    /// This is typically encoded before the substeps of a simulation step so that
+    /// [`Self::read_total_impulses`] returns the impulses applied during that step.
+    pub fn clear_total_impulses(&self, encoder: &mut CommandEncoder) {
+        encoder.clear_buffer(self.total_impulses.buffer(), 0, None);
+    }
+
+    /// Queues the copy of the total impulses into [`Self::total_impulses_staging`].
+    ///
+    /// This must be encoded after the simulation step for [`Self::read_total_impulses`]
+    /// to return the impulses applied during that step.
+    pub fn queue_total_impulses_readback(&self, encoder: &mut CommandEncoder) {
+        self.total_impulses_staging
+            .copy_from(encoder, &self.total_impulses);
+    }
+
+    /// Reads back the total impulses copied by the last [`Self::queue_total_impulses_readback`].
+    ///
+    /// The impulses are in the same order as the coupling entries of the
+    /// [`crate::pipeline::MpmData`].
+    pub async fn read_total_impulses(&self, device: &Device) -> Result<Vec<BodyImpulse>, Error> {
+        let impulses = self
+            .total_impulses_staging
+            .read(device)
+            .await
+            .map_err(Error::readback)?;
+        Ok(impulses[..self.num_bodies]
+            .iter()
+            .map(|impulse| BodyImpulse::from(*impulse))
+            .collect())
+    }
 }
 
 impl WgRigidImpulses {
@@ -75,8 +193,9 @@ impl WgRigidImpulses {
                 bodies.mprops().buffer(),
                 bodies.poses().buffer(),
                 sim_params.params.buffer(),
+                impulses.total_impulses.buffer(),
             ])
-            .queue(1);
+            .queue(bodies.len().div_ceil(WORKGROUP_SIZE));
     }
 
     pub fn queue_update_world_mass_properties<'a>(
@@ -95,7 +214,7 @@ impl WgRigidImpulses {
                     (bodies.poses().buffer(), 5),
                 ],
             )
-            .queue(1);
+            .queue(bodies.len().div_ceil(WORKGROUP_SIZE));
     }
 }
 
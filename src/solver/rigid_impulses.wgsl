@@ -11,8 +11,8 @@
 #endif
 
 struct IntegerImpulse {
-    // HACK: we store the center of mass here to reduce the number
-    //       of bindings needed for p2g.
+    // HACK: we store the center of mass and the coupling mode here to reduce
+    //       the number of bindings needed for p2g.
 #if DIM == 2
     com: vec2<f32>,
     linear: vec2<i32>,
@@ -22,30 +22,39 @@ struct IntegerImpulse {
     linear: vec3<i32>,
     angular: vec3<i32>,
 #endif
+    // 1 if the body is affected by the particles, 0 if it only drives them (one-way coupling).
+    two_ways: u32,
 }
 
+// NOTE: must have the same layout as `IntegerImpulse`.
 struct IntegerImpulseAtomic {
-    // HACK: we store the center of mass here to reduce the number
-    //       of bindings needed for p2g.
+    // HACK: we store the center of mass and the coupling mode here to reduce
+    //       the number of bindings needed for p2g.
 #if DIM == 2
     com: vec2<f32>,
     linear_x: atomic<i32>,
     linear_y: atomic<i32>,
     angular: atomic<i32>,
-    padding: i32,
 #else
     com: vec3<f32>,
+    padding_a: i32,
     linear_x: atomic<i32>,
     linear_y: atomic<i32>,
     linear_z: atomic<i32>,
-    padding_a: i32,
+    padding_b: i32,
     angular_x: atomic<i32>,
     angular_y: atomic<i32>,
     angular_z: atomic<i32>,
-    padding_b: i32,
 #endif
+    two_ways: u32,
 }
 
+// The impulses accumulated over several substeps, for readback.
+// The angular impulse is stored in `angular.x` in 2D.
+struct TotalImpulse {
+    linear: vec4<f32>,
+    angular: vec4<f32>,
+}
 
 const FLOAT_TO_INT_FACTOR: f32 = 1e5;
 
@@ -88,23 +97,35 @@ var<storage, read_write> poses: array<Pose::Sim3>;
 #endif
 @group(0) @binding(6)
 var<uniform> params: Params::SimulationParams;
+@group(0) @binding(7)
+var<storage, read_write> total_impulses: array<TotalImpulse>;
 
-// NOTE: this is set to 16 exactly becaure we are currently limited to 16 bodies
-//       due to the CPIC affinity bitmask size.
-@compute @workgroup_size(16, 1, 1)
+@compute @workgroup_size(64, 1, 1)
 fn update(
     @builtin(global_invocation_id) gid: vec3<u32>,
 ) {
     let id = gid.x;
 
     if id < arrayLength(&vels) {
-        var inc_impulse = int_impulse_to_float(incremental_impulses[id]);
+        // NOTE: the impulse is always zero for one-way bodies since p2g doesn’t accumulate it.
+        let inc_impulse = int_impulse_to_float(incremental_impulses[id]);
+
+        // Accumulate the impulse for readback.
+#if DIM == 2
+        total_impulses[id].linear += vec4(inc_impulse.linear, 0.0, 0.0);
+        total_impulses[id].angular.x += inc_impulse.angular;
+#else
+        total_impulses[id].linear += vec4(inc_impulse.linear, 0.0);
+        total_impulses[id].angular += vec4(inc_impulse.angular, 0.0);
+#endif
 
-        // Reset the incremental impulse to zero for the next substep.
+        // Reset the incremental impulse to zero for the next substep, keeping the coupling mode.
 #if DIM == 2
-        incremental_impulses[id] = IntegerImpulse(vec2(0.0), vec2(0i), 0i);
+        incremental_impulses[id].linear = vec2(0i);
+        incremental_impulses[id].angular = 0i;
 #else
-        incremental_impulses[id] = IntegerImpulse(vec3(0.0), vec3(0i), vec3(0i));
+        incremental_impulses[id].linear = vec3(0i);
+        incremental_impulses[id].angular = vec3(0i);
 #endif
 
         // Apply impulse and integrate
@@ -0,0 +1,708 @@
+use crate::solver::ParticleDynamics;
+#[cfg(feature = "dim2")]
+use rapier::geometry::Polyline;
+use rapier::geometry::{Aabb, Collider, Shape};
+use rapier::math::{Isometry, Point, Vector, DIM};
+use rapier::parry::query::PointQuery;
+#[cfg(feature = "dim3")]
+use rapier::parry::shape::{TriMesh, TriMeshBuilderError, TriMeshFlags};
+#[cfg(feature = "dim2")]
+use std::collections::BTreeMap;
+
+/// The number of candidates tested around each active sample of the Poisson-disk sampling.
+const POISSON_DISK_ATTEMPTS: usize = 30;
+
+/// How particles are distributed inside a sampled volume.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SamplingPattern {
+    /// One particle at the center of each cell of a regular lattice.
+    Lattice,
+    /// One particle at a random position inside each cell of a regular lattice.
+    Jittered { seed: u64 },
+    /// Particles at random positions no closer than the sampling spacing from each other.
+    ///
+    /// This avoids the grid-aligned artifacts of the lattice patterns.
+    PoissonDisk { seed: u64 },
+}
+
+/// Particle positions filling a volume.
+#[derive(Clone, Debug, Default)]
+pub struct VolumeSamples {
+    pub positions: Vec<Vector<f32>>,
+    /// The radius to give to [`ParticleDynamics::with_density`] so that the particle volumes
+    /// add up to the sampled volume.
+    pub radius: f32,
+}
+
+impl VolumeSamples {
+    /// The dynamics of each sampled particle, for a material of the given `density`.
+    pub fn dynamics(&self, density: f32) -> ParticleDynamics {
+        ParticleDynamics::with_density(self.radius, density)
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+/// Fills the region where the signed-distance function `sdf` is negative, within `aabb`,
+/// with particles separated by `spacing`.
+pub fn sample_sdf(
+    aabb: &Aabb,
+    sdf: impl Fn(&Point<f32>) -> f32,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    sample_volume(aabb, |pt| sdf(pt) < 0.0, spacing, pattern)
+}
+
+/// Fills a shape at the position `pose` with particles separated by `spacing`.
+///
+/// Shapes without an interior, like triangle meshes without the `TriMeshFlags::ORIENTED`
+/// flag, or polylines, generate no particles. In 2D, use `sample_polyline_interior` to fill
+/// the region enclosed by a polyline.
+pub fn sample_shape(
+    shape: &dyn Shape,
+    pose: &Isometry<f32>,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    let aabb = shape.compute_aabb(pose);
+    sample_volume(&aabb, |pt| shape.contains_point(pose, pt), spacing, pattern)
+}
+
+/// Fills the shape of a rapier collider with particles separated by `spacing`.
+pub fn sample_collider(
+    collider: &Collider,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    sample_shape(collider.shape(), collider.position(), spacing, pattern)
+}
+
+/// Fills a closed triangle mesh with particles separated by `spacing`.
+///
+/// The mesh must be manifold and consistently oriented, with its triangles facing outward.
+#[cfg(feature = "dim3")]
+pub fn sample_closed_mesh(
+    vertices: Vec<Point<f32>>,
+    indices: Vec<[u32; 3]>,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> Result<VolumeSamples, TriMeshBuilderError> {
+    let trimesh = TriMesh::with_flags(vertices, indices, TriMeshFlags::ORIENTED)?;
+    Ok(sample_shape(
+        &trimesh,
+        &Isometry::identity(),
+        spacing,
+        pattern,
+    ))
+}
+
+/// Fills a closed polygon with particles separated by `spacing`.
+///
+/// The last vertex is connected to the first one. Self-intersecting polygons are filled
+/// following the even-odd rule.
+#[cfg(feature = "dim2")]
+pub fn sample_polygon(
+    vertices: &[Point<f32>],
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    let mut aabb = Aabb::new_invalid();
+    for vertex in vertices {
+        aabb.take_point(*vertex);
+    }
+
+    let edges = || {
+        vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    };
+    sample_volume(&aabb, |pt| is_enclosed(edges(), pt), spacing, pattern)
+}
+
+/// Fills the region enclosed by a polyline at the position `pose` with particles separated
+/// by `spacing`.
+///
+/// The polyline must be made of one or more closed loops, like the outline of a 2D terrain.
+/// Nested loops carve holes, following the even-odd rule.
+#[cfg(feature = "dim2")]
+pub fn sample_polyline_interior(
+    polyline: &Polyline,
+    pose: &Isometry<f32>,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    let aabb = polyline.compute_aabb(pose);
+    let edges = || polyline.segments().map(|seg| (seg.a, seg.b));
+    sample_volume(
+        &aabb,
+        |pt| is_enclosed(edges(), &pose.inverse_transform_point(pt)),
+        spacing,
+        pattern,
+    )
+}
+
+/// A bitmap image whose opaque pixels are filled with particles by [`sample_bitmap`].
+#[cfg(feature = "dim2")]
+#[derive(Copy, Clone, Debug)]
+pub struct BitmapMask<'a> {
+    pub width: usize,
+    pub height: usize,
+    /// The RGBA pixels of the image, row by row, starting with the top row.
+    pub pixels: &'a [[u8; 4]],
+    /// The world-space position of the bottom-left corner of the image.
+    pub origin: Point<f32>,
+    /// The world-space width of a pixel.
+    pub pixel_size: f32,
+    /// Pixels with an alpha lower than this threshold are transparent and generate no
+    /// particles.
+    pub alpha_threshold: u8,
+}
+
+#[cfg(feature = "dim2")]
+impl<'a> BitmapMask<'a> {
+    /// A mask with its bottom-left corner at the origin and one world unit per pixel.
+    pub fn new(width: usize, height: usize, pixels: &'a [[u8; 4]]) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "The number of pixels doesn’t match the image size."
+        );
+        Self {
+            width,
+            height,
+            pixels,
+            origin: Point::origin(),
+            pixel_size: 1.0,
+            alpha_threshold: 128,
+        }
+    }
+
+    /// The world-space AABB covered by the image.
+    pub fn aabb(&self) -> Aabb {
+        let extents = Vector::new(self.width as f32, self.height as f32) * self.pixel_size;
+        Aabb::new(self.origin, self.origin + extents)
+    }
+
+    /// The color of the opaque pixel containing `pt`, if any.
+    pub fn opaque_pixel_at(&self, pt: &Point<f32>) -> Option<[u8; 4]> {
+        let local = (pt - self.origin) / self.pixel_size;
+        let (i, j) = (local.x.floor(), local.y.floor());
+        if i < 0.0 || j < 0.0 || i as usize >= self.width || j as usize >= self.height {
+            return None;
+        }
+
+        // NOTE: the image rows go downward while the world y axis goes upward.
+        let row = self.height - 1 - j as usize;
+        let pixel = self.pixels[row * self.width + i as usize];
+        (pixel[3] >= self.alpha_threshold).then_some(pixel)
+    }
+}
+
+/// Fills the opaque pixels of a bitmap with particles separated by `spacing`.
+///
+/// The `material` closure is called once for every distinct color of the opaque pixels and
+/// returns the material of the particles generated in these pixels, or `None` if they must
+/// stay empty. One group of samples is returned for each color with a material.
+///
+/// Poisson-disk sampling only enforces the spacing between particles of the same color.
+#[cfg(feature = "dim2")]
+pub fn sample_bitmap<M>(
+    mask: &BitmapMask,
+    spacing: f32,
+    pattern: SamplingPattern,
+    mut material: impl FnMut([u8; 4]) -> Option<M>,
+) -> Vec<(M, VolumeSamples)> {
+    let mut colors: BTreeMap<[u8; 4], Aabb> = BTreeMap::new();
+
+    for (id, pixel) in mask.pixels.iter().enumerate() {
+        if pixel[3] < mask.alpha_threshold {
+            continue;
+        }
+
+        let (i, row) = (id % mask.width, id / mask.width);
+        let mins =
+            mask.origin + Vector::new(i as f32, (mask.height - 1 - row) as f32) * mask.pixel_size;
+        let pixel_aabb = Aabb::new(mins, mins + Vector::repeat(mask.pixel_size));
+
+        colors
+            .entry(*pixel)
+            .and_modify(|aabb| aabb.merge(&pixel_aabb))
+            .or_insert(pixel_aabb);
+    }
+
+    colors
+        .into_iter()
+        .filter_map(|(color, aabb)| {
+            let material = material(color)?;
+            let samples = sample_volume(
+                &aabb,
+                |pt| mask.opaque_pixel_at(pt) == Some(color),
+                spacing,
+                pattern,
+            );
+            Some((material, samples))
+        })
+        .collect()
+}
+
+/// Tests if `pt` is enclosed by the given edges, following the even-odd rule.
+#[cfg(feature = "dim2")]
+fn is_enclosed(edges: impl Iterator<Item = (Point<f32>, Point<f32>)>, pt: &Point<f32>) -> bool {
+    // Count the crossings of the edges with a horizontal ray going from `pt` to +x.
+    edges
+        .filter(|(a, b)| {
+            (a.y > pt.y) != (b.y > pt.y) && pt.x < a.x + (pt.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+fn sample_volume(
+    aabb: &Aabb,
+    is_inside: impl Fn(&Point<f32>) -> bool,
+    spacing: f32,
+    pattern: SamplingPattern,
+) -> VolumeSamples {
+    assert!(spacing > 0.0, "The sampling spacing must be positive.");
+    let lattice = Lattice::new(aabb, spacing);
+
+    match pattern {
+        SamplingPattern::Lattice => {
+            let positions = lattice
+                .cells()
+                .map(|cell| lattice.cell_center(&cell))
+                .filter(|pt| is_inside(pt))
+                .map(|pt| pt.coords)
+                .collect();
+            VolumeSamples {
+                positions,
+                radius: spacing / 2.0,
+            }
+        }
+        SamplingPattern::Jittered { seed } => {
+            let mut rng = Rng::new(seed);
+            let positions = lattice
+                .cells()
+                .map(|cell| {
+                    lattice.cell_mins(&cell) + Vector::from_fn(|_, _| rng.next_f32() * spacing)
+                })
+                .filter(|pt| is_inside(pt))
+                .map(|pt| pt.coords)
+                .collect();
+            VolumeSamples {
+                positions,
+                radius: spacing / 2.0,
+            }
+        }
+        SamplingPattern::PoissonDisk { seed } => {
+            sample_poisson_disk(&lattice, &is_inside, &mut Rng::new(seed))
+        }
+    }
+}
+
+/// Bridson’s Poisson-disk sampling, restarted from every lattice cell center left uncovered
+/// so that disconnected parts of the volume are sampled too.
+fn sample_poisson_disk(
+    lattice: &Lattice,
+    is_inside: &impl Fn(&Point<f32>) -> bool,
+    rng: &mut Rng,
+) -> VolumeSamples {
+    let min_dist = lattice.spacing;
+    // With cells of width `min_dist / sqrt(DIM)`, each background cell contains at most one
+    // sample.
+    let background = Lattice::new(&lattice.aabb, min_dist / (DIM as f32).sqrt());
+    let mut cells = vec![u32::MAX; background.num_cells()];
+    let mut samples: Vec<Point<f32>> = vec![];
+    let mut active = vec![];
+    let mut num_inside_cells = 0;
+
+    let insert = |pt: Point<f32>,
+                  samples: &mut Vec<Point<f32>>,
+                  cells: &mut [u32],
+                  active: &mut Vec<usize>| {
+        let cell = background.cell_containing(&pt).unwrap();
+        cells[background.linear_index(&cell)] = samples.len() as u32;
+        active.push(samples.len());
+        samples.push(pt);
+    };
+    let is_far_enough = |samples: &[Point<f32>], cells: &[u32], pt: &Point<f32>| {
+        let Some(cell) = background.cell_containing(pt) else {
+            return false;
+        };
+        // Samples closer than `min_dist` are at most 2 background cells away along each axis.
+        background.neighbors(&cell, 2).all(|neighbor| {
+            let id = cells[background.linear_index(&neighbor)];
+            id == u32::MAX || nalgebra::distance(&samples[id as usize], pt) >= min_dist
+        })
+    };
+
+    for seed_cell in lattice.cells() {
+        let seed = lattice.cell_center(&seed_cell);
+        if !is_inside(&seed) {
+            continue;
+        }
+        num_inside_cells += 1;
+
+        if !is_far_enough(&samples, &cells, &seed) {
+            continue;
+        }
+
+        insert(seed, &mut samples, &mut cells, &mut active);
+
+        while !active.is_empty() {
+            let active_id = (rng.next_u64() % active.len() as u64) as usize;
+            let center = samples[active[active_id]];
+            let mut found = false;
+
+            for _ in 0..POISSON_DISK_ATTEMPTS {
+                // A random point in the annulus between `min_dist` and `2 * min_dist`.
+                let dir = rng.next_direction();
+                let dist = min_dist * (1.0 + rng.next_f32());
+                let candidate = center + dir * dist;
+
+                if lattice.aabb.contains_local_point(&candidate)
+                    && is_inside(&candidate)
+                    && is_far_enough(&samples, &cells, &candidate)
+                {
+                    insert(candidate, &mut samples, &mut cells, &mut active);
+                    found = true;
+                    break;
+                }
+            }
+
+            if !found {
+                active.swap_remove(active_id);
+            }
+        }
+    }
+
+    // NOTE: the volume is estimated from the number of lattice cells inside of it.
+    let volume = num_inside_cells as f32 * min_dist.powi(DIM as i32);
+    let radius = if samples.is_empty() {
+        min_dist / 2.0
+    } else {
+        (volume / samples.len() as f32).powf(1.0 / DIM as f32) / 2.0
+    };
+
+    VolumeSamples {
+        positions: samples.into_iter().map(|pt| pt.coords).collect(),
+        radius,
+    }
+}
+
+/// A regular grid of cells covering an AABB.
+struct Lattice {
+    aabb: Aabb,
+    spacing: f32,
+    num_cells: [usize; DIM],
+}
+
+impl Lattice {
+    fn new(aabb: &Aabb, spacing: f32) -> Self {
+        let extents = aabb.extents();
+        let num_cells = std::array::from_fn(|k| (extents[k] / spacing).ceil().max(1.0) as usize);
+        Self {
+            aabb: *aabb,
+            spacing,
+            num_cells,
+        }
+    }
+
+    fn num_cells(&self) -> usize {
+        self.num_cells.iter().product()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = [usize; DIM]> + '_ {
+        (0..self.num_cells()).map(|mut id| {
+            std::array::from_fn(|k| {
+                let i = id % self.num_cells[k];
+                id /= self.num_cells[k];
+                i
+            })
+        })
+    }
+
+    fn linear_index(&self, cell: &[usize; DIM]) -> usize {
+        (0..DIM)
+            .rev()
+            .fold(0, |acc, k| acc * self.num_cells[k] + cell[k])
+    }
+
+    fn cell_mins(&self, cell: &[usize; DIM]) -> Point<f32> {
+        self.aabb.mins + Vector::from_fn(|k, _| cell[k] as f32 * self.spacing)
+    }
+
+    fn cell_center(&self, cell: &[usize; DIM]) -> Point<f32> {
+        self.cell_mins(cell) + Vector::repeat(self.spacing / 2.0)
+    }
+
+    fn cell_containing(&self, pt: &Point<f32>) -> Option<[usize; DIM]> {
+        let mut cell = [0; DIM];
+        for (k, i) in cell.iter_mut().enumerate() {
+            let x = ((pt[k] - self.aabb.mins[k]) / self.spacing).floor();
+            if x < 0.0 || x as usize >= self.num_cells[k] {
+                return None;
+            }
+            *i = x as usize;
+        }
+        Some(cell)
+    }
+
+    /// The cells at most `range` cells away from `cell` along each axis.
+    fn neighbors(
+        &self,
+        cell: &[usize; DIM],
+        range: usize,
+    ) -> impl Iterator<Item = [usize; DIM]> + '_ {
+        let mins: [usize; DIM] = std::array::from_fn(|k| cell[k].saturating_sub(range));
+        let maxs: [usize; DIM] =
+            std::array::from_fn(|k| (cell[k] + range).min(self.num_cells[k] - 1));
+        let widths: [usize; DIM] = std::array::from_fn(|k| maxs[k] - mins[k] + 1);
+        (0..widths.iter().product()).map(move |mut id| {
+            std::array::from_fn(|k| {
+                let i = mins[k] + id % widths[k];
+                id /= widths[k];
+                i
+            })
+        })
+    }
+}
+
+/// A small deterministic random number generator (SplitMix64), so that sampling with the
+/// same seed always generates the same particles.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform random number in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A uniform random unit vector.
+    fn next_direction(&mut self) -> Vector<f32> {
+        loop {
+            let v = Vector::from_fn(|_, _| self.next_f32() * 2.0 - 1.0);
+            let norm = v.norm();
+            if norm > 1.0e-3 && norm <= 1.0 {
+                return v / norm;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::{sample_closed_mesh, sample_collider, sample_sdf, SamplingPattern};
+    use nalgebra::{point, vector};
+    use rapier::geometry::{Aabb, ColliderBuilder, Cuboid};
+
+    #[test]
+    fn lattice_sampling_fills_a_ball() {
+        let collider = ColliderBuilder::ball(1.0)
+            .translation(vector![1.0, 2.0, 3.0])
+            .build();
+        let samples = sample_collider(&collider, 0.1, SamplingPattern::Lattice);
+
+        let expected_volume = 4.0 / 3.0 * std::f32::consts::PI;
+        let volume = samples.len() as f32 * (samples.radius * 2.0).powi(3);
+        assert!((volume - expected_volume).abs() < expected_volume * 0.02);
+        for pt in &samples.positions {
+            assert!((pt - vector![1.0, 2.0, 3.0]).norm() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn jittered_sampling_is_deterministic() {
+        let aabb = Aabb::new(point![-1.0, -1.0, -1.0], point![1.0, 1.0, 1.0]);
+        let sdf = |pt: &nalgebra::Point3<f32>| pt.coords.norm() - 1.0;
+        let pattern = SamplingPattern::Jittered { seed: 42 };
+        let a = sample_sdf(&aabb, sdf, 0.2, pattern);
+        let b = sample_sdf(&aabb, sdf, 0.2, pattern);
+        assert_eq!(a.positions, b.positions);
+        assert!(a.positions.iter().all(|pt| pt.norm() < 1.0));
+    }
+
+    #[test]
+    fn poisson_disk_sampling_respects_the_spacing() {
+        let aabb = Aabb::new(point![-1.0, -1.0, -1.0], point![1.0, 1.0, 1.0]);
+        let sdf = |pt: &nalgebra::Point3<f32>| pt.coords.norm() - 0.5;
+        let spacing = 0.1;
+        let samples = sample_sdf(
+            &aabb,
+            sdf,
+            spacing,
+            SamplingPattern::PoissonDisk { seed: 0 },
+        );
+        assert!(!samples.is_empty());
+
+        for (i, a) in samples.positions.iter().enumerate() {
+            assert!(a.norm() < 0.5);
+            for b in &samples.positions[i + 1..] {
+                assert!((a - b).norm() >= spacing);
+            }
+        }
+
+        // The particle volumes add up to the volume of the sphere.
+        let expected_volume = 4.0 / 3.0 * std::f32::consts::PI * 0.125;
+        let volume = samples.len() as f32 * (samples.radius * 2.0).powi(3);
+        assert!((volume - expected_volume).abs() < expected_volume * 0.05);
+    }
+
+    #[test]
+    fn closed_mesh_sampling_fills_a_cube() {
+        let (vertices, indices) = Cuboid::new(vector![1.0, 1.0, 1.0]).to_trimesh();
+        let samples =
+            sample_closed_mesh(vertices, indices, 0.25, SamplingPattern::Lattice).unwrap();
+        assert_eq!(samples.len(), 8 * 8 * 8);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim2")]
+mod test {
+    use super::{
+        sample_bitmap, sample_polygon, sample_polyline_interior, BitmapMask, SamplingPattern,
+        VolumeSamples,
+    };
+    use nalgebra::{point, Isometry2};
+    use rapier::geometry::Polyline;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    fn area(samples: &VolumeSamples) -> f32 {
+        samples.len() as f32 * (samples.radius * 2.0).powi(2)
+    }
+
+    #[test]
+    fn polygon_sampling_fills_a_square() {
+        let square = [
+            point![0.0, 0.0],
+            point![2.0, 0.0],
+            point![2.0, 2.0],
+            point![0.0, 2.0],
+        ];
+        let samples = sample_polygon(&square, 0.25, SamplingPattern::Lattice);
+        assert_eq!(samples.len(), 8 * 8);
+        assert_eq!(area(&samples), 4.0);
+
+        let samples = sample_polygon(&square, 0.05, SamplingPattern::PoissonDisk { seed: 0 });
+        assert!((area(&samples) - 4.0).abs() < 4.0 * 0.05);
+        assert!(samples
+            .positions
+            .iter()
+            .all(|pt| (0.0..=2.0).contains(&pt.x) && (0.0..=2.0).contains(&pt.y)));
+    }
+
+    #[test]
+    fn polyline_sampling_carves_nested_loops() {
+        let vertices = vec![
+            point![0.0, 0.0],
+            point![4.0, 0.0],
+            point![4.0, 4.0],
+            point![0.0, 4.0],
+            point![1.0, 1.0],
+            point![3.0, 1.0],
+            point![3.0, 3.0],
+            point![1.0, 3.0],
+        ];
+        let indices = vec![
+            [0, 1],
+            [1, 2],
+            [2, 3],
+            [3, 0],
+            [4, 5],
+            [5, 6],
+            [6, 7],
+            [7, 4],
+        ];
+        let polyline = Polyline::new(vertices, Some(indices));
+        let samples = sample_polyline_interior(
+            &polyline,
+            &Isometry2::identity(),
+            0.25,
+            SamplingPattern::Lattice,
+        );
+
+        assert_eq!(area(&samples), 16.0 - 4.0);
+        assert!(samples
+            .positions
+            .iter()
+            .all(|pt| !((1.0..=3.0).contains(&pt.x) && (1.0..=3.0).contains(&pt.y))));
+    }
+
+    #[test]
+    fn bitmap_sampling_maps_the_top_row_to_the_highest_y() {
+        // Only the top row of this 2x2 image is opaque.
+        let pixels = [RED, RED, CLEAR, CLEAR];
+        let mask = BitmapMask::new(2, 2, &pixels);
+        let groups = sample_bitmap(&mask, 0.25, SamplingPattern::Lattice, Some);
+
+        assert_eq!(groups.len(), 1);
+        let (color, samples) = &groups[0];
+        assert_eq!(*color, RED);
+        assert_eq!(samples.len(), 2 * 16);
+        assert!(samples.positions.iter().all(|pt| pt.y > 1.0 && pt.y < 2.0));
+    }
+
+    #[test]
+    fn bitmap_sampling_skips_pixels_below_the_alpha_threshold() {
+        let translucent = [255, 0, 0, 100];
+        let pixels = [translucent];
+        let mut mask = BitmapMask::new(1, 1, &pixels);
+        assert!(sample_bitmap(&mask, 0.25, SamplingPattern::Lattice, Some).is_empty());
+
+        mask.alpha_threshold = 100;
+        let groups = sample_bitmap(&mask, 0.25, SamplingPattern::Lattice, Some);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].1.len(), 16);
+    }
+
+    #[test]
+    fn bitmap_sampling_groups_pixels_by_color() {
+        let pixels = [RED, BLUE, RED, CLEAR];
+        let mask = BitmapMask::new(2, 2, &pixels);
+
+        let groups = sample_bitmap(&mask, 0.25, SamplingPattern::Lattice, Some);
+        assert_eq!(groups.len(), 2);
+        for (color, samples) in &groups {
+            let expected_pixels = pixels.iter().filter(|pixel| *pixel == color).count();
+            assert_eq!(samples.len(), expected_pixels * 16);
+            assert!(samples
+                .positions
+                .iter()
+                .all(|pt| mask.opaque_pixel_at(&(*pt).into()) == Some(*color)));
+        }
+
+        // Colors without a material generate no particles.
+        let groups = sample_bitmap(&mask, 0.25, SamplingPattern::Lattice, |color| {
+            (color == BLUE).then_some("blue")
+        });
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, "blue");
+        assert_eq!(groups[0].1.len(), 16);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::models::ElasticCoefficients;
+use crate::pipeline::{MpmData, MpmPipeline};
+use crate::solver::{
+    Particle, ParticleDynamics, ParticleFields, ParticleSnapshot, SimulationParams,
+};
+use rapier::math::{Vector, DIM};
+use rapier::prelude::{ColliderSet, RigidBodySet};
+use wgcore::gpu::GpuInstance;
+use wgcore::kernel::KernelInvocationQueue;
+
+/// A block of `n` particles along each axis, separated by `spacing`, starting at `origin`.
+///
+/// The particles are elastic, with a radius of `spacing / 2` and a unit density. They are
+/// ordered with the last axis varying the fastest.
+pub fn particle_block(n: usize, spacing: f32, origin: Vector<f32>) -> Vec<Particle> {
+    (0..n.pow(DIM as u32))
+        .map(|id| {
+            let cell = Vector::from_fn(|k, _| (id / n.pow((DIM - 1 - k) as u32) % n) as f32);
+            Particle::new(
+                origin + cell * spacing,
+                ParticleDynamics::with_density(spacing / 2.0, 1.0),
+                ElasticCoefficients::from_young_modulus(100_000.0, 0.33),
+            )
+        })
+        .collect()
+}
+
+/// Runs `num_steps` simulation steps on a unit-width grid, then reads back every field of
+/// the particles.
+pub async fn simulate(
+    particles: &[Particle],
+    params: SimulationParams,
+    bodies: &RigidBodySet,
+    colliders: &ColliderSet,
+    num_steps: usize,
+) -> ParticleSnapshot {
+    let gpu = GpuInstance::new().await.unwrap();
+    let pipeline = MpmPipeline::new(gpu.device()).unwrap();
+    let mut data = MpmData::new(
+        gpu.device(),
+        params,
+        particles,
+        bodies,
+        colliders,
+        1.0,
+        100_000,
+    )
+    .unwrap();
+    let mut queue = KernelInvocationQueue::new(gpu.device());
+    pipeline.queue_step(&mut data, &mut queue, false);
+
+    for _ in 0..num_steps {
+        let mut encoder = gpu.device().create_command_encoder(&Default::default());
+        queue.encode(&mut encoder, None);
+        gpu.queue().submit(Some(encoder.finish()));
+    }
+
+    data.read_particles(gpu.device(), gpu.queue(), ParticleFields::ALL)
+        .await
+        .unwrap()
+}
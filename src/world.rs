@@ -0,0 +1,186 @@
+use crate::error::Error;
+use crate::pipeline::{MpmData, MpmPipeline};
+use crate::solver::ParticleEmitter;
+use rapier::dynamics::{
+    CCDSolver, ImpulseJointSet, IntegrationParameters, IslandManager, MultibodyJointSet,
+    RigidBodySet,
+};
+use rapier::geometry::{ColliderSet, DefaultBroadPhase, NarrowPhase};
+use rapier::pipeline::PhysicsPipeline;
+use wgcore::kernel::KernelInvocationQueue;
+use wgpu::{Device, Queue};
+
+/// The rapier structures simulating the rigid bodies coupled with the particles.
+#[derive(Default)]
+pub struct RapierData {
+    pub bodies: RigidBodySet,
+    pub colliders: ColliderSet,
+    pub impulse_joints: ImpulseJointSet,
+    pub multibody_joints: MultibodyJointSet,
+    pub params: IntegrationParameters,
+    pub physics_pipeline: PhysicsPipeline,
+    pub narrow_phase: NarrowPhase,
+    pub broad_phase: DefaultBroadPhase,
+    pub ccd_solver: CCDSolver,
+    pub islands: IslandManager,
+}
+
+impl RapierData {
+    /// Advances the rigid-body simulation by `self.params.dt`, without gravity.
+    ///
+    /// Gravity is already applied to the coupled bodies during the MPM step.
+    pub fn step(&mut self) {
+        self.physics_pipeline.step(
+            &nalgebra::zero(),
+            &self.params,
+            &mut self.islands,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.bodies,
+            &mut self.colliders,
+            &mut self.impulse_joints,
+            &mut self.multibody_joints,
+            &mut self.ccd_solver,
+            None,
+            &(),
+            &(),
+        );
+    }
+}
+
+/// A particle simulation two-ways coupled with a rapier simulation.
+///
+/// This runs the same coupled step as the testbed: the rigid bodies are uploaded to the gpu,
+/// the MPM simulation runs for [`Self::num_substeps`] substeps, the rigid bodies velocities
+/// are updated from the poses reached on the gpu, and rapier steps.
+pub struct MpmWorld {
+    pub pipeline: MpmPipeline,
+    pub data: MpmData,
+    pub rapier: RapierData,
+    /// The number of MPM substeps per rapier step.
+    ///
+    /// The `dt` of the [`crate::solver::SimulationParams`] should match
+    /// `rapier.params.dt / num_substeps`.
+    pub num_substeps: usize,
+    /// The emitters inserting new particles at the beginning of each step.
+    pub emitters: Vec<ParticleEmitter>,
+}
+
+impl MpmWorld {
+    /// Creates the world, compiling the simulation pipeline on the given `device`.
+    pub fn new(
+        device: &Device,
+        data: MpmData,
+        rapier: RapierData,
+        num_substeps: usize,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            pipeline: MpmPipeline::new(device)?,
+            data,
+            rapier,
+            num_substeps,
+            emitters: vec![],
+        })
+    }
+
+    /// Advances the coupled simulation by `self.rapier.params.dt`.
+    ///
+    /// Returns an error if the rigid bodies or the grid occupancy couldn’t be read back, or if
+    /// the grid can’t grow any further. The rapier simulation isn’t stepped in that case.
+    pub async fn step(&mut self, device: &Device, queue: &Queue) -> Result<(), Error> {
+        let dt = self.rapier.params.dt;
+        let emitted: Vec<_> = self
+            .emitters
+            .iter_mut()
+            .flat_map(|emitter| emitter.step(dt))
+            .collect();
+        self.pipeline
+            .emit_particles(device, queue, &self.data, &emitted);
+        self.data.upload_rigid_bodies(
+            queue,
+            &self.rapier.bodies,
+            &self.rapier.colliders,
+            dt,
+            self.num_substeps,
+        );
+
+        {
+            let mut invocations = KernelInvocationQueue::new(device);
+            self.pipeline
+                .queue_step(&mut self.data, &mut invocations, false);
+
+            let mut encoder = device.create_command_encoder(&Default::default());
+            for _ in 0..self.num_substeps {
+                invocations.encode(&mut encoder, None);
+            }
+            self.data.queue_step_readback(&mut encoder);
+            queue.submit(Some(encoder.finish()));
+        }
+
+        self.data
+            .apply_rigid_body_feedback(device, &mut self.rapier.bodies, dt)
+            .await?;
+        // Make room for the particles that spread out during this step.
+        self.data.grow_grid_if_needed(device).await?;
+
+        self.rapier.step();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "dim3")]
+mod test {
+    use super::{MpmWorld, RapierData};
+    use crate::pipeline::MpmData;
+    use crate::solver::{SimulationDomain, SimulationParams};
+    use crate::test_utils::particle_block;
+    use nalgebra::vector;
+    use rapier::prelude::{ColliderBuilder, RigidBodyBuilder};
+    use wgcore::gpu::GpuInstance;
+
+    #[futures_test::test]
+    #[serial_test::serial]
+    async fn mpm_world_step_drives_rapier_bodies() {
+        let gpu = GpuInstance::new().await.unwrap();
+
+        let cell_width = 1.0;
+        let cpu_particles = particle_block(10, 0.5, vector![0.0, 0.0, 0.0]);
+
+        // A dynamic ball far above the particles, falling freely.
+        let mut rapier = RapierData::default();
+        let start = vector![2.5, 20.0, 2.5];
+        let ball = rapier
+            .bodies
+            .insert(RigidBodyBuilder::dynamic().translation(start));
+        rapier
+            .colliders
+            .insert_with_parent(ColliderBuilder::ball(1.0), ball, &mut rapier.bodies);
+
+        let num_substeps = 10;
+        let params = SimulationParams {
+            gravity: vector![0.0, -9.81, 0.0],
+            dt: rapier.params.dt / num_substeps as f32,
+            domain: SimulationDomain::unbounded(),
+        };
+        let data = MpmData::new(
+            gpu.device(),
+            params,
+            &cpu_particles,
+            &rapier.bodies,
+            &rapier.colliders,
+            cell_width,
+            100_000,
+        )
+        .unwrap();
+        let mut world = MpmWorld::new(gpu.device(), data, rapier, num_substeps).unwrap();
+
+        for _ in 0..10 {
+            world.step(gpu.device(), gpu.queue()).await.unwrap();
+        }
+
+        let ball = &world.rapier.bodies[ball];
+        assert!(ball.linvel().y < 0.0, "{:?}", ball.linvel());
+        assert!(ball.translation().y < start.y, "{:?}", ball.translation());
+    }
+}
@@ -35,12 +35,8 @@ use instancing::INSTANCING_SHADER_HANDLE;
 use prep_vertex_buffer::{GpuRenderConfig, RenderConfig, WgPrepVertexBuffer};
 use wgcore::hot_reloading::HotReloadState;
 use wgcore::timestamps::GpuTimestamps;
-use wgsparkl::rapier::dynamics::{CCDSolver, IntegrationParameters, RigidBodySet};
-use wgsparkl::rapier::geometry::{ColliderSet, NarrowPhase};
-use wgsparkl::rapier::prelude::{
-    DefaultBroadPhase, ImpulseJointSet, IslandManager, MultibodyJointSet, PhysicsPipeline,
-    ShapeType,
-};
+use wgsparkl::rapier::prelude::ShapeType;
+pub use wgsparkl::world::RapierData;
 use wgsparkl::{
     pipeline::{MpmData, MpmPipeline},
     solver::Particle,
@@ -102,20 +98,6 @@ pub struct AppState {
     pub show_rigid_particles: bool,
 }
 
-#[derive(Default)]
-pub struct RapierData {
-    pub bodies: RigidBodySet,
-    pub colliders: ColliderSet,
-    pub impulse_joints: ImpulseJointSet,
-    pub multibody_joints: MultibodyJointSet,
-    pub params: IntegrationParameters,
-    pub physics_pipeline: PhysicsPipeline,
-    pub narrow_phase: NarrowPhase,
-    pub broad_phase: DefaultBroadPhase,
-    pub ccd_solver: CCDSolver,
-    pub islands: IslandManager,
-}
-
 #[derive(Resource)]
 pub struct PhysicsContext {
     pub data: MpmData,
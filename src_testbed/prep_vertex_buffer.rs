@@ -15,6 +15,7 @@ pub enum RenderMode {
     CdfNormals = 3,
     CdfDistances = 4,
     CdfSigns = 5,
+    Temperature = 6,
 }
 
 impl RenderMode {
@@ -26,6 +27,7 @@ impl RenderMode {
             Self::CdfNormals => "cdf (normals)",
             Self::CdfDistances => "cdf (distances)",
             Self::CdfSigns => "cdf (signs)",
+            Self::Temperature => "temperature",
         }
     }
 
@@ -37,6 +39,7 @@ impl RenderMode {
             3 => Self::CdfNormals,
             4 => Self::CdfDistances,
             5 => Self::CdfSigns,
+            6 => Self::Temperature,
             _ => unreachable!(),
         }
     }
@@ -99,6 +102,7 @@ impl WgPrepVertexBuffer {
                 grid.meta.buffer(),
                 params.params.buffer(),
                 config.buffer.buffer(),
+                particles.count.buffer(),
             ])
             .queue(particles.positions.len().div_ceil(64) as u32);
 
@@ -17,6 +17,8 @@ var<storage, read_write> grid: Grid::Grid;
 var<uniform> params: Params::SimulationParams;
 @group(0) @binding(5)
 var<storage, read> config: RenderConfig;
+@group(0) @binding(6)
+var<storage, read> particles_count: Particle::Count;
 
 struct RenderConfig {
     mode: u32,
@@ -28,6 +30,10 @@ const VELOCITY: u32 = 2;
 const CDF_NORMALS: u32 = 3;
 const CDF_DISTANCES: u32 = 4;
 const CDF_SIGNS: u32 = 5;
+const TEMPERATURE: u32 = 6;
+
+// Temperatures are displayed from blue (at 0) to red (at MAX_DISPLAYED_TEMPERATURE).
+const MAX_DISPLAYED_TEMPERATURE: f32 = 1000.0;
 
 
 struct InstanceData {
@@ -44,6 +50,12 @@ fn main(
     let particle_id = tid.x;
 
     if particle_id < arrayLength(&instances) {
+        // Hide the particle slots that are not alive.
+        if particle_id >= particles_count.len {
+            instances[particle_id].deformation = mat3x3(vec3(0.0), vec3(0.0), vec3(0.0));
+            return;
+        }
+
         let def_grad = particles_dyn[particle_id].def_grad;
         instances[particle_id].deformation = def_grad;
         instances[particle_id].position = particles_pos[particle_id].pt;
@@ -79,15 +91,19 @@ fn main(
             }
         } else if config.mode == CDF_SIGNS {
              let d = particles_dyn[particle_id].cdf.affinity;
-             let a = (d >> 16) & (d & 0x0000ffff);
-             if d == 0 {
+             // Sign bits of each packed affinity (see `Grid::NodeCdf`).
+             let a = (d.x | d.y) & 0x80008000u;
+             if all(d == vec2(0u)) {
                  instances[particle_id].color = vec4(0.0, 0.0, 0.0, color.w);
              } else if a == 0 {
                  instances[particle_id].color = vec4(0.0, 1.0, 0.0, color.w);
              } else {
                  instances[particle_id].color = vec4(1.0, 0.0, 0.0, color.w);
              }
-         }
+        } else if config.mode == TEMPERATURE {
+            let t = clamp(particles_dyn[particle_id].temperature / MAX_DISPLAYED_TEMPERATURE, 0.0, 1.0);
+            instances[particle_id].color = vec4(t, 0.2, 1.0 - t, color.w);
+        }
     }
 }
 
@@ -32,7 +32,9 @@ pub fn setup_app(mut commands: Commands, device: Res<RenderDevice>) {
 
     let mut hot_reload = HotReloadState::new().unwrap();
     let pipeline = MpmPipeline::new(device.wgpu_device()).unwrap();
-    pipeline.init_hot_reloading(&mut hot_reload);
+    if let Err(e) = pipeline.init_hot_reloading(&mut hot_reload) {
+        println!("Failed to initialize the MPM pipeline hot reloading: {e}");
+    }
 
     commands.insert_resource(AppState {
         render_config,
@@ -165,9 +167,20 @@ fn setup_particles_graphics(
     /*
      * Particles rendering.
      */
+    // NOTE: allocate one instance per particle slot so particles emitted later on
+    //       get rendered too. Unused slots are hidden by the vertex buffer preparation.
     let mut instances = vec![];
-    for (rb_id, particle) in physics.particles.iter().enumerate() {
+    for rb_id in 0..physics.data.particles.capacity() {
         let base_color = colors[rb_id % colors.len()].to_linear().to_f32_array();
+        let Some(particle) = physics.particles.get(rb_id) else {
+            instances.push(InstanceData {
+                deformation: [Vec4::ZERO; 3],
+                position: Vec4::ZERO,
+                base_color,
+                color: base_color,
+            });
+            continue;
+        };
         instances.push(InstanceData {
             deformation: [Vec4::X, Vec4::Y, Vec4::Z],
             #[cfg(feature = "dim2")]
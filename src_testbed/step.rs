@@ -6,12 +6,7 @@ use bevy::prelude::*;
 use bevy::render::renderer::{RenderDevice, RenderQueue};
 use bevy::tasks::ComputeTaskPool;
 use wgcore::kernel::KernelInvocationQueue;
-use wgcore::re_exports::encase::StorageBuffer;
 use wgcore::timestamps::GpuTimestamps;
-use wgsparkl::rapier::math::Vector;
-use wgsparkl::rapier::prelude::RigidBodyPosition;
-use wgsparkl::wgparry::math::GpuSim;
-use wgsparkl::wgrapier::dynamics::GpuVelocity;
 
 #[derive(Resource)]
 pub struct TimestampChannel {
@@ -75,49 +70,14 @@ pub fn step_simulation_legacy(
     let mut encoder = device.create_command_encoder(&Default::default());
 
     // Send updated bodies information to the gpu.
-    // PERF: don’t reallocate the buffers at each step.
-    let poses_data: Vec<GpuSim> = physics
-        .data
-        .coupling()
-        .iter()
-        .map(|coupling| {
-            let c = &physics.rapier_data.colliders[coupling.collider];
-            #[cfg(feature = "dim2")]
-            return (*c.position()).into();
-            #[cfg(feature = "dim3")]
-            return GpuSim::from_isometry(*c.position(), 1.0);
-        })
-        .collect();
-    // println!("poses: {:?}", poses_data);
-    compute_queue.write_buffer(
-        physics.data.bodies.poses().buffer(),
-        0,
-        bytemuck::cast_slice(&poses_data),
+    physics.data.upload_rigid_bodies(
+        compute_queue,
+        &physics.rapier_data.bodies,
+        &physics.rapier_data.colliders,
+        physics.rapier_data.params.dt,
+        app_state.num_substeps,
     );
 
-    let divisor = 1.0; // app_state.num_substeps as f32;
-    let gravity = Vector::y() * -9.81;
-    let vels_data: Vec<_> = physics
-        .data
-        .coupling()
-        .iter()
-        .map(|coupling| {
-            let rb = &physics.rapier_data.bodies[coupling.body];
-            GpuVelocity {
-                linear: *rb.linvel()
-                    + gravity * physics.rapier_data.params.dt * (rb.is_dynamic() as u32 as f32)
-                        / (app_state.num_substeps as f32),
-                #[allow(clippy::clone_on_copy)] // Needed for the 2d/3d switch.
-                angular: rb.angvel().clone(),
-            }
-        })
-        .collect();
-
-    let mut vels_bytes = vec![];
-    let mut buffer = StorageBuffer::new(&mut vels_bytes);
-    buffer.write(&vels_data).unwrap();
-    compute_queue.write_buffer(physics.data.bodies.vels().buffer(), 0, &vels_bytes);
-
     //// Step the simulation.
     app_state
         .pipeline
@@ -126,10 +86,7 @@ pub fn step_simulation_legacy(
     for _ in 0..app_state.num_substeps {
         queue.encode(&mut encoder, timings.timestamps.as_mut());
     }
-    physics
-        .data
-        .poses_staging
-        .copy_from(&mut encoder, physics.data.bodies.poses());
+    physics.data.queue_step_readback(&mut encoder);
 
     // physics
     //     .data
@@ -172,49 +129,20 @@ pub fn step_simulation_legacy(
     //        Currently, this means there won’t be any two-ways coupling on wasm.
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let new_poses =
-            futures::executor::block_on(physics.data.poses_staging.read(device)).unwrap();
-
-        // println!("Impulses: {:?}", new_poses[8]);
-
-        for (i, coupling) in physics.data.coupling().iter().enumerate() {
-            let rb = &mut physics.rapier_data.bodies[coupling.body];
-            if rb.is_dynamic() {
-                let interpolator = RigidBodyPosition {
-                    position: *rb.position(),
-                    #[cfg(feature = "dim2")]
-                    next_position: new_poses[i].similarity.isometry,
-                    #[cfg(feature = "dim3")]
-                    next_position: new_poses[i].isometry,
-                };
-                let vel = interpolator.interpolate_velocity(
-                    1.0 / (physics.rapier_data.params.dt / divisor),
-                    &rb.mass_properties().local_mprops.local_com,
-                );
-                rb.set_linvel(vel.linvel, true);
-                rb.set_angvel(vel.angvel, true);
-                // println!("dvel: {:?}", vel.linvel - vel_before);
-            }
+        if let Err(e) = futures::executor::block_on(physics.data.apply_rigid_body_feedback(
+            device,
+            &mut physics.rapier_data.bodies,
+            physics.rapier_data.params.dt,
+        )) {
+            println!("Failed to read back the rigid bodies: {e}");
+        }
+        // Make room for the particles that spread out during this step.
+        if let Err(e) = futures::executor::block_on(physics.data.grow_grid_if_needed(device)) {
+            println!("Failed to grow the grid: {e}");
         }
     }
 
-    let mut params = physics.rapier_data.params;
-    params.dt /= divisor;
-    physics.rapier_data.physics_pipeline.step(
-        &nalgebra::zero(),
-        &params, // physics.rapier_data.params,
-        &mut physics.rapier_data.islands,
-        &mut physics.rapier_data.broad_phase,
-        &mut physics.rapier_data.narrow_phase,
-        &mut physics.rapier_data.bodies,
-        &mut physics.rapier_data.colliders,
-        &mut physics.rapier_data.impulse_joints,
-        &mut physics.rapier_data.multibody_joints,
-        &mut physics.rapier_data.ccd_solver,
-        None,
-        &(),
-        &(),
-    );
+    physics.rapier_data.step();
 
     if let Some(timestamps) = std::mem::take(&mut timings.timestamps) {
         let timings_snd = timings_channel.snd.clone();
@@ -11,7 +11,7 @@ use wgsparkl::solver::SimulationParams;
 pub fn update_ui(
     mut commands: Commands,
     mut ui_context: EguiContexts,
-    physics: ResMut<PhysicsContext>,
+    mut physics: ResMut<PhysicsContext>,
     mut app_state: ResMut<AppState>,
     scenes: Res<SceneInits>,
     timings: Res<Timestamps>,
@@ -39,7 +39,7 @@ pub fn update_ui(
         egui::ComboBox::from_label("render mode")
             .selected_text(RenderMode::from_u32(app_state.render_config.mode).text())
             .show_ui(ui, |ui| {
-                for i in 0..6 {
+                for i in 0..7 {
                     changed = ui
                         .selectable_value(
                             &mut app_state.render_config.mode,
@@ -92,14 +92,13 @@ pub fn update_ui(
             let new_params = SimulationParams {
                 gravity: gravity * app_state.gravity_factor,
                 dt: (1.0 / 60.0) / (app_state.num_substeps as f32),
-                #[cfg(feature = "dim2")]
-                padding: 0.0,
+                ..physics.data.sim_params.cpu_params
             };
-            queue.write_buffer(
-                physics.data.sim_params.params.buffer(),
-                0,
-                bytemuck::bytes_of(&new_params),
-            );
+            physics
+                .data
+                .sim_params
+                .update(&queue, new_params)
+                .expect("the domain is unchanged");
             queue.submit([]);
         }
 